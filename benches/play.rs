@@ -0,0 +1,51 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use durak_rt::game::cards::Deck;
+use durak_rt::game::game::{Game, GameLogic, ObservableSnapshot};
+use durak_rt::game::player::{Player, RandomPlayer};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Play one seeded game between two random players and return the rewards.
+fn play_seeded(seed: u64) -> (f32, f32) {
+    let mut players = [
+        RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(seed)))),
+        RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(seed + 1)))),
+    ];
+    let mut game = Game::new_seeded(seed);
+    while !game.is_over() {
+        let acting_player = game.game_state().acting_player;
+        let ObservableSnapshot {
+            state,
+            actions,
+            history,
+        } = game.snapshot(acting_player);
+        let action = players[acting_player as usize]
+            .choose_action(state, actions, history)
+            .unwrap();
+        game.step(action).unwrap();
+    }
+    game.get_rewards()
+}
+
+fn bench_random_games(c: &mut Criterion) {
+    c.bench_function("random game", |b| {
+        let mut seed = 0;
+        b.iter(|| {
+            seed += 1;
+            black_box(play_seeded(seed))
+        })
+    });
+}
+
+fn bench_shuffle(c: &mut Criterion) {
+    c.bench_function("seeded shuffle", |b| {
+        let mut rng = StdRng::seed_from_u64(0);
+        b.iter(|| {
+            let mut deck = Deck::new(6);
+            deck.shuffle_with(&mut rng);
+            black_box(deck)
+        })
+    });
+}
+
+criterion_group!(benches, bench_random_games, bench_shuffle);
+criterion_main!(benches);
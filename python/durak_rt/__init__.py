@@ -21,19 +21,29 @@ Example:
 
 from .rust import (
     GameEnv,
+    StateHandle,
     GamePlayer,
+    RandomPlayer,
     ObservableGameState,
     ActionList,
     Card,
     ObservableGameHistory,
+    GameConfig,
+    Game,
+    ENCODING_VERSION,
 )
 
 __version__ = "0.1.0"
 __all__ = [
     "GameEnv",
+    "StateHandle",
     "GamePlayer",
+    "RandomPlayer",
     "ObservableGameState",
     "ActionList",
     "Card",
     "ObservableGameHistory",
+    "GameConfig",
+    "Game",
+    "ENCODING_VERSION",
 ]
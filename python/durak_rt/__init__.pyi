@@ -7,6 +7,8 @@ from .rust import (
     ActionList,
     Card,
     ObservableGameHistory,
+    GameConfig,
+    Game,
 )
 
 __all__ = [
@@ -16,4 +18,6 @@ __all__ = [
     "ActionList",
     "Card",
     "ObservableGameHistory",
+    "GameConfig",
+    "Game",
 ]
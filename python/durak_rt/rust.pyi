@@ -4,9 +4,11 @@ and whatnot to the classes
 """
 
 from os import PathLike
-from typing import List, Tuple
+from typing import Any, Dict, List, Optional, Tuple
 import numpy as np
 
+ENCODING_VERSION: int
+
 class Card:
     rank: int
     suit: int
@@ -60,9 +62,34 @@ class ObservableGameState:
         """Returns the defender"""
         ...
 
+    @property
+    def undefended_count(self) -> int:
+        """Returns the number of attacks on the table still waiting to be beaten"""
+        ...
+
     @property
     def cards_in_opp_hand(self) -> int:
-        """Returns the number of cards in opponent's hand"""
+        """Returns the number of cards in opponent's hand, or the lowest count of its bucket if the config buckets it"""
+        ...
+
+    @property
+    def bout(self) -> int:
+        """Returns the number of bouts resolved so far"""
+        ...
+
+    @property
+    def table(self) -> List[Tuple[Card, Optional[Card]]]:
+        """Returns the table as (attack, defense) pairs in laying order, defense None while undefended"""
+        ...
+
+    @property
+    def face_up_card(self) -> Optional[Card]:
+        """Returns the trump card lying face up under the deck, None once it has been drawn"""
+        ...
+
+    @property
+    def opponent_hand(self) -> Optional[List[Card]]:
+        """Returns the opponent's hand in open-hands (cheat/debug) mode, otherwise None"""
         ...
 
     def __repr__(self) -> str:
@@ -77,6 +104,14 @@ class ObservableGameState:
         """Converts the game state to a numpy array"""
         ...
 
+    def to_numpy_f32(self) -> np.ndarray:
+        """Converts the game state to a float32 numpy array with counts scaled into [0, 1]"""
+        ...
+
+    def hidden_entropy(self) -> float:
+        """Returns how much is unknown about the opponent's hand, in bits"""
+        ...
+
 class ActionList:
     @property
     def actions(self) -> List[str]:
@@ -97,7 +132,9 @@ class ActionList:
 
     def __repr__(self) -> str: ...
     def __str__(self) -> str: ...
-    def __getitem__(self, index: int) -> str: ...
+    def __getitem__(self, index: int) -> str:
+        """Returns the action at index, counting from the end if negative; raises IndexError if out of range"""
+        ...
 
 class GamePlayer:
     """Base class for game players. Subclasses must implement choose_action."""
@@ -127,21 +164,180 @@ class GamePlayer:
         """
         ...
 
+class RandomPlayer(GamePlayer):
+    """Picks a uniformly random legal action, reproducibly when seeded. GameEnv's default opponent."""
+
+    def __init__(self, seed: Optional[int] = None) -> None: ...
+    def choose_action(
+        self,
+        state: ObservableGameState,
+        actions: ActionList,
+        history: Optional[ObservableGameHistory] = None,
+    ) -> int:
+        """Returns the index of a uniformly random action in the actions list"""
+        ...
+
+class GameConfig:
+    """Game setup. Games built from a config that cannot be dealt raise ValueError."""
+
+    def __init__(
+        self,
+        lowest_rank: int = 6,
+        hand_size: int = 6,
+        refill_order: str = "attacker_first",
+        defense_order: str = "in_order",
+        max_attacks_per_bout: Optional[int] = None,
+        no_trump_lead: bool = False,
+        reveal_opponent: bool = False,
+        ranks: Optional[List[int]] = None,
+        hand_encoding: str = "full",
+        next_attacker: str = "defender",
+        recall: Optional[int] = None,
+        opponent_count_bucket: Optional[int] = None,
+    ) -> None: ...
+    @property
+    def lowest_rank(self) -> int:
+        """Returns the lowest rank in the deck"""
+        ...
+    @property
+    def ranks(self) -> List[int]:
+        """Returns the ranks in the deck in ascending order"""
+        ...
+    @property
+    def hand_size(self) -> int:
+        """Returns the number of cards each player is refilled up to"""
+        ...
+    @property
+    def refill_order(self) -> str:
+        """Returns who draws first after a bout: attacker_first, defender_first or seat_order"""
+        ...
+    @property
+    def defense_order(self) -> str:
+        """Returns whether attacks are beaten in_order or in any_order"""
+        ...
+    @property
+    def next_attacker(self) -> str:
+        """Returns who attacks after a bout is beaten off: defender (standard) or attacker"""
+        ...
+    @property
+    def max_attacks_per_bout(self) -> Optional[int]:
+        """Returns the cap on attacks per bout, None under the standard rules"""
+        ...
+    @property
+    def no_trump_lead(self) -> bool:
+        """Returns whether opening a bout with a trump is forbidden while the deck has cards"""
+        ...
+    @property
+    def reveal_opponent(self) -> bool:
+        """Returns whether observations include the opponent's hand (cheat/debug mode)"""
+        ...
+    @property
+    def hand_encoding(self) -> str:
+        """Returns how observations encode the player's hand: full (bitmap) or aggregate (counts)"""
+        ...
+    @property
+    def recall(self) -> Optional[int]:
+        """Returns how many of the latest states players get as history, None for the whole game"""
+        ...
+    @property
+    def opponent_count_bucket(self) -> Optional[int]:
+        """Returns the width of the buckets the opponent's hand size is reported in, None for exact counts"""
+        ...
+
+class Game:
+    """Fully observable game for tests and debugging. Exposes both hands."""
+
+    def __init__(self, config: Optional[GameConfig] = None) -> None: ...
+    @staticmethod
+    def new_seeded(seed: int, config: Optional[GameConfig] = None) -> Game: ...
+    @property
+    def acting_player(self) -> int:
+        """Returns the player whose turn it is"""
+        ...
+
+    def legal_actions(self) -> ActionList: ...
+    def step(self, action_index: int) -> None:
+        """Takes the action at the given index of legal_actions()"""
+        ...
+
+    def observe(self, player: int) -> ObservableGameState: ...
+    def is_over(self) -> bool: ...
+    def get_rewards(self) -> Tuple[float, float]: ...
+    def get_winner(self) -> Optional[int]: ...
+    def debug_hands(self) -> Tuple[List[Card], List[Card]]:
+        """Returns both players' full hands (debug only)"""
+        ...
+    def trajectory(self, player: int) -> Tuple[np.ndarray, np.ndarray]:
+        """Returns the (T, state_dim) observations and (T,) action indices of a player's decisions"""
+        ...
+
+class StateHandle:
+    """An opaque snapshot of a GameEnv's game, from GameEnv.clone_state"""
+
 class GameEnv:
     @classmethod
     def from_file(cls, file_path: PathLike) -> GameEnv: ...
-    def __init__(self, player: GamePlayer):
-        """Play against a random player"""
+    def __init__(
+        self,
+        player: GamePlayer,
+        player2: Optional[GamePlayer] = None,
+        seed: Optional[int] = None,
+        config: Optional[GameConfig] = None,
+        win_reward: float = 1.0,
+        step_penalty: float = 0.0,
+        hand_lead_weight: float = 0.0,
+    ):
+        """Play against a random player. The reward arguments shape what step returns"""
+        ...
+
+    @property
+    def config(self) -> GameConfig:
+        """Returns the configuration used for every reset"""
+        ...
+
+    def reset(
+        self, seed: Optional[int] = None, config: Optional[GameConfig] = None
+    ) -> ObservableGameState:
+        """Starts a new game with the stored (or overriding) configuration"""
+        ...
+
+    def legal_actions(self) -> ActionList:
+        """Returns the acting player's legal actions; step takes an index into them"""
+        ...
+
+    def clone_state(self) -> StateHandle:
+        """Snapshots the game, hidden cards and deck order included (a cheat-capable API for search)"""
+        ...
+    def restore_state(self, handle: StateHandle) -> ObservableGameState:
+        """Puts the game back to a snapshot and returns the acting player's view of it"""
         ...
 
     def play(self) -> Tuple[float, float]: ...
+    def trajectory(self, player: int) -> Tuple[np.ndarray, np.ndarray]:
+        """Returns the (T, state_dim) observations and (T,) action indices of a player's decisions"""
+        ...
+    def render(self, mode: str = "unicode") -> str:
+        """Returns a readable board of the current game as seen by player 1, with suit
+        symbols ("unicode") or letters ("ascii")"""
+        ...
     def save_game(self, file_path: PathLike) -> None: ...
+    def action_space(self) -> Dict[str, Any]:
+        """Returns the action space for gym-style wrappers: {"type": "discrete", "n": num_actions()}"""
+        ...
+    def observation_space(self) -> Dict[str, Any]:
+        """Returns the observation space for gym-style wrappers: {"type": "box", "shape": state_shape(), "low": 0, "high": 255}"""
+        ...
     @staticmethod
-    def state_shape() -> np._AnyShapeT:
-        """Returns the shape of the game state as a numpy array"""
+    def state_shape(config: Optional[GameConfig] = None) -> np._AnyShapeT:
+        """Returns the shape of the game state as a numpy array, under config if given"""
         ...
 
     @staticmethod
     def num_actions() -> int:
         """Returns the number of possible actions"""
         ...
+
+    @staticmethod
+    def encoding_version() -> int:
+        """Returns the version of the observation layout, same as ENCODING_VERSION"""
+        ...
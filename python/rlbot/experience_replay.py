@@ -4,17 +4,18 @@ This module contains the Experience Replay class for the Durak game.
 
 from os import PathLike
 from pathlib import Path
-from durak_rt import GameEnv, GamePlayer, ObservableGameState, ActionList
+from durak_rt import ENCODING_VERSION, GameEnv, GamePlayer, ObservableGameState, ActionList
 import numpy as np
-from typing import List, Optional
+from typing import List, Optional, Tuple
 
 
 class ExperienceReplay:
-    def __init__(self, save_dir: PathLike):
+    def __init__(self, save_dir: PathLike, hand_encoding: str = "full"):
         self.save_dir = Path(save_dir)
+        # The GameConfig hand_encoding the observations were made with; it changes the layout
+        self.hand_encoding = hand_encoding
         self.save_dir.mkdir(parents=True, exist_ok=True)
         self.experience_file = self.save_dir / "experience.npz"
-        self.experience_file.touch()
 
     def save_experience(
         self,
@@ -22,10 +23,27 @@ class ExperienceReplay:
             Tuple[ObservableGameState, ActionList, float, ObservableGameState]
         ],
     ):
-        with np.load(self.experience_file, allow_pickle=True) as data:
-            data["experience"] = experience
-        np.savez(self.experience_file, experience=experience)
+        # Store the layout version and hand encoding so stale or differently encoded
+        # experience is not mixed with new observations
+        np.savez(
+            self.experience_file,
+            experience=np.array(experience, dtype=object),
+            encoding_version=ENCODING_VERSION,
+            hand_encoding=self.hand_encoding,
+        )
 
     def load_experience(self):
         with np.load(self.experience_file, allow_pickle=True) as data:
+            version = int(data["encoding_version"]) if "encoding_version" in data else None
+            if version != ENCODING_VERSION:
+                raise ValueError(
+                    f"Experience was saved with encoding version {version}, "
+                    f"but this build uses version {ENCODING_VERSION}"
+                )
+            hand_encoding = str(data["hand_encoding"]) if "hand_encoding" in data else None
+            if hand_encoding != self.hand_encoding:
+                raise ValueError(
+                    f"Experience was saved with hand encoding {hand_encoding}, "
+                    f"but this replay uses {self.hand_encoding}"
+                )
             return data["experience"]
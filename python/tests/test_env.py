@@ -0,0 +1,183 @@
+"""Tests for the GameEnv Python bindings."""
+
+import pytest
+
+from durak_rt import Game, GameConfig, GameEnv, GamePlayer, RandomPlayer
+
+
+class FirstActionPlayer(GamePlayer):
+    def choose_action(self, state, actions, history=None):
+        return 0
+
+
+def test_reset_keeps_config():
+    env = GameEnv(FirstActionPlayer(), config=GameConfig(lowest_rank=10))
+    for _ in range(5):
+        state = env.reset()
+        # 20 cards minus two dealt hands of 6
+        assert state.deck_size == 8
+        assert env.config.lowest_rank == 10
+
+
+def test_reset_config_override():
+    env = GameEnv(FirstActionPlayer(), config=GameConfig(lowest_rank=10))
+    state = env.reset(config=GameConfig())
+    assert state.deck_size == 24
+    assert env.reset().deck_size == 24
+
+
+def test_invalid_config_raises():
+    with pytest.raises(ValueError):
+        GameEnv(FirstActionPlayer(), config=GameConfig(hand_size=20))
+    with pytest.raises(ValueError):
+        GameConfig(lowest_rank=5)
+    env = GameEnv(FirstActionPlayer())
+    with pytest.raises(ValueError):
+        env.reset(config=GameConfig(hand_size=0))
+    assert env.config.hand_size == 6
+
+
+def test_player_without_choose_action_raises():
+    with pytest.raises(TypeError, match="choose_action"):
+        GameEnv(object())
+
+    class Lazy(GamePlayer):
+        pass
+
+    with pytest.raises(TypeError, match="choose_action"):
+        GameEnv(FirstActionPlayer(), player2=Lazy())
+
+
+def test_legal_actions_match_current_state():
+    env = GameEnv(FirstActionPlayer(), seed=5)
+    game = Game.new_seeded(5)
+    for _ in range(4):
+        indices = env.legal_actions().to_indices()
+        assert indices == game.legal_actions().to_indices()
+        assert all(0 <= i < GameEnv.num_actions() for i in indices)
+        env.step(0)
+        game.step(0)
+
+
+def test_render_shows_trump():
+    env = GameEnv(FirstActionPlayer(), seed=3)
+    trump = env.reset(seed=3).visible_card
+    assert "Trump:" in env.render()
+
+    rank = {11: "J", 12: "Q", 13: "K", 14: "A"}.get(trump.rank, str(trump.rank))
+    assert f"Trump: {rank}{'SHDC'[trump.suit]}" in env.render(mode="ascii")
+    with pytest.raises(ValueError):
+        env.render(mode="html")
+
+
+def test_spaces_match_env():
+    env = GameEnv(FirstActionPlayer())
+    action_space = env.action_space()
+    assert action_space["type"] == "discrete"
+    assert action_space["n"] == GameEnv.num_actions()
+    observation_space = env.observation_space()
+    assert observation_space["type"] == "box"
+    assert list(observation_space["shape"]) == list(GameEnv.state_shape())
+    assert (observation_space["low"], observation_space["high"]) == (0, 255)
+
+
+def test_aggregate_hand_encoding_shrinks_state():
+    config = GameConfig(hand_encoding="aggregate")
+    full = GameEnv.state_shape()
+    aggregate = GameEnv.state_shape(config)
+    assert aggregate[0] < full[0]
+    env = GameEnv(FirstActionPlayer(), config=config)
+    assert env.reset().to_numpy().shape[0] == aggregate[0]
+    assert list(env.observation_space()["shape"]) == list(aggregate)
+
+
+def test_seeded_random_players_agree():
+    first, second = RandomPlayer(seed=11), RandomPlayer(seed=11)
+    game = Game.new_seeded(11)
+    while not game.is_over():
+        state, actions = game.observe(game.acting_player), game.legal_actions()
+        choice = first.choose_action(state, actions)
+        assert second.choose_action(state, actions) == choice
+        assert 0 <= choice < len(actions)
+        game.step(choice)
+
+
+class RecallProbe(GamePlayer):
+    def __init__(self):
+        self.longest = 0
+
+    def choose_action(self, state, actions, history=None):
+        self.longest = max(self.longest, len(history.history))
+        return 0
+
+
+def test_recall_limits_history():
+    probe = RecallProbe()
+    env = GameEnv(probe, seed=2, config=GameConfig(recall=1))
+    env.play()
+    assert probe.longest == 1
+
+
+def test_step_penalty_favors_short_games():
+    # Player1's total reward in games where it made the final move, by the game's result
+    totals = {}
+    for seed in range(30):
+        env = GameEnv(FirstActionPlayer(), seed=seed, step_penalty=0.01)
+        env.reset(seed=seed)
+        total, steps, done = 0.0, 0, False
+        while not done:
+            _, reward, done, info = env.step(0)
+            if info["acting_player"] == 0:
+                total += reward
+                steps += 1
+        if info["acting_player"] == 0:
+            totals.setdefault(env.get_rewards()[0], []).append((steps, total))
+
+    compared = 0
+    for games in totals.values():
+        for steps, total in games:
+            for other_steps, other_total in games:
+                if steps > other_steps:
+                    assert total < other_total
+                    compared += 1
+    assert compared > 0
+
+
+def test_action_list_negative_index():
+    actions = Game.new_seeded(3).legal_actions()
+    n = len(actions)
+    assert actions[-1].action == actions[n - 1].action
+    assert actions[-n].action == actions[0].action
+    for index in (n, -n - 1):
+        with pytest.raises(IndexError):
+            actions[index]
+
+
+def test_restore_state_replays_continuation():
+    env = GameEnv(FirstActionPlayer(), seed=6)
+    env.reset(seed=6)
+    env.step(0)
+    handle = env.clone_state()
+
+    def continuation():
+        observations = []
+        for _ in range(8):
+            if env.is_done():
+                break
+            state, _, _, _ = env.step(len(env.legal_actions()) - 1)
+            observations.append(state.to_numpy().tolist())
+        return observations
+
+    first = continuation()
+    restored = env.restore_state(handle)
+    assert restored.to_numpy().tolist() == env.get_state().to_numpy().tolist()
+    assert continuation() == first
+
+
+def test_opponent_count_bucket_reports_bucket():
+    game = Game.new_seeded(4, config=GameConfig(opponent_count_bucket=4))
+    # Both players were dealt 6 cards, which is in the bucket of 4 to 7
+    assert game.observe(0).cards_in_opp_hand == 4
+    assert GameConfig().opponent_count_bucket is None
+    with pytest.raises(ValueError):
+        GameConfig(opponent_count_bucket=0)
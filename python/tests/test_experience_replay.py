@@ -0,0 +1,41 @@
+"""Tests for saving and loading experience with the observation encoding version."""
+
+import sys
+from pathlib import Path
+
+import numpy as np
+import pytest
+
+from durak_rt import ENCODING_VERSION, GameEnv
+
+sys.path.insert(0, str(Path(__file__).resolve().parents[1]))
+from rlbot.experience_replay import ExperienceReplay  # noqa: E402
+
+
+def test_state_shape_matches_encoding_version():
+    assert GameEnv.encoding_version() == ENCODING_VERSION
+    assert len(GameEnv.state_shape()) == 1
+
+
+def test_experience_round_trip(tmp_path):
+    replay = ExperienceReplay(tmp_path)
+    replay.save_experience([(1, 2, 0.5, 3)])
+    assert replay.load_experience().tolist() == [[1, 2, 0.5, 3]]
+
+
+def test_mismatched_encoding_version_raises(tmp_path):
+    replay = ExperienceReplay(tmp_path)
+    np.savez(
+        replay.experience_file,
+        experience=np.array([(1, 2, 0.5, 3)], dtype=object),
+        encoding_version=ENCODING_VERSION + 1,
+    )
+    with pytest.raises(ValueError):
+        replay.load_experience()
+
+
+def test_mismatched_hand_encoding_raises(tmp_path):
+    ExperienceReplay(tmp_path, hand_encoding="aggregate").save_experience([(1, 2, 0.5, 3)])
+    assert len(ExperienceReplay(tmp_path, hand_encoding="aggregate").load_experience()) == 1
+    with pytest.raises(ValueError):
+        ExperienceReplay(tmp_path).load_experience()
@@ -0,0 +1,50 @@
+"""Tests for the fully observable Game debug wrapper."""
+
+from durak_rt import Game
+
+
+def play_first_actions(game):
+    """Play a scripted game that always takes the first legal action."""
+    steps = 0
+    while not game.is_over():
+        game.step(0)
+        steps += 1
+    return steps
+
+
+def test_scripted_game_reaches_known_outcome():
+    game = Game.new_seeded(42)
+    other = Game.new_seeded(42)
+    hands = [[repr(c) for c in hand] for hand in game.debug_hands()]
+    assert hands == [[repr(c) for c in hand] for hand in other.debug_hands()]
+
+    steps = play_first_actions(game)
+    assert steps == play_first_actions(other)
+    assert game.is_over()
+    assert game.get_rewards() == other.get_rewards()
+
+    rewards = game.get_rewards()
+    winner = game.get_winner()
+    if winner is None:
+        assert rewards == (0.0, 0.0)
+    else:
+        assert rewards[winner] == 1.0
+        assert len(game.debug_hands()[winner]) == 0
+
+
+def test_observe_matches_debug_hands():
+    game = Game.new_seeded(3)
+    hand1, hand2 = game.debug_hands()
+    assert len(game.observe(0).player_hand) == len(hand1)
+    assert len(game.observe(1).player_hand) == len(hand2)
+    assert game.observe(0).cards_in_opp_hand == len(hand2)
+
+
+def test_trajectory_aligns_observations_and_actions():
+    game = Game.new_seeded(7)
+    play_first_actions(game)
+    for player in (0, 1):
+        observations, actions = game.trajectory(player)
+        assert observations.ndim == 2
+        assert observations.shape[0] == actions.shape[0]
+        assert observations.shape[0] > 0
@@ -1,31 +1,51 @@
-use axum::Router;
-use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
 
-use durak_rt::server::api::create_api_router;
+use durak_rt::server::api::{create_api_router_with_state, nest_api, AppState, DEFAULT_API_PREFIX};
+use durak_rt::server::game_session::{expire_sessions_every, SessionTtl};
+use durak_rt::server::rate_limit::RateLimitConfig;
+use durak_rt::server::seeds::DealSeeds;
 use durak_rt::server::GameSessions;
 
 #[tokio::main]
 async fn main() {
     // Initialize game sessions storage
-    let sessions: GameSessions = Arc::new(RwLock::new(HashMap::new()));
+    let sessions = GameSessions::default();
 
-    // Create API router
-    let api_router = create_api_router(sessions);
+    // Forfeit idle players and drop finished games in the background
+    tokio::spawn(expire_sessions_every(
+        sessions.clone(),
+        SessionTtl::default(),
+        Duration::from_secs(30),
+    ));
+
+    // Create API router, under DURAK_API_PREFIX when running behind a proxy
+    let api_prefix =
+        std::env::var("DURAK_API_PREFIX").unwrap_or_else(|_| DEFAULT_API_PREFIX.to_string());
+    let mut state = AppState::new(sessions, RateLimitConfig::default());
+    // Deal every game from DURAK_MASTER_SEED, if set, to make a whole run reproducible
+    if let Ok(seed) = std::env::var("DURAK_MASTER_SEED") {
+        let seed = seed
+            .parse()
+            .expect("DURAK_MASTER_SEED must be a 64-bit unsigned number");
+        state.deal_seeds = Arc::new(DealSeeds::from_master(seed));
+    }
+    let api_router = nest_api(&api_prefix, create_api_router_with_state(state));
 
     // Create main router with static file serving and CORS
-    let app = Router::new()
-        .nest("/api", api_router)
+    let app = api_router
         .nest_service("/", ServeDir::new("webapp"))
         .layer(CorsLayer::permissive());
 
     // Start server
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    println!("Server running on http://{}", addr);
+    println!(
+        "Server running on http://{} (API under {})",
+        addr, api_prefix
+    );
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
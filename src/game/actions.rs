@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use super::cards::Card;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Action {
     StopAttack,
     Take,
@@ -36,7 +38,7 @@ impl From<u8> for Action {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct ActionList(pub Vec<Action>);
 
 #[allow(dead_code)]
@@ -60,6 +62,12 @@ impl ActionList {
         bitmap
     }
 
+    /// Whether the player may end their attack rather than play a card. False when they
+    /// must act: opening a bout with cards in hand, or defending.
+    pub fn is_voluntary_stop_available(&self) -> bool {
+        self.0.contains(&Action::StopAttack)
+    }
+
     #[allow(dead_code)]
     pub fn from_bitmap(bitmap: Vec<bool>) -> Self {
         let actions = bitmap
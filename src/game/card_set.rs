@@ -0,0 +1,188 @@
+use super::cards::{Card, Hand, Suit, NUM_CARDS};
+
+/// A set of cards as a bitmap over card indices, for search code where set operations on
+/// hands are hot. Iteration yields cards in index order, the same canonical order as the
+/// legal actions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CardSet(pub u128);
+
+/// The bits of one suit's nine cards, before shifting to the suit's position.
+const SUIT_BITS: u128 = (1 << 9) - 1;
+
+impl CardSet {
+    pub const EMPTY: CardSet = CardSet(0);
+
+    /// Every card of a deck starting at `lowest_rank`.
+    pub fn full(lowest_rank: u8) -> CardSet {
+        CardSet::of_ranks(&(lowest_rank..15).collect::<Vec<u8>>())
+    }
+
+    /// Every card of the given ranks.
+    pub fn of_ranks(ranks: &[u8]) -> CardSet {
+        ranks
+            .iter()
+            .copied()
+            .map(CardSet::rank)
+            .fold(CardSet::EMPTY, CardSet::union)
+    }
+
+    /// All cards of `suit`.
+    pub fn suit(suit: Suit) -> CardSet {
+        CardSet(SUIT_BITS << (u8::from(suit) as usize * 9))
+    }
+
+    /// All cards of `rank`, in every suit.
+    pub fn rank(rank: u8) -> CardSet {
+        [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs]
+            .into_iter()
+            .map(|suit| CardSet::single(Card { suit, rank }))
+            .fold(CardSet::EMPTY, CardSet::union)
+    }
+
+    /// The cards that can cover `attack` when `trump` is trumps.
+    pub fn beating(attack: Card, trump: Suit) -> CardSet {
+        let below_or_equal = (1u128 << (usize::from(attack) + 1)) - 1;
+        let higher = CardSet::suit(attack.suit).0 & !below_or_equal;
+        let trumps = match attack.is_trump(trump) {
+            true => 0,
+            false => CardSet::suit(trump).0,
+        };
+        CardSet(higher | trumps)
+    }
+
+    pub fn single(card: Card) -> CardSet {
+        CardSet(1 << usize::from(card))
+    }
+
+    pub fn insert(&mut self, card: Card) {
+        self.0 |= CardSet::single(card).0;
+    }
+
+    pub fn remove(&mut self, card: Card) {
+        self.0 &= !CardSet::single(card).0;
+    }
+
+    pub fn contains(&self, card: Card) -> bool {
+        self.0 & CardSet::single(card).0 != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(self, other: CardSet) -> CardSet {
+        CardSet(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: CardSet) -> CardSet {
+        CardSet(self.0 & other.0)
+    }
+
+    pub fn difference(self, other: CardSet) -> CardSet {
+        CardSet(self.0 & !other.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Card> {
+        let bits = self.0;
+        (0..NUM_CARDS)
+            .filter(move |i| bits & (1 << i) != 0)
+            .map(Card::from)
+    }
+}
+
+impl FromIterator<Card> for CardSet {
+    fn from_iter<I: IntoIterator<Item = Card>>(iter: I) -> Self {
+        let mut set = CardSet::EMPTY;
+        for card in iter {
+            set.insert(card);
+        }
+        set
+    }
+}
+
+impl From<&Hand> for CardSet {
+    fn from(hand: &Hand) -> Self {
+        hand.0.iter().copied().collect()
+    }
+}
+
+impl From<CardSet> for Hand {
+    fn from(set: CardSet) -> Self {
+        set.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::cards::Deck;
+
+    #[test]
+    fn test_hand_round_trip() {
+        let mut cards = Deck::new(6).cards;
+        cards.truncate(11);
+        let hand = Hand(cards.iter().rev().copied().collect());
+        let set = CardSet::from(&hand);
+        assert_eq!(set.len(), 11);
+
+        let mut sorted = hand.0.clone();
+        sorted.sort_by_key(|card| usize::from(*card));
+        assert_eq!(Hand::from(set).0, sorted);
+    }
+
+    #[test]
+    fn test_set_ops_match_vec_equivalents() {
+        let deck = Deck::new(6).cards;
+        let a: Vec<Card> = deck.iter().step_by(2).copied().collect();
+        let b: Vec<Card> = deck.iter().step_by(3).copied().collect();
+        let (sa, sb): (CardSet, CardSet) =
+            (a.iter().copied().collect(), b.iter().copied().collect());
+        let in_order = |cards: Vec<Card>| -> Vec<Card> {
+            let mut cards = cards;
+            cards.sort_by_key(|card| usize::from(*card));
+            cards
+        };
+
+        let both: Vec<Card> = a.iter().filter(|c| b.contains(c)).copied().collect();
+        let only_a: Vec<Card> = a.iter().filter(|c| !b.contains(c)).copied().collect();
+        assert_eq!(
+            sa.intersection(sb).iter().collect::<Vec<_>>(),
+            in_order(both)
+        );
+        assert_eq!(
+            sa.difference(sb).iter().collect::<Vec<_>>(),
+            in_order(only_a)
+        );
+        assert_eq!(CardSet::full(6).len(), 36);
+        assert_eq!(CardSet::full(10).len(), 20);
+
+        let mut set = sa;
+        let card = b[1];
+        set.insert(card);
+        assert!(set.contains(card));
+        set.remove(card);
+        assert!(!set.contains(card));
+    }
+
+    #[test]
+    fn test_beating_matches_rules() {
+        let deck = Deck::new(6).cards;
+        let trump = Suit::Diamonds;
+        for attack in deck.iter() {
+            let expected: CardSet = deck
+                .iter()
+                .filter(|card| match (card.suit == trump, attack.suit == trump) {
+                    (true, false) => true,
+                    (false, true) => false,
+                    _ => card.suit == attack.suit && card.rank > attack.rank,
+                })
+                .copied()
+                .collect();
+            assert_eq!(CardSet::beating(*attack, trump), expected, "{:?}", attack);
+        }
+    }
+}
@@ -1,12 +1,16 @@
 use core::fmt;
+use std::{collections::HashSet, str::FromStr};
 
 use ndarray::Array1;
-use rand::seq::SliceRandom;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 
 use super::utils::{indices_to_bitmap, indices_to_bitmap_as_array1};
 
-#[derive(Clone, Copy, PartialEq, Debug, Eq, Ord, PartialOrd, Serialize, Deserialize)]
+/// Number of distinct cards in the full 36-card deck, which is the size of every card encoding.
+pub const NUM_CARDS: usize = 36;
+
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum Suit {
     Spades,
     Hearts,
@@ -14,6 +18,46 @@ pub enum Suit {
     Clubs,
 }
 
+/// The color a suit is printed in.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, Serialize, Deserialize)]
+pub enum Color {
+    Red,
+    Black,
+}
+
+impl Suit {
+    pub fn is_red(&self) -> bool {
+        matches!(self, Suit::Hearts | Suit::Diamonds)
+    }
+
+    pub fn color(&self) -> Color {
+        match self.is_red() {
+            true => Color::Red,
+            false => Color::Black,
+        }
+    }
+
+    /// The suit's name, e.g. `"Spades"`, as `FromStr` parses it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Suit::Spades => "Spades",
+            Suit::Hearts => "Hearts",
+            Suit::Diamonds => "Diamonds",
+            Suit::Clubs => "Clubs",
+        }
+    }
+
+    /// The suit's symbol, e.g. `"♠"`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Suit::Spades => "♠",
+            Suit::Hearts => "♥",
+            Suit::Diamonds => "♦",
+            Suit::Clubs => "♣",
+        }
+    }
+}
+
 impl From<Suit> for u8 {
     fn from(value: Suit) -> Self {
         match value {
@@ -37,12 +81,118 @@ impl From<u8> for Suit {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
+impl FromStr for Suit {
+    type Err = String;
+
+    /// Parse a suit from its name as written by `Debug`, e.g. `"Hearts"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs]
+            .into_iter()
+            .find(|suit| suit.name() == s)
+            .ok_or_else(|| format!("Unknown suit {:?}", s))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct Card {
     pub suit: Suit,
     pub rank: u8,
 }
 
+impl Card {
+    /// A card of `rank` (6 to 14 for the ace) in `suit`. Panics on any other rank.
+    pub fn new(suit: Suit, rank: u8) -> Card {
+        assert!((6..=14).contains(&rank), "Rank {} is outside 6..=14", rank);
+        Card { suit, rank }
+    }
+
+    /// The rank as printed on the card: the number, or `J`, `Q`, `K` or `A`.
+    pub fn rank_name(&self) -> &'static str {
+        match self.rank {
+            6 => "6",
+            7 => "7",
+            8 => "8",
+            9 => "9",
+            10 => "10",
+            11 => "J",
+            12 => "Q",
+            13 => "K",
+            14 => "A",
+            _ => "?",
+        }
+    }
+
+    /// The suit's name, e.g. `"Spades"`.
+    pub fn suit_name(&self) -> &'static str {
+        self.suit.name()
+    }
+
+    /// The card in words, e.g. `"Queen of Spades"`.
+    pub fn full_name(&self) -> String {
+        let rank = match self.rank {
+            6 => "Six",
+            7 => "Seven",
+            8 => "Eight",
+            9 => "Nine",
+            10 => "Ten",
+            11 => "Jack",
+            12 => "Queen",
+            13 => "King",
+            14 => "Ace",
+            _ => "Unknown",
+        };
+        format!("{} of {}", rank, self.suit_name())
+    }
+
+    /// Whether the card is of the `trump` suit.
+    pub fn is_trump(&self, trump: Suit) -> bool {
+        self.suit == trump
+    }
+}
+
+/// Why a card could not be built from a suit and rank.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CardError {
+    /// The rank is outside 6..=14.
+    Rank(u8),
+    /// The suit number is outside 0..=3, see `Suit::from(u8)`.
+    Suit(u8),
+}
+
+impl fmt::Display for CardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CardError::Rank(rank) => write!(f, "Rank {} is outside 6..=14", rank),
+            CardError::Suit(suit) => write!(f, "Suit number {} is outside 0..=3", suit),
+        }
+    }
+}
+
+impl std::error::Error for CardError {}
+
+impl TryFrom<(Suit, u8)> for Card {
+    type Error = CardError;
+
+    fn try_from((suit, rank): (Suit, u8)) -> Result<Self, Self::Error> {
+        match rank {
+            6..=14 => Ok(Card { suit, rank }),
+            _ => Err(CardError::Rank(rank)),
+        }
+    }
+}
+
+/// From a suit number as in `Suit::from(u8)` and a rank.
+impl TryFrom<(u8, u8)> for Card {
+    type Error = CardError;
+
+    fn try_from((suit, rank): (u8, u8)) -> Result<Self, Self::Error> {
+        match suit {
+            0..=3 => Card::try_from((Suit::from(suit), rank)),
+            _ => Err(CardError::Suit(suit)),
+        }
+    }
+}
+
 impl From<Card> for usize {
     fn from(value: Card) -> Self {
         usize::from(u8::from(value.suit) * 9 + value.rank - 6)
@@ -65,21 +215,14 @@ impl From<usize> for Card {
 
 impl fmt::Debug for Card {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let suit = match self.suit {
-            Suit::Spades => "♠",
-            Suit::Hearts => "♥",
-            Suit::Diamonds => "♦",
-            Suit::Clubs => "♣",
-        };
-        let rstr = self.rank.to_string();
-        let rank = match self.rank {
-            11 => "J",
-            12 => "Q",
-            13 => "K",
-            14 => "A",
-            _ => rstr.as_str(),
-        };
-        write!(f, "{}{}", rank, suit)
+        write!(f, "{}{}", self.rank_name(), self.suit.symbol())
+    }
+}
+
+/// The same short form as `Debug`, e.g. `Q♠`.
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
     }
 }
 
@@ -96,6 +239,25 @@ impl PartialEq for Hand {
     }
 }
 
+impl FromIterator<Card> for Hand {
+    fn from_iter<I: IntoIterator<Item = Card>>(iter: I) -> Self {
+        Hand(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Card> for Hand {
+    fn extend<I: IntoIterator<Item = Card>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl Hand {
+    /// The hand's trumps and its other cards, each in hand order.
+    pub fn split_trumps(&self, trump: Suit) -> (Vec<Card>, Vec<Card>) {
+        self.0.iter().partition(|card| card.is_trump(trump))
+    }
+}
+
 impl Into<Vec<u8>> for Hand {
     fn into(self) -> Vec<u8> {
         indices_to_bitmap(
@@ -143,28 +305,62 @@ impl fmt::Debug for Deck {
 
 impl Deck {
     pub fn new(lowest_rank: u8) -> Deck {
+        Deck::from_rank_set(&(lowest_rank..15).collect::<Vec<u8>>())
+    }
+
+    /// A deck of the given ranks in all four suits, e.g. without the tens. The ranks need
+    /// not be contiguous but must lie within 6..=14.
+    pub fn from_rank_set(ranks: &[u8]) -> Deck {
         let mut cards = Vec::new();
         for suit in [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs].iter() {
-            for rank in lowest_rank..15 {
+            for rank in ranks.iter().copied() {
                 cards.push(Card {
                     suit: suit.clone(),
                     rank,
                 });
             }
         }
+        // Encodings always cover the full deck so that smaller decks share the same layout;
+        // every card keeps its slot however the ranks are trimmed
         Deck {
-            num_total_cards: cards.len(),
+            num_total_cards: NUM_CARDS,
             cards,
         }
     }
 
+    /// Build a deck from the given cards, the last card being drawn first. Fails if a card
+    /// appears twice or has a rank outside the 36-card deck.
+    pub fn from_cards(cards: Vec<Card>) -> Result<Deck, String> {
+        let mut seen = HashSet::new();
+        for card in cards.iter() {
+            if !(6..=14).contains(&card.rank) {
+                return Err(format!("Invalid rank {} for {:?}", card.rank, card.suit));
+            }
+            if !seen.insert(*card) {
+                return Err(format!("Duplicate card {:?}", card));
+            }
+        }
+        Ok(cards.into_iter().collect())
+    }
+
     pub fn len(&self) -> usize {
         self.cards.len()
     }
 
     pub fn shuffle(&mut self) {
         let mut rng = rand::thread_rng();
-        self.cards.shuffle(&mut rng);
+        self.shuffle_with(&mut rng);
+    }
+
+    /// Shuffle with an explicit Fisher–Yates pass that only draws `u32`s from `rng`, so a
+    /// seeded rng gives the same deck on every platform regardless of how `rand` samples
+    /// ranges internally. For decks that must reproduce across `rand` releases too, seed a
+    /// portable rng such as `rand_chacha::ChaCha8Rng`; `StdRng` may change its algorithm.
+    pub fn shuffle_with(&mut self, rng: &mut dyn RngCore) {
+        for i in (1..self.cards.len()).rev() {
+            let j = uniform_below(rng, i as u32 + 1) as usize;
+            self.cards.swap(i, j);
+        }
     }
 
     fn draw(&mut self) -> Option<Card> {
@@ -172,19 +368,57 @@ impl Deck {
     }
 
     pub fn draw_n(&mut self, n: usize) -> Vec<Card> {
-        let mut drawn = Vec::new();
+        let mut drawn = Vec::with_capacity(n.min(self.len()));
+        self.draw_into(n, &mut drawn);
+        drawn
+    }
+
+    /// Draw up to `n` cards straight into `cards`, avoiding a temporary allocation.
+    pub fn draw_into(&mut self, n: usize, cards: &mut Vec<Card>) {
         for _ in 0..n {
             match self.draw() {
-                Some(card) => drawn.push(card),
+                Some(card) => cards.push(card),
                 None => break,
             }
         }
-        drawn
     }
 
     pub fn get_first(&self) -> Option<Card> {
         self.cards.first().cloned()
     }
+
+    /// The face-up trump card under the stock. Cards are drawn from the top, so it is the
+    /// last one drawn; `None` once it has been.
+    pub fn peek_bottom(&self) -> Option<&Card> {
+        self.cards.first()
+    }
+}
+
+/// A uniform number in `0..n`, rejecting the top partial range of `u32` to avoid modulo bias.
+fn uniform_below(rng: &mut dyn RngCore, n: u32) -> u32 {
+    let zone = u32::MAX - u32::MAX % n;
+    loop {
+        let x = rng.next_u32();
+        if x < zone {
+            return x % n;
+        }
+    }
+}
+
+/// Collects cards into a deck without checking them; see `Deck::from_cards` for the checked version.
+impl FromIterator<Card> for Deck {
+    fn from_iter<I: IntoIterator<Item = Card>>(iter: I) -> Self {
+        Deck {
+            cards: iter.into_iter().collect(),
+            num_total_cards: NUM_CARDS,
+        }
+    }
+}
+
+impl Extend<Card> for Deck {
+    fn extend<I: IntoIterator<Item = Card>>(&mut self, iter: I) {
+        self.cards.extend(iter)
+    }
 }
 
 // This does not preserve order for the deck state, but it is useful for the numpy array
@@ -208,3 +442,190 @@ impl Into<Array1<u8>> for Deck {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_card_names() {
+        for (rank, name) in (6..=10).zip(["6", "7", "8", "9", "10"]) {
+            let card = Card::new(Suit::Diamonds, rank);
+            assert_eq!(card.rank_name(), name);
+            assert_eq!(card.to_string(), format!("{}♦", name));
+        }
+        let faces = [
+            (11, "J", "Jack"),
+            (12, "Q", "Queen"),
+            (13, "K", "King"),
+            (14, "A", "Ace"),
+        ];
+        for (rank, short, word) in faces {
+            let card = Card::new(Suit::Spades, rank);
+            assert_eq!(card.rank_name(), short);
+            assert_eq!(card.suit_name(), "Spades");
+            assert_eq!(format!("{:?}", card), format!("{}♠", short));
+            assert_eq!(card.full_name(), format!("{} of Spades", word));
+        }
+        assert_eq!(Card::new(Suit::Hearts, 10).full_name(), "Ten of Hearts");
+    }
+
+    #[test]
+    fn test_split_trumps() {
+        let hand = Hand(vec![
+            Card::new(Suit::Hearts, 6),
+            Card::new(Suit::Clubs, 14),
+            Card::new(Suit::Hearts, 12),
+            Card::new(Suit::Spades, 9),
+            Card::new(Suit::Diamonds, 10),
+        ]);
+        let (trumps, others) = hand.split_trumps(Suit::Hearts);
+        assert_eq!(
+            trumps,
+            vec![Card::new(Suit::Hearts, 6), Card::new(Suit::Hearts, 12)]
+        );
+        assert_eq!(others.len(), 3);
+        assert!(others.iter().all(|card| card.suit != Suit::Hearts));
+        assert!(hand
+            .0
+            .iter()
+            .all(|c| trumps.contains(c) || others.contains(c)));
+
+        let (trumps, others) = Hand(Vec::new()).split_trumps(Suit::Clubs);
+        assert!(trumps.is_empty() && others.is_empty());
+    }
+
+    #[test]
+    fn test_card_constructors_match_literals() {
+        let queen = Card {
+            suit: Suit::Hearts,
+            rank: 12,
+        };
+        assert_eq!(Card::new(Suit::Hearts, 12), queen);
+        assert_eq!(Card::try_from((Suit::Hearts, 12)), Ok(queen));
+        assert_eq!(Card::try_from((1, 12)), Ok(queen));
+        let six: Result<Card, _> = (Suit::Clubs, 6).try_into();
+        assert_eq!(
+            six,
+            Ok(Card {
+                suit: Suit::Clubs,
+                rank: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_card_conversions_reject_bad_suits_and_ranks() {
+        assert_eq!(Card::try_from((Suit::Spades, 15)), Err(CardError::Rank(15)));
+        assert_eq!(Card::try_from((0, 5)), Err(CardError::Rank(5)));
+        assert_eq!(Card::try_from((4, 6)), Err(CardError::Suit(4)));
+        assert_eq!(
+            CardError::Suit(4).to_string(),
+            "Suit number 4 is outside 0..=3"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Rank 15 is outside 6..=14")]
+    fn test_card_new_rejects_bad_rank() {
+        Card::new(Suit::Spades, 15);
+    }
+
+    #[test]
+    fn test_rank_set_deck_round_trips_encodings() {
+        let ranks = [6, 7, 9, 12, 14];
+        let deck = Deck::from_rank_set(&ranks);
+        assert_eq!(deck.len(), 20);
+        for card in deck.cards.iter() {
+            assert!(ranks.contains(&card.rank));
+            assert_eq!(Card::from(usize::from(*card)), *card);
+        }
+
+        let bitmap: Array1<u8> = Hand(deck.cards.clone()).into();
+        assert_eq!(bitmap.len(), NUM_CARDS);
+        let decoded: Vec<Card> = bitmap
+            .iter()
+            .enumerate()
+            .filter(|(_, bit)| **bit == 1)
+            .map(|(i, _)| Card::from(i))
+            .collect();
+        assert_eq!(Hand(decoded), Hand(deck.cards));
+    }
+
+    #[test]
+    fn test_suit_colors() {
+        assert_eq!(Suit::Spades.color(), Color::Black);
+        assert_eq!(Suit::Hearts.color(), Color::Red);
+        assert_eq!(Suit::Diamonds.color(), Color::Red);
+        assert_eq!(Suit::Clubs.color(), Color::Black);
+        assert!(Suit::Hearts.is_red() && !Suit::Clubs.is_red());
+    }
+
+    #[test]
+    fn test_collect_into_hand() {
+        let cards = vec![
+            Card {
+                suit: Suit::Spades,
+                rank: 6,
+            },
+            Card {
+                suit: Suit::Hearts,
+                rank: 14,
+            },
+        ];
+        let hand: Hand = cards.iter().copied().collect();
+        assert_eq!(hand, Hand(cards.clone()));
+
+        let mut extended = Hand(vec![cards[0]]);
+        extended.extend([cards[1]]);
+        assert_eq!(extended, Hand(cards));
+    }
+
+    #[test]
+    fn test_peek_bottom_shows_trump_until_drawn() {
+        let mut deck = Deck::new(6);
+        deck.shuffle_with(&mut StdRng::seed_from_u64(3));
+        let trump = deck.cards[0];
+        while deck.len() > 1 {
+            assert_eq!(deck.peek_bottom(), Some(&trump));
+            deck.draw_n(5);
+        }
+        assert_eq!(deck.draw_n(1), vec![trump]);
+        assert_eq!(deck.peek_bottom(), None);
+    }
+
+    #[test]
+    fn test_deck_from_cards_is_checked() {
+        let six = Card {
+            suit: Suit::Spades,
+            rank: 6,
+        };
+        let deck = Deck::from_cards(vec![six]).unwrap();
+        assert_eq!(deck.cards, vec![six]);
+        assert!(Deck::from_cards(vec![six, six]).is_err());
+        assert!(Deck::from_cards(vec![Card {
+            suit: Suit::Clubs,
+            rank: 2,
+        }])
+        .is_err());
+    }
+
+    #[test]
+    fn test_seeded_shuffle_is_pinned() {
+        // The Fisher–Yates shuffle only depends on the rng's u32 stream
+        let mut deck = Deck::new(6);
+        deck.shuffle_with(&mut ChaCha8Rng::seed_from_u64(42));
+        let order: Vec<usize> = deck.cards.iter().map(|card| usize::from(*card)).collect();
+        // Golden values: if this changes, seeded shuffles no longer reproduce
+        assert_eq!(
+            order,
+            vec![
+                28, 25, 27, 11, 23, 7, 3, 15, 29, 13, 2, 30, 26, 35, 6, 19, 22, 4, 24, 21, 17, 8,
+                5, 20, 18, 16, 33, 31, 34, 10, 1, 32, 12, 14, 0, 9,
+            ]
+        );
+    }
+}
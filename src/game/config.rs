@@ -0,0 +1,342 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Setup parameters for a game. A game built from the default config is the standard
+/// 36-card game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    /// The lowest rank in the deck; every suit runs from this rank up to the ace (14).
+    pub lowest_rank: u8,
+    /// The ranks in the deck if not a run up to the ace, e.g. without the tens. Overrides
+    /// `lowest_rank` when set.
+    pub ranks: Option<Vec<u8>>,
+    /// The number of cards each player is dealt and refilled up to after every bout.
+    pub hand_size: usize,
+    /// Who draws first after a bout, which decides who ends up short when the deck runs out.
+    pub refill_order: RefillOrder,
+    /// Whether several attacks on the table must be beaten in the order they were laid.
+    pub defense_order: DefenseOrder,
+    /// Who attacks in the next bout after a bout has been beaten off. After a take the
+    /// attacker always attacks again.
+    pub next_attacker: NextAttacker,
+    /// At most this many attacks per bout, on top of the table cap, to bound bout length
+    /// in self-play. `None` plays by the standard rules.
+    pub max_attacks_per_bout: Option<usize>,
+    /// House rule: no opening a bout with a trump while the deck still has cards, unless
+    /// the attacker holds only trumps.
+    pub no_trump_lead: bool,
+    /// Cheat/debug mode: observations include the opponent's actual hand. Meant for
+    /// teaching and for checking search code against a perfect-information oracle, never
+    /// for agents being trained or evaluated.
+    pub reveal_opponent: bool,
+    /// How observations are encoded for agents.
+    pub observation: ObservationConfig,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            lowest_rank: 6,
+            ranks: None,
+            hand_size: 6,
+            refill_order: RefillOrder::default(),
+            defense_order: DefenseOrder::default(),
+            next_attacker: NextAttacker::default(),
+            max_attacks_per_bout: None,
+            no_trump_lead: false,
+            reveal_opponent: false,
+            observation: ObservationConfig::default(),
+        }
+    }
+}
+
+/// The order in which hands are refilled from the deck at the end of a bout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefillOrder {
+    /// The attacker draws first, then the defender. After a take, or when the defender
+    /// beats the whole table, that is the bout's attacker; when the attacker passes on a
+    /// beaten table the roles have already moved on, so the next bout's attacker draws
+    /// first.
+    #[default]
+    AttackerFirst,
+    /// The defender draws first, then the attacker, with the roles taken as for
+    /// `AttackerFirst`. With two players this is also seating order starting to the left
+    /// of the attacker.
+    DefenderFirst,
+    /// Player1 draws first, then Player2, whoever attacked.
+    SeatOrder,
+}
+
+/// The order in which the defender has to answer the attacks on the table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefenseOrder {
+    /// Attacks are beaten one after another in the order they were laid.
+    #[default]
+    InOrder,
+    /// Any undefended attack may be beaten next. `Defend(card)` does not name its target,
+    /// so the card covers the first attack it beats that still leaves the rest of the
+    /// table answerable from the defender's hand.
+    AnyOrder,
+}
+
+/// Who opens the next bout after the defender has beaten every attack.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NextAttacker {
+    /// The standard rule: the player who just defended attacks next.
+    #[default]
+    Defender,
+    /// The attacker keeps attacking and the same player defends again.
+    Attacker,
+}
+
+/// Options for the encoding of observations, see `ObservableGameState::to_numpy`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObservationConfig {
+    pub hand: HandEncoding,
+    /// How much of the game's history players are given with each decision.
+    pub history: ObservationPolicy,
+    /// How precisely players are told the size of the opponent's hand.
+    pub opponent_count: OpponentCount,
+}
+
+/// How `ObservableGameState::cards_in_opponent` reports the opponent's hand size, for
+/// experiments with noisier observations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpponentCount {
+    /// The exact number of cards.
+    #[default]
+    Exact,
+    /// Only the bucket of this width the count falls in, reported as its lowest count,
+    /// e.g. 3 for 3 to 5 cards with a width of 3.
+    Bucketed(u8),
+}
+
+impl OpponentCount {
+    /// The count players are told when the opponent holds `count` cards.
+    pub fn report(self, count: u8) -> u8 {
+        match self {
+            OpponentCount::Exact => count,
+            OpponentCount::Bucketed(width) => count / width.max(1) * width.max(1),
+        }
+    }
+
+    /// The smallest and largest hand size that could have been reported as `reported`.
+    pub fn range(self, reported: u8) -> (u8, u8) {
+        match self {
+            OpponentCount::Exact => (reported, reported),
+            OpponentCount::Bucketed(width) => (reported, reported.saturating_add(width.max(1) - 1)),
+        }
+    }
+}
+
+/// How much of the observed history is passed to a player's `choose_action`, for
+/// experiments with agents of imperfect recall.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationPolicy {
+    /// Every state since the start of the game.
+    #[default]
+    FullRecall,
+    /// Only the last `k` states, the current one included.
+    LastK(usize),
+}
+
+/// How the observing player's own hand is encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandEncoding {
+    /// A 36-card bitmap of the exact hand.
+    #[default]
+    Full,
+    /// Only counts: cards per suit (spades, hearts, diamonds, clubs), cards per rank (6 to
+    /// ace) and the number of trumps, 14 values in all. For experiments with agents that
+    /// cannot remember the exact hand.
+    Aggregate,
+}
+
+/// How a step is scored for reinforcement learning, see `Game::step_reward`. The default
+/// is the plain game result: 1 for a win, -1 for a loss and 0 for anything else.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RewardConfig {
+    /// The reward for winning. Losing gives its negative.
+    pub win: f32,
+    /// Taken off every step, so that shorter games score higher.
+    pub step_penalty: f32,
+    /// Weight of the change in the player's hand-size lead over a step, the opponent's
+    /// card count minus their own, to reward shedding cards before the game ends.
+    pub hand_lead_weight: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            win: 1.0,
+            step_penalty: 0.0,
+            hand_lead_weight: 0.0,
+        }
+    }
+}
+
+impl GameConfig {
+    /// The ranks in the deck this config plays with, in ascending order.
+    pub fn rank_set(&self) -> Vec<u8> {
+        match &self.ranks {
+            Some(ranks) => {
+                let mut ranks = ranks.clone();
+                ranks.sort_unstable();
+                ranks.dedup();
+                ranks
+            }
+            None => (self.lowest_rank.clamp(6, 15)..15).collect(),
+        }
+    }
+
+    /// The number of cards in the deck this config plays with.
+    pub fn deck_size(&self) -> usize {
+        4 * self.rank_set().len()
+    }
+
+    /// Check that a game can be dealt under this config.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        // Encodings only have room for the 36-card deck, so it cannot grow past that
+        if !(6..=14).contains(&self.lowest_rank) {
+            return Err(ConfigError::LowestRank(self.lowest_rank));
+        }
+        if let Some(rank) = self.rank_set().into_iter().find(|r| !(6..=14).contains(r)) {
+            return Err(ConfigError::Rank(rank));
+        }
+        if self.hand_size == 0 {
+            return Err(ConfigError::EmptyHand);
+        }
+        if self.max_attacks_per_bout == Some(0) {
+            return Err(ConfigError::NoAttacks);
+        }
+        if self.observation.opponent_count == OpponentCount::Bucketed(0) {
+            return Err(ConfigError::EmptyBucket);
+        }
+        // Both hands are dealt and one card must remain to show the trump
+        let needed = 2 * self.hand_size + 1;
+        if needed > self.deck_size() {
+            return Err(ConfigError::DeckTooSmall {
+                needed,
+                available: self.deck_size(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Why `GameConfig::validate` rejected a config.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigError {
+    /// The lowest rank is outside 6..=14.
+    LowestRank(u8),
+    /// One of the explicit ranks is outside 6..=14.
+    Rank(u8),
+    /// Players would be dealt no cards.
+    EmptyHand,
+    /// The attack cap would not even allow a bout's opening attack.
+    NoAttacks,
+    /// Opponent hand sizes would be reported in buckets of width zero.
+    EmptyBucket,
+    /// The deck cannot deal both hands and still show a trump card.
+    DeckTooSmall { needed: usize, available: usize },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::LowestRank(rank) => {
+                write!(f, "Lowest rank {} is outside 6..=14", rank)
+            }
+            ConfigError::Rank(rank) => write!(f, "Rank {} is outside 6..=14", rank),
+            ConfigError::EmptyHand => write!(f, "Hand size must be at least 1"),
+            ConfigError::NoAttacks => write!(f, "Attacks per bout must be at least 1"),
+            ConfigError::EmptyBucket => write!(f, "Opponent count buckets must be at least 1 wide"),
+            ConfigError::DeckTooSmall { needed, available } => write!(
+                f,
+                "Dealing needs {} cards but the deck only has {}",
+                needed, available
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert_eq!(GameConfig::default().validate(), Ok(()));
+        let short = GameConfig {
+            lowest_rank: 14,
+            hand_size: 1,
+            ..GameConfig::default()
+        };
+        assert_eq!(short.validate(), Ok(()));
+        let no_tens = GameConfig {
+            ranks: Some(vec![14, 6, 7, 8, 9, 11, 12, 13]),
+            ..GameConfig::default()
+        };
+        assert_eq!(no_tens.validate(), Ok(()));
+        assert_eq!(no_tens.rank_set(), vec![6, 7, 8, 9, 11, 12, 13, 14]);
+        assert_eq!(no_tens.deck_size(), 32);
+    }
+
+    #[test]
+    fn test_invalid_configs_are_rejected() {
+        let config = |lowest_rank, hand_size| GameConfig {
+            lowest_rank,
+            hand_size,
+            ..GameConfig::default()
+        };
+        assert_eq!(config(5, 6).validate(), Err(ConfigError::LowestRank(5)));
+        assert_eq!(config(15, 6).validate(), Err(ConfigError::LowestRank(15)));
+        assert_eq!(config(6, 0).validate(), Err(ConfigError::EmptyHand));
+        let ranks = |ranks: Vec<u8>| GameConfig {
+            ranks: Some(ranks),
+            ..GameConfig::default()
+        };
+        assert_eq!(ranks(vec![6, 9, 15]).validate(), Err(ConfigError::Rank(15)));
+        assert_eq!(
+            ranks(vec![6, 9, 9]).validate(),
+            Err(ConfigError::DeckTooSmall {
+                needed: 13,
+                available: 8
+            })
+        );
+        let no_attacks = GameConfig {
+            max_attacks_per_bout: Some(0),
+            ..GameConfig::default()
+        };
+        assert_eq!(no_attacks.validate(), Err(ConfigError::NoAttacks));
+        let mut empty_bucket = GameConfig::default();
+        empty_bucket.observation.opponent_count = OpponentCount::Bucketed(0);
+        assert_eq!(empty_bucket.validate(), Err(ConfigError::EmptyBucket));
+        assert_eq!(
+            config(6, 18).validate(),
+            Err(ConfigError::DeckTooSmall {
+                needed: 37,
+                available: 36
+            })
+        );
+        assert_eq!(
+            config(14, 2).validate(),
+            Err(ConfigError::DeckTooSmall {
+                needed: 5,
+                available: 4
+            })
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+
+use super::config::{
+    ConfigError, DefenseOrder, GameConfig, HandEncoding, NextAttacker, ObservationConfig,
+    ObservationPolicy, OpponentCount, RefillOrder,
+};
+
+/// Layout version of the packed bytes behind a deal code.
+const VERSION: u8 = 2;
+/// Version, seed, lowest rank, rank mask, hand size, flags, attack cap, recall, opponent
+/// count bucket, checksum.
+const PACKED_LEN: usize = 1 + 8 + 1 + 2 + 1 + 1 + 1 + 2 + 1 + 1;
+const ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Why `parse_deal_code` rejected a code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DealCodeError {
+    /// The code has a character outside `[0-9A-Za-z]`.
+    Character(char),
+    /// The code is too long or too short to be a deal code.
+    Length,
+    /// The code was mistyped, or was never a deal code.
+    Checksum,
+    /// The code comes from a newer version.
+    Version(u8),
+    /// The code describes a config that cannot be played.
+    Config(ConfigError),
+}
+
+impl fmt::Display for DealCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DealCodeError::Character(c) => write!(f, "Invalid character {:?} in deal code", c),
+            DealCodeError::Length => write!(f, "Deal code has the wrong length"),
+            DealCodeError::Checksum => write!(f, "Deal code checksum does not match"),
+            DealCodeError::Version(version) => {
+                write!(f, "Unknown deal code version {}", version)
+            }
+            DealCodeError::Config(e) => write!(f, "Deal code config is invalid: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DealCodeError {}
+
+/// A short base62 code from which `parse_deal_code` recovers `config` and `seed`, so that
+/// `Game::with_config_seeded` deals the same game again. Explicit ranks come back sorted,
+/// and attack caps above 255 or recall above 65534 states are capped, which plays the same.
+pub fn deal_code(config: &GameConfig, seed: u64) -> String {
+    let mut packed = Vec::with_capacity(PACKED_LEN);
+    packed.push(VERSION);
+    packed.extend_from_slice(&seed.to_le_bytes());
+    packed.push(config.lowest_rank);
+    let mask = match &config.ranks {
+        Some(ranks) => ranks
+            .iter()
+            .filter(|rank| (6..=14).contains(*rank))
+            .fold(0u16, |mask, rank| mask | 1 << (rank - 6)),
+        None => 0,
+    };
+    packed.extend_from_slice(&mask.to_le_bytes());
+    packed.push(config.hand_size.min(u8::MAX as usize) as u8);
+    let refill = match config.refill_order {
+        RefillOrder::AttackerFirst => 0,
+        RefillOrder::DefenderFirst => 1,
+        RefillOrder::SeatOrder => 2,
+    };
+    let flags = refill
+        | u8::from(config.defense_order == DefenseOrder::AnyOrder) << 2
+        | u8::from(config.next_attacker == NextAttacker::Attacker) << 3
+        | u8::from(config.no_trump_lead) << 4
+        | u8::from(config.reveal_opponent) << 5
+        | u8::from(config.observation.hand == HandEncoding::Aggregate) << 6;
+    packed.push(flags);
+    // Zero stands for no cap, since a cap of zero is invalid anyway
+    packed.push(match config.max_attacks_per_bout {
+        Some(cap) => cap.clamp(1, u8::MAX as usize) as u8,
+        None => 0,
+    });
+    let recall: u16 = match config.observation.history {
+        ObservationPolicy::FullRecall => 0,
+        ObservationPolicy::LastK(k) => k.min(u16::MAX as usize - 1) as u16 + 1,
+    };
+    packed.extend_from_slice(&recall.to_le_bytes());
+    // Zero stands for exact counts, since a bucket width of zero is invalid anyway
+    packed.push(match config.observation.opponent_count {
+        OpponentCount::Exact => 0,
+        OpponentCount::Bucketed(width) => width,
+    });
+    packed.push(checksum(&packed));
+    to_base62(&packed)
+}
+
+/// The config and seed packed into `code` by `deal_code`, after checking the code and the
+/// config it describes.
+pub fn parse_deal_code(code: &str) -> Result<(GameConfig, u64), DealCodeError> {
+    let packed = from_base62(code)?;
+    let (body, sum) = packed.split_at(PACKED_LEN - 1);
+    if checksum(body) != sum[0] {
+        return Err(DealCodeError::Checksum);
+    }
+    if body[0] != VERSION {
+        return Err(DealCodeError::Version(body[0]));
+    }
+    let seed = u64::from_le_bytes(body[1..9].try_into().unwrap());
+    let mask = u16::from_le_bytes([body[10], body[11]]);
+    let flags = body[13];
+    let recall = u16::from_le_bytes([body[15], body[16]]);
+    let config = GameConfig {
+        lowest_rank: body[9],
+        ranks: match mask {
+            0 => None,
+            mask => Some(
+                (6..=14)
+                    .filter(|rank| mask & 1 << (rank - 6) != 0)
+                    .collect(),
+            ),
+        },
+        hand_size: body[12] as usize,
+        refill_order: match flags & 0b11 {
+            0 => RefillOrder::AttackerFirst,
+            1 => RefillOrder::DefenderFirst,
+            _ => RefillOrder::SeatOrder,
+        },
+        defense_order: match flags & 1 << 2 {
+            0 => DefenseOrder::InOrder,
+            _ => DefenseOrder::AnyOrder,
+        },
+        next_attacker: match flags & 1 << 3 {
+            0 => NextAttacker::Defender,
+            _ => NextAttacker::Attacker,
+        },
+        no_trump_lead: flags & 1 << 4 != 0,
+        reveal_opponent: flags & 1 << 5 != 0,
+        max_attacks_per_bout: match body[14] {
+            0 => None,
+            cap => Some(cap as usize),
+        },
+        observation: ObservationConfig {
+            hand: match flags & 1 << 6 {
+                0 => HandEncoding::Full,
+                _ => HandEncoding::Aggregate,
+            },
+            history: match recall {
+                0 => ObservationPolicy::FullRecall,
+                k => ObservationPolicy::LastK(k as usize - 1),
+            },
+            opponent_count: match body[17] {
+                0 => OpponentCount::Exact,
+                width => OpponentCount::Bucketed(width),
+            },
+        },
+    };
+    config.validate().map_err(DealCodeError::Config)?;
+    Ok((config, seed))
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_mul(31).wrapping_add(*byte))
+}
+
+/// The bytes as one big-endian number in base 62.
+fn to_base62(bytes: &[u8]) -> String {
+    let mut number = bytes.to_vec();
+    let mut digits = Vec::new();
+    while number.iter().any(|byte| *byte != 0) {
+        let mut remainder = 0u32;
+        for byte in number.iter_mut() {
+            let value = remainder << 8 | *byte as u32;
+            *byte = (value / 62) as u8;
+            remainder = value % 62;
+        }
+        digits.push(ALPHABET[remainder as usize]);
+    }
+    digits.iter().rev().map(|digit| *digit as char).collect()
+}
+
+/// The inverse of `to_base62` for `PACKED_LEN` bytes.
+fn from_base62(code: &str) -> Result<Vec<u8>, DealCodeError> {
+    let mut number = vec![0u8; PACKED_LEN];
+    for c in code.chars() {
+        let digit = ALPHABET
+            .iter()
+            .position(|a| *a as char == c)
+            .ok_or(DealCodeError::Character(c))?;
+        let mut carry = digit as u32;
+        for byte in number.iter_mut().rev() {
+            let value = *byte as u32 * 62 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry != 0 {
+            return Err(DealCodeError::Length);
+        }
+    }
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::game::Game;
+
+    #[test]
+    fn test_deal_code_round_trips() {
+        let config = GameConfig {
+            ranks: Some(vec![6, 7, 8, 9, 11, 12, 13, 14]),
+            hand_size: 5,
+            refill_order: RefillOrder::SeatOrder,
+            defense_order: DefenseOrder::AnyOrder,
+            max_attacks_per_bout: Some(4),
+            no_trump_lead: true,
+            observation: ObservationConfig {
+                hand: HandEncoding::Aggregate,
+                history: ObservationPolicy::LastK(3),
+                opponent_count: OpponentCount::Bucketed(2),
+            },
+            ..GameConfig::default()
+        };
+        for (config, seed) in [(GameConfig::default(), 0), (config, u64::MAX - 7)] {
+            let code = deal_code(&config, seed);
+            assert!(code.len() <= 26, "{}", code);
+            assert_eq!(parse_deal_code(&code), Ok((config.clone(), seed)));
+
+            let (config, seed) = parse_deal_code(&code).unwrap();
+            let first = Game::with_config_seeded(config.clone(), seed).unwrap();
+            let second = Game::with_config_seeded(config, seed).unwrap();
+            assert_eq!(first.game_state(), second.game_state());
+        }
+    }
+
+    #[test]
+    fn test_bad_deal_codes_are_rejected() {
+        let code = deal_code(&GameConfig::default(), 42);
+        let mut typo = code.clone().into_bytes();
+        typo[10] = if typo[10] == b'a' { b'b' } else { b'a' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert_eq!(parse_deal_code(&typo), Err(DealCodeError::Checksum));
+        assert_eq!(
+            parse_deal_code(&format!("{}!", code)),
+            Err(DealCodeError::Character('!'))
+        );
+        assert_eq!(
+            parse_deal_code(&format!("{}00", code)),
+            Err(DealCodeError::Length)
+        );
+
+        let too_big = GameConfig {
+            hand_size: 30,
+            ..GameConfig::default()
+        };
+        assert!(matches!(
+            parse_deal_code(&deal_code(&too_big, 1)),
+            Err(DealCodeError::Config(ConfigError::DeckTooSmall { .. }))
+        ));
+    }
+}
@@ -0,0 +1,74 @@
+use rand::Rng;
+
+use super::{
+    cards::{Card, Deck, Hand},
+    config::{ConfigError, GameConfig},
+};
+
+/// The opening deal: the remaining deck, both hands and the visible trump card.
+pub type Deal = (Deck, Hand, Hand, Card);
+
+/// Produces the opening deal. Fails if there are not enough cards to deal both hands and
+/// still show a trump.
+pub trait Dealer {
+    fn deal(&mut self, config: &GameConfig) -> Result<Deal, ConfigError>;
+}
+
+/// The standard deal: shuffle a full deck with `rng` and deal each player a hand.
+pub struct ShuffleDealer<R: Rng> {
+    rng: R,
+}
+
+impl<R: Rng> ShuffleDealer<R> {
+    pub fn new(rng: R) -> Self {
+        Self { rng }
+    }
+}
+
+impl<R: Rng> Dealer for ShuffleDealer<R> {
+    fn deal(&mut self, config: &GameConfig) -> Result<Deal, ConfigError> {
+        let mut deck = Deck::from_rank_set(&config.rank_set());
+        deck.shuffle_with(&mut self.rng);
+        deal_from(deck, config)
+    }
+}
+
+/// Deals the given cards in order: the first hand goes to Player1, the next to Player2 and
+/// the rest form the deck, so the last card is the visible trump at its bottom.
+pub struct StackedDealer {
+    cards: Vec<Card>,
+}
+
+impl StackedDealer {
+    pub fn new(cards: Vec<Card>) -> Result<Self, String> {
+        // Check the cards up front so a bad stack fails here rather than mid-game
+        Deck::from_cards(cards.clone())?;
+        Ok(Self { cards })
+    }
+}
+
+impl Dealer for StackedDealer {
+    fn deal(&mut self, config: &GameConfig) -> Result<Deal, ConfigError> {
+        // The deck draws from the end of its list, so reverse the deal order
+        deal_from(self.cards.iter().rev().copied().collect(), config)
+    }
+}
+
+fn deal_from(mut deck: Deck, config: &GameConfig) -> Result<Deal, ConfigError> {
+    let needed = 2 * config.hand_size + 1;
+    if deck.len() < needed {
+        return Err(ConfigError::DeckTooSmall {
+            needed,
+            available: deck.len(),
+        });
+    }
+    // Hands rarely grow past the hand size, so reserve it up front
+    let mut hand1 = Hand(Vec::with_capacity(config.hand_size));
+    let mut hand2 = Hand(Vec::with_capacity(config.hand_size));
+    deck.draw_into(config.hand_size, &mut hand1.0);
+    deck.draw_into(config.hand_size, &mut hand2.0);
+    let visible_card = *deck
+        .peek_bottom()
+        .expect("checked above that a card is left");
+    Ok((deck, hand1, hand2, visible_card))
+}
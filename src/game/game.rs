@@ -1,24 +1,129 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use serde_json::to_writer_pretty;
 use std::{
     collections::HashSet,
+    fmt,
     fs::File,
     io::{BufReader, BufWriter},
     path::PathBuf,
+    sync::OnceLock,
     vec,
 };
 
-use crate::game::gamestate::ObservableGameHistory;
+use crate::game::gamestate::{ObservableGameHistory, ObservableGameState};
 
 use super::{
     actions::{Action, ActionList},
-    cards::{Card, Deck, Hand, Suit},
+    card_set::CardSet,
+    cards::{Card, Hand, Suit},
+    config::{
+        ConfigError, DefenseOrder, GameConfig, NextAttacker, ObservationPolicy, RefillOrder,
+        RewardConfig,
+    },
+    dealer::{Dealer, ShuffleDealer},
     gamestate::{GamePlayer, GameState},
+    mcts::determinize,
     player::{Player, RandomPlayer},
 };
 
+/// Playouts longer than this are cut off and scored as a draw.
+pub const MAX_ROLLOUT_STEPS: usize = 1000;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Game {
     pub history: Vec<GameState>,
-    pub game_state: GameState,
+    game_state: GameState,
+    pub actions: Vec<Action>,
+    /// Everything that happened in the game in order: player actions and the refills
+    /// in between them.
+    pub events: Vec<GameEvent>,
+    config: GameConfig,
+    /// The player who gave up, which ends the game in the other player's favour.
+    pub conceded: Option<GamePlayer>,
+    /// What happened in the most recently finished bout, if any.
+    pub last_bout: Option<BoutSummary>,
+    /// Legal actions for the current state, computed on first use. Everything that can
+    /// change legality (`step`, `concede`, `game_state_mut`, `config_mut`) clears it.
+    #[serde(skip)]
+    legal_actions_cache: OnceLock<ActionList>,
+}
+
+/// Something that happened in a game. Actions are chosen by players; refills are dealt
+/// by the game when a bout ends. A concession ends the game early.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    Action { player: GamePlayer, action: Action },
+    Refill { player: GamePlayer, count: usize },
+    Concede { player: GamePlayer },
+}
+
+/// What happened in a bout, recorded when it resolves.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoutSummary {
+    pub attacker: GamePlayer,
+    pub defender: GamePlayer,
+    /// The attacks in the order they were laid.
+    pub attacks: Vec<Card>,
+    /// The cards that beat them, `defenses[i]` covering `attacks[i]`.
+    pub defenses: Vec<Card>,
+    /// Whether the defender picked up the table rather than beating it off.
+    pub took: bool,
+    /// Who attacks in the next bout.
+    pub next_attacker: GamePlayer,
+}
+
+/// A replayable record of a game: the state it started from, the rules it was played
+/// under and every action taken.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameLog {
+    pub initial_state: GameState,
+    pub actions: Vec<Action>,
+    #[serde(default)]
+    pub config: GameConfig,
+    /// The full narrative of the game, including refills. Optional when loading a log.
+    #[serde(default)]
+    pub events: Vec<GameEvent>,
+}
+
+/// Everything a player needs to make a decision: what they can see, what they can do,
+/// and what they have seen so far.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObservableSnapshot {
+    pub state: ObservableGameState,
+    pub actions: ActionList,
+    pub history: ObservableGameHistory,
+}
+
+/// One of a player's decisions as a training transition, see `Game::experiences`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Experience {
+    pub state: ObservableGameState,
+    pub action: Action,
+    /// The player's reward if the action ended the game, otherwise zero.
+    pub reward: f32,
+    /// What the player saw right after the action.
+    pub next_state: ObservableGameState,
+    /// Whether the action ended the game.
+    pub done: bool,
+    /// The history the player was given along with `state`, for recurrent policies. Left
+    /// out unless asked for, since it makes each transition as long as the game so far.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<ObservableGameHistory>,
+}
+
+/// What kind of decision the acting player faces, see `Game::turn_kind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TurnKind {
+    /// The attacker must lead a card on an empty table.
+    Opening,
+    /// The attacker has no cards to lead but the deck is not empty, so may only stop.
+    Pass,
+    /// The attacker may add a card of a rank on the table, or stop.
+    FollowUp,
+    /// The defender must beat the undefended attacks or take the table.
+    Defense,
 }
 
 fn det_first_attacker(hand1: &Hand, hand2: &Hand, suit: Suit) -> GamePlayer {
@@ -54,11 +159,30 @@ fn det_first_attacker(hand1: &Hand, hand2: &Hand, suit: Suit) -> GamePlayer {
 
 impl Game {
     pub fn new() -> Self {
-        let mut deck = Deck::new(6);
-        deck.shuffle();
-        let hand1 = Hand(deck.draw_n(6));
-        let hand2 = Hand(deck.draw_n(6));
-        let visible_card = deck.get_first().unwrap();
+        Self::with_config(GameConfig::default()).expect("the default config is valid")
+    }
+
+    pub fn with_config(config: GameConfig) -> Result<Self, ConfigError> {
+        Self::with_dealer(config, &mut ShuffleDealer::new(rand::thread_rng()))
+    }
+
+    /// A standard game whose deal is fully determined by `seed`.
+    pub fn new_seeded(seed: u64) -> Self {
+        Self::with_config_seeded(GameConfig::default(), seed).expect("the default config is valid")
+    }
+
+    pub fn with_config_seeded(config: GameConfig, seed: u64) -> Result<Self, ConfigError> {
+        Self::with_dealer(config, &mut ShuffleDealer::new(StdRng::seed_from_u64(seed)))
+    }
+
+    /// A game whose opening deal comes from `dealer`, e.g. a `StackedDealer` to set up a
+    /// specific scenario. Fails if `config` cannot be dealt.
+    pub fn with_dealer<D: Dealer + ?Sized>(
+        config: GameConfig,
+        dealer: &mut D,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let (deck, hand1, hand2, visible_card) = dealer.deal(&config)?;
         let first_attacker = det_first_attacker(&hand1, &hand2, visible_card.suit);
         let game_state = GameState::new(
             deck,
@@ -73,11 +197,106 @@ impl Game {
             Vec::new(),
         );
 
-        let history = vec![game_state.clone()];
+        Ok(Self {
+            history: vec![game_state.clone()],
+            game_state,
+            actions: Vec::new(),
+            events: Vec::new(),
+            config,
+            conceded: None,
+            last_bout: None,
+            legal_actions_cache: OnceLock::new(),
+        })
+    }
+
+    /// Start a game from a state built by hand, rejecting it if any card of the configured
+    /// deck is duplicated or missing, or the trump is not the card under the deck.
+    pub fn try_from_state(game_state: GameState, config: GameConfig) -> Result<Self, String> {
+        game_state.validate_complete(&config.rank_set())?;
+        let mut game = Self::from_state(game_state);
+        game.config = config;
+        Ok(game)
+    }
 
+    /// Start a game from an arbitrary state. The state is taken as-is; use
+    /// `Game::try_from_state` if it comes from an untrusted source.
+    pub fn from_state(game_state: GameState) -> Self {
         Self {
+            history: vec![game_state.clone()],
             game_state,
-            history,
+            actions: Vec::new(),
+            events: Vec::new(),
+            config: GameConfig::default(),
+            conceded: None,
+            last_bout: None,
+            legal_actions_cache: OnceLock::new(),
+        }
+    }
+
+    /// `player` gives up, e.g. after leaving a game idle, and the other player wins. Does
+    /// nothing once the game is over.
+    pub fn concede(&mut self, player: GamePlayer) {
+        if self.is_over() {
+            return;
+        }
+        self.conceded = Some(player);
+        self.events.push(GameEvent::Concede { player });
+        self.invalidate_action_cache();
+    }
+
+    /// The log of this game, from its first recorded state through every action taken.
+    pub fn log(&self) -> GameLog {
+        GameLog {
+            initial_state: self.history[0].clone(),
+            actions: self.actions.clone(),
+            config: self.config.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    /// Replay a log from its initial state, checking that every action is legal at the
+    /// point it was taken, that the resulting state is still valid and, if the log records
+    /// events, that the replay produces the same ones.
+    pub fn validate_log(log: &GameLog) -> Result<Game, String> {
+        log.initial_state.validate()?;
+        let mut game = Game::from_state(log.initial_state.clone());
+        game.config = log.config.clone();
+        for (i, action) in log.actions.iter().enumerate() {
+            game.step(*action)
+                .map_err(|e| format!("Step {} ({:?}): {}", i, action, e))?;
+        }
+        game.game_state.validate()?;
+        if let Some(GameEvent::Concede { player }) = log.events.last() {
+            game.concede(*player);
+        }
+        if !log.events.is_empty() && log.events != game.events {
+            return Err(String::from("Replayed events do not match the log"));
+        }
+        Ok(game)
+    }
+
+    /// Replay a log and compare every state it passes through with `expected_states`, laid
+    /// out like `history`: the initial state, then the state after each action. States must
+    /// match exactly, deck order included. Returns the index of the first state that differs,
+    /// where an action that no longer applies or a list of the wrong length differs at the
+    /// first state one side is missing. For regression tests against stored golden states.
+    pub fn replay_compare(log: &GameLog, expected_states: &[GameState]) -> Result<(), usize> {
+        let mut game = Game::from_state(log.initial_state.clone());
+        game.config = log.config.clone();
+        for action in log.actions.iter() {
+            if game.step(*action).is_err() {
+                break;
+            }
+        }
+        let replayed = &game.history;
+        match replayed
+            .iter()
+            .zip(expected_states)
+            .position(|(state, expected)| state != expected)
+        {
+            Some(i) => Err(i),
+            None if replayed.len() == expected_states.len() => Ok(()),
+            None => Err(replayed.len().min(expected_states.len())),
         }
     }
 
@@ -90,6 +309,12 @@ impl Game {
         Self {
             game_state,
             history,
+            actions: Vec::new(),
+            events: Vec::new(),
+            config: GameConfig::default(),
+            conceded: None,
+            last_bout: None,
+            legal_actions_cache: OnceLock::new(),
         }
     }
 
@@ -115,26 +340,43 @@ impl Game {
     }
 
     /// This function should be called after a round of the game has ended and the cards on the table have been added to the defender's hand.
-    /// It refills the hands of the players up to 6 cards, starting with the player who will be attacking in the next round.
+    /// It refills the hands of the players up to the configured hand size in the configured refill order, taking the roles as they
+    /// stand when it is called, and moves the bout counter on.
     fn refill_hands(&mut self) {
-        let refill_order = match self.game_state.defending_player {
-            GamePlayer::Player2 => vec![GamePlayer::Player1, GamePlayer::Player2],
-            GamePlayer::Player1 => vec![GamePlayer::Player2, GamePlayer::Player1],
+        self.game_state.bout += 1;
+        let hand_size = self.config.hand_size;
+        let defender = self.game_state.defending_player;
+        let refill_order = match self.config.refill_order {
+            RefillOrder::AttackerFirst => [defender.other(), defender],
+            RefillOrder::DefenderFirst => [defender, defender.other()],
+            RefillOrder::SeatOrder => [GamePlayer::Player1, GamePlayer::Player2],
         };
         for player in refill_order.iter() {
-            let hand = match player {
-                GamePlayer::Player1 => &mut self.game_state.hand1,
-                GamePlayer::Player2 => &mut self.game_state.hand2,
+            let (hand, void_suits) = match player {
+                GamePlayer::Player1 => {
+                    (&mut self.game_state.hand1, &mut self.game_state.void_suits1)
+                }
+                GamePlayer::Player2 => {
+                    (&mut self.game_state.hand2, &mut self.game_state.void_suits2)
+                }
             };
-            let num_cards: i8 = 6 - hand.0.len() as i8;
-            if num_cards > 0 {
-                let mut new_cards = self.game_state.deck.draw_n(num_cards as usize);
-                hand.0.append(&mut new_cards);
+            let num_cards = hand_size.saturating_sub(hand.0.len());
+            let held = hand.0.len();
+            self.game_state.deck.draw_into(num_cards, &mut hand.0);
+            let count = hand.0.len() - held;
+            if count > 0 {
+                // Fresh cards from the deck may beat what the player could not beat before
+                void_suits.clear();
+                self.events.push(GameEvent::Refill {
+                    player: *player,
+                    count,
+                });
             }
         }
     }
 
     fn add_table_to_defender(&mut self) {
+        self.record_bout(true);
         // Temporarily take mutable references to the tables you want to modify.
         let defense_table = &mut self.game_state.defense_table;
         let attack_table = &mut self.game_state.attack_table;
@@ -147,11 +389,13 @@ impl Game {
 
         // Now, you can append the tables to the hand without violating Rust's borrowing rules,
         // because `hand`, `defense_table`, and `attack_table` are clearly separate mutable references.
+        hand.reserve(defense_table.len() + attack_table.len());
         hand.append(defense_table);
         hand.append(attack_table);
     }
 
     fn clear_table(&mut self) {
+        self.record_bout(false);
         self.game_state
             .graveyard
             .append(&mut self.game_state.attack_table);
@@ -161,10 +405,17 @@ impl Game {
     }
 
     fn handle_take(&mut self) {
+        // The defender gave up against the first undefended card; remember its suit
+        let suit = self.game_state.attack_table[self.game_state.defense_table.len()].suit;
+        let void_suits = match self.game_state.defending_player {
+            GamePlayer::Player1 => &mut self.game_state.void_suits1,
+            GamePlayer::Player2 => &mut self.game_state.void_suits2,
+        };
+        if !void_suits.contains(&suit) {
+            void_suits.push(suit);
+        }
         // check whether attacker can add more cards
-        let num_attack = self.game_state.attack_table.len() as u8;
-        let num_defend = self.game_state.defense_table.len() as u8;
-        if num_attack == 6 || (num_attack - num_defend) >= self.defender_hand().0.len() as u8 {
+        if !self.can_add_attack() {
             // here we need to give defender all cards, round is over
             self.add_table_to_defender();
             self.refill_hands();
@@ -184,16 +435,14 @@ impl Game {
             self.add_table_to_defender();
             // Refill the hands of the players
             self.refill_hands();
+        } else if self.game_state.num_undefended() == 0 {
+            // Clear the table
+            self.clear_table();
+            // Hand out the roles of the next bout, so its attacker draws first by default
+            self.start_bout_after_defense();
+            // Refill the hands of the players
+            self.refill_hands();
         } else {
-            // If there are no undefended cards on the table
-            if self.game_state.num_undefended() == 0 {
-                // Clear the table
-                self.clear_table();
-                // Switch the defending player
-                self.game_state.defending_player = self.game_state.defending_player.other();
-                // Refill the hands of the players
-                self.refill_hands();
-            }
             // Switch the acting player
             self.game_state.acting_player = self.game_state.acting_player.other();
         }
@@ -206,11 +455,17 @@ impl Game {
         // remove card from player hand
         let hand = self._attacker_hand();
         let index = hand.0.iter().position(|x| *x == card).unwrap();
-        hand.0.remove(index);
+        // Hands are unordered, so there is no need to shift the remaining cards
+        hand.0.swap_remove(index);
     }
 
     // Function to handle the defense action
     fn handle_defense(&mut self, card: Card) {
+        // Move the answered attack to the front of the undefended ones, so that
+        // `defense_table[i]` always covers `attack_table[i]`
+        let next = self.game_state.defense_table.len();
+        let target = next + self.defense_target(card);
+        self.game_state.attack_table.swap(next, target);
         // Add the card to the defense table
         self.game_state.defense_table.push(card);
         {
@@ -221,8 +476,8 @@ impl Game {
             };
             // Find the position of the card in the hand
             let index = hand.0.iter().position(|x| *x == card).unwrap();
-            // Remove the card from the hand
-            hand.0.remove(index);
+            // Remove the card from the hand; hands are unordered so swap_remove is enough
+            hand.0.swap_remove(index);
         }
         // If the defense table is full or the defender has no cards left
         if self.game_state.defense_table.len() == 6 || self.defender_hand().0.len() == 0 {
@@ -232,8 +487,8 @@ impl Game {
             self.refill_hands();
             // Reset the flag indicating that the defender has taken the cards
             self.game_state.defender_has_taken = false;
-            // Switch the defending player
-            self.game_state.defending_player = self.game_state.defending_player.other();
+            // Hand out the roles of the next bout
+            self.start_bout_after_defense();
         }
         // If there are no undefended cards on the table
         else if self.game_state.num_undefended() == 0 {
@@ -242,6 +497,118 @@ impl Game {
         }
     }
 
+    /// Who attacks after the current bout: the attacker again after a take, otherwise as
+    /// `config.next_attacker` says.
+    fn next_attacker(&self, took: bool) -> GamePlayer {
+        let defender = self.game_state.defending_player;
+        match (took, self.config.next_attacker) {
+            (false, NextAttacker::Defender) => defender,
+            (true, _) | (false, NextAttacker::Attacker) => defender.other(),
+        }
+    }
+
+    /// Set the roles for the bout after a successful defense and give the turn to the new
+    /// attacker.
+    fn start_bout_after_defense(&mut self) {
+        let attacker = self.next_attacker(false);
+        self.game_state.defending_player = attacker.other();
+        self.game_state.acting_player = attacker;
+    }
+
+    /// Keep a summary of the bout that is resolving, while its cards are still on the table.
+    fn record_bout(&mut self, took: bool) {
+        let defender = self.game_state.defending_player;
+        self.last_bout = Some(BoutSummary {
+            attacker: defender.other(),
+            defender,
+            attacks: self.game_state.attack_table.clone(),
+            defenses: self.game_state.defense_table.clone(),
+            took,
+            next_attacker: self.next_attacker(took),
+        });
+    }
+
+    /// Whether the attacker has played their last card with the deck gone and the defender
+    /// has yet to answer it. The game isn't decided until they do: beating the table with
+    /// their own last card leaves both players out at once, which is a draw, whatever the
+    /// refill order.
+    fn awaiting_last_defense(&self) -> bool {
+        let state = &self.game_state;
+        state.deck.len() == 0
+            && self.attacker_hand().0.is_empty()
+            && !self.defender_hand().0.is_empty()
+            && state.num_undefended() > 0
+            && !state.defender_has_taken
+    }
+
+    /// The number of cards in the current state, to compare with `expected_total`. A cheap
+    /// count; `GameState::validate_complete` checks which cards they are.
+    pub fn total_cards(&self) -> usize {
+        self.game_state.total_cards()
+    }
+
+    /// The number of cards the game is played with, from the config.
+    pub fn expected_total(&self) -> usize {
+        self.config.deck_size()
+    }
+
+    /// What kind of decision the acting player faces, or `None` once the game is over.
+    pub fn turn_kind(&self) -> Option<TurnKind> {
+        if self.is_over() {
+            return None;
+        }
+        let state = &self.game_state;
+        Some(if state.acting_player == state.defending_player {
+            TurnKind::Defense
+        } else if self.attacker_must_pass() {
+            TurnKind::Pass
+        } else if state.attack_table.is_empty() {
+            TurnKind::Opening
+        } else {
+            TurnKind::FollowUp
+        })
+    }
+
+    /// Whether the opening bout is still being played.
+    pub fn is_first_bout(&self) -> bool {
+        self.game_state.bout == 0
+    }
+
+    /// The ranks on the table, sorted. Follow-up attacks must match one of them.
+    pub fn table_ranks(&self) -> Vec<u8> {
+        let mut ranks: Vec<u8> = self.ranks().into_iter().collect();
+        ranks.sort_unstable();
+        ranks
+    }
+
+    /// The undefended attacks the defender may answer next, in table order.
+    fn answerable_attacks(&self) -> &[Card] {
+        let undefended = &self.game_state.attack_table[self.game_state.defense_table.len()..];
+        match self.config.defense_order {
+            DefenseOrder::InOrder => &undefended[..undefended.len().min(1)],
+            DefenseOrder::AnyOrder => undefended,
+        }
+    }
+
+    /// Which of the answerable attacks `card` covers, as an offset into them: the first one
+    /// it beats that leaves the remaining attacks answerable from the rest of the hand, or
+    /// just the first one it beats if there is no such attack.
+    fn defense_target(&self, card: Card) -> usize {
+        let attacks = self.answerable_attacks();
+        let tsuit = self.game_state.visible_card.suit;
+        let mut rest = self.defender_hand().0.clone();
+        rest.retain(|c| *c != card);
+        let beaten = || (0..attacks.len()).filter(|i| beats(&card, &attacks[*i], tsuit));
+        beaten()
+            .find(|i| {
+                let mut remaining = attacks.to_vec();
+                remaining.remove(*i);
+                covers_all(&remaining, &rest, tsuit)
+            })
+            .or_else(|| beaten().next())
+            .unwrap_or(0)
+    }
+
     fn ranks(&self) -> HashSet<u8> {
         let mut ranks = HashSet::new();
         for card in self.game_state.attack_table.iter() {
@@ -253,6 +620,90 @@ impl Game {
         ranks
     }
 
+    /// The number of legal actions in the current state, counted without building the
+    /// action list.
+    pub fn num_legal_actions(&self) -> usize {
+        if let Some(actions) = self.legal_actions_cache.get() {
+            return actions.0.len();
+        }
+        if self.conceded.is_some() {
+            return 0;
+        }
+        let state = &self.game_state;
+        if state.acting_player == state.defending_player {
+            let defenses = self
+                .answerable_attacks()
+                .iter()
+                .map(|attack| CardSet::beating(*attack, state.visible_card.suit))
+                .fold(CardSet::EMPTY, CardSet::union);
+            // Take is always available
+            return 1 + CardSet::from(self.defender_hand())
+                .intersection(defenses)
+                .len();
+        }
+        match state.attack_table.len() {
+            0 if self.attacker_must_pass() => 1,
+            0 if self.may_lead_trump() => self.attacker_hand().0.len(),
+            0 => CardSet::from(self.attacker_hand())
+                .difference(CardSet::suit(state.visible_card.suit))
+                .len(),
+            _ if !self.can_add_attack() => 1,
+            // StopAttack plus every card matching a rank on the table
+            _ => {
+                let ranks = state
+                    .attack_table
+                    .iter()
+                    .chain(state.defense_table.iter())
+                    .map(|card| CardSet::rank(card.rank))
+                    .fold(CardSet::EMPTY, CardSet::union);
+                1 + CardSet::from(self.attacker_hand())
+                    .intersection(ranks)
+                    .len()
+            }
+        }
+    }
+
+    /// Whether the attacker faces an empty table without cards but with a deck to draw from,
+    /// as in a hand-built state. They may only stop: the bout ends empty and the roles move
+    /// on after a refill.
+    fn attacker_must_pass(&self) -> bool {
+        self.game_state.attack_table.is_empty()
+            && self.attacker_hand().0.is_empty()
+            && self.game_state.deck.len() > 0
+    }
+
+    /// Whether the attacker may open a bout with a trump. Under `GameConfig::no_trump_lead`
+    /// only once the deck is empty, or if they hold nothing but trumps.
+    fn may_lead_trump(&self) -> bool {
+        let trump = self.game_state.visible_card.suit;
+        !self.config.no_trump_lead
+            || self.game_state.deck.len() == 0
+            || self.attacker_hand().split_trumps(trump).1.is_empty()
+    }
+
+    /// The maximum number of attack cards allowed on the table in a bout: five in the
+    /// opening bout, six after that.
+    fn attack_cap(&self) -> usize {
+        match self.is_first_bout() {
+            true => 5,
+            false => 6,
+        }
+    }
+
+    /// Whether the attacker may add another card to the table. Besides the table cap and
+    /// `GameConfig::max_attacks_per_bout`, every undefended card must still be answerable
+    /// by a card in the defender's hand, so the attacker can never pile on more than the
+    /// defender holds.
+    fn can_add_attack(&self) -> bool {
+        let attacks = self.game_state.attack_table.len();
+        attacks < self.attack_cap()
+            && self
+                .config
+                .max_attacks_per_bout
+                .is_none_or(|cap| attacks < cap)
+            && (self.game_state.num_undefended() as usize) < self.defender_hand().0.len()
+    }
+
     // This function determines the legal attack actions for the current game state
     fn legal_attacks(&self) -> Vec<Action> {
         // Initialize an empty vector to store the actions
@@ -260,19 +711,28 @@ impl Game {
         // Check the length of the attack table
         match self.game_state.attack_table.len() {
             // If the attack table is empty, all cards in the attacker's hand are legal attacks
-            0 => self
-                .attacker_hand()
-                .0
-                .iter()
-                // Map each card in the attacker's hand to an Attack action
-                .map(|card| Action::Attack(*card))
-                .collect(),
+            0 if self.attacker_must_pass() => vec![Action::StopAttack],
+            0 => {
+                let trump = self.game_state.visible_card.suit;
+                let lead_trump = self.may_lead_trump();
+                self.attacker_hand()
+                    .0
+                    .iter()
+                    .filter(|card| lead_trump || !card.is_trump(trump))
+                    // Map each card in the attacker's hand to an Attack action
+                    .map(|card| Action::Attack(*card))
+                    .collect()
+            }
             // If the attack table is not empty
             _ => {
-                // Get the ranks of the cards on the table
-                let ranks = self.ranks();
                 // Add the StopAttack action to the list of actions
                 actions.push(Action::StopAttack);
+                // Once the defender could not answer another card, only stopping is allowed
+                if !self.can_add_attack() {
+                    return actions;
+                }
+                // Get the ranks of the cards on the table
+                let ranks = self.ranks();
                 // Append the legal attack actions to the list of actions
                 actions.append(
                     &mut self
@@ -297,8 +757,8 @@ impl Game {
         let mut actions = Vec::new();
         // Add the Take action to the list of actions
         actions.push(Action::Take);
-        // Get the last attack from the attack table
-        let last_attack = self.game_state.attack_table[self.game_state.defense_table.len()];
+        // Get the attacks that may be answered now
+        let attacks = self.answerable_attacks();
         // Get the suit of the visible card
         let tsuit = self.game_state.visible_card.suit;
         // Initialize a vector to store the defense actions
@@ -306,28 +766,8 @@ impl Game {
             .defender_hand()
             .0
             .iter()
-            // Filter the cards in the defender's hand that can legally defend against the last attack
-            .filter(|card| match last_attack {
-                Card {
-                    suit: a_suit,
-                    rank: a_rank,
-                } if a_suit == tsuit => match card {
-                    Card {
-                        suit: d_suit,
-                        rank: d_rank,
-                    } if *d_suit == tsuit => *d_rank > a_rank,
-                    _ => false,
-                },
-                Card {
-                    suit: a_suit,
-                    rank: a_rank,
-                } => match card {
-                    Card {
-                        suit: d_suit,
-                        rank: d_rank,
-                    } => (*d_suit == tsuit) || (*d_suit == a_suit && *d_rank > a_rank),
-                },
-            })
+            // Filter the cards in the defender's hand that can legally defend against one of them
+            .filter(|card| attacks.iter().any(|attack| beats(card, attack, tsuit)))
             // Map each card to a Defend action
             .map(|i| Action::Defend(*i))
             // Collect the defense actions into a vector
@@ -338,14 +778,199 @@ impl Game {
         actions
     }
 
+    /// The state that `action` would lead to, leaving this game untouched. Refills draw from
+    /// the current deck, whose order is already fixed, so the result is deterministic.
+    pub fn peek(&self, action: Action) -> Result<GameState, StepError> {
+        let mut next = Game::from_state(self.game_state.clone());
+        next.config = self.config.clone();
+        next.step(action)?;
+        Ok(next.game_state)
+    }
+
+    /// A plain-text account of the game for people: the deal, every action numbered in
+    /// order with the refills in between, and the result. Shows both hands, so only share
+    /// it once the game is over.
+    pub fn transcript(&self) -> String {
+        let initial = &self.history[0];
+        let cards = |hand: &Hand| {
+            let mut cards = hand.0.clone();
+            cards.sort_by_key(|card| usize::from(*card));
+            cards
+                .iter()
+                .map(|card| format!("{:?}", card))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let mut lines = vec![
+            format!("Trump: {:?}", initial.visible_card),
+            format!("Player1: {}", cards(&initial.hand1)),
+            format!("Player2: {}", cards(&initial.hand2)),
+            String::new(),
+        ];
+        let mut moves = 0;
+        for event in self.events.iter() {
+            lines.push(match event {
+                GameEvent::Action { player, action } => {
+                    moves += 1;
+                    let action = match action {
+                        Action::Attack(card) => format!("attacks with {:?}", card),
+                        Action::Defend(card) => format!("beats with {:?}", card),
+                        Action::Take => String::from("takes"),
+                        Action::StopAttack => String::from("stops"),
+                    };
+                    format!("{}. {:?} {}", moves, player, action)
+                }
+                GameEvent::Refill { player, count } => format!("   {:?} draws {}", player, count),
+                GameEvent::Concede { player } => format!("   {:?} concedes", player),
+            });
+        }
+        lines.push(String::new());
+        lines.push(match (self.is_over(), self.get_winner()) {
+            (false, _) => String::from("Result: unfinished"),
+            (true, Some(winner)) => format!("Result: {:?} wins", winner),
+            (true, None) => String::from("Result: draw"),
+        });
+        lines.join("\n") + "\n"
+    }
+
+    /// Each player's hand size at every recorded state, from the first entry of `history` to
+    /// the current state, so there is one value more than there are recorded actions.
+    pub fn hand_size_series(&self) -> (Vec<usize>, Vec<usize>) {
+        self.history
+            .iter()
+            .map(|state| (state.hand1.0.len(), state.hand2.0.len()))
+            .unzip()
+    }
+
+    /// The decisions `player` made during the recorded part of this game, in order: what
+    /// they observed at each of their turns and the action they took.
+    pub fn decisions(&self, player: GamePlayer) -> Vec<(ObservableGameState, Action)> {
+        // `history` ends with the current state and holds one state before each action
+        let start = self.history.len() - 1 - self.actions.len();
+        self.history[start..]
+            .iter()
+            .zip(self.actions.iter())
+            .filter(|(state, _)| state.acting_player == player)
+            .map(|(state, action)| (self.view(state, player), *action))
+            .collect()
+    }
+
+    /// `player`'s decisions during the recorded part of this game as transitions, each with
+    /// the observed history that came with it if `with_history` is set.
+    pub fn experiences(&self, player: GamePlayer, with_history: bool) -> Vec<Experience> {
+        let start = self.history.len() - 1 - self.actions.len();
+        let last = self.history.len() - 1;
+        (start..last)
+            .filter(|i| self.history[*i].acting_player == player)
+            .map(|i| {
+                let done = i + 1 == last && self.is_over();
+                Experience {
+                    state: self.view(&self.history[i], player),
+                    action: self.actions[i - start],
+                    reward: if done { self.reward_for(player) } else { 0.0 },
+                    next_state: self.view(&self.history[i + 1], player),
+                    done,
+                    history: with_history.then(|| self.observed_history(i, player)),
+                }
+            })
+            .collect()
+    }
+
+    /// `player`'s view of the current state. In open-hands mode, see
+    /// `GameConfig::reveal_opponent`, it includes the opponent's hand.
+    pub fn observe(&self, player: GamePlayer) -> ObservableGameState {
+        self.view(&self.game_state, player)
+    }
+
+    /// `player`'s view of `state` as this game's config has players observe it.
+    fn view(&self, state: &GameState, player: GamePlayer) -> ObservableGameState {
+        let mut observed = state.observe_with(player, self.config.reveal_opponent);
+        observed.hand_encoding = self.config.observation.hand;
+        observed.opponent_count = self.config.observation.opponent_count;
+        observed.deck_ranks = self.config.rank_set();
+        observed.cards_in_opponent = observed.opponent_count.report(observed.cards_in_opponent);
+        observed
+    }
+
+    /// Bundle the observation, legal actions and observed history for `player`. The history
+    /// is cut down as `config.observation.history` says.
+    pub fn snapshot(&self, player: GamePlayer) -> ObservableSnapshot {
+        ObservableSnapshot {
+            state: self.observe(player),
+            actions: self.legal_actions(),
+            history: self.observed_history(self.history.len() - 1, player),
+        }
+    }
+
+    /// The history `player` is given at `history[upto]`, ending with that state and cut down
+    /// as `config.observation.history` says.
+    fn observed_history(&self, upto: usize, player: GamePlayer) -> ObservableGameHistory {
+        let seen = upto + 1;
+        let forgotten = match self.config.observation.history {
+            ObservationPolicy::FullRecall => 0,
+            ObservationPolicy::LastK(k) => seen.saturating_sub(k),
+        };
+        ObservableGameHistory(
+            self.history[forgotten..seen]
+                .iter()
+                .map(|x| self.view(x, player))
+                .collect(),
+        )
+    }
+
+    /// The legal actions for the current state. The list is computed once per state and
+    /// reused until the state or the rules change.
     pub fn legal_actions(&self) -> ActionList {
-        let actions = match (
+        self.legal_actions_cache
+            .get_or_init(|| self.compute_legal_actions())
+            .clone()
+    }
+
+    /// The current state.
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+
+    /// Mutable access to the current state, e.g. to set up a position by hand. Drops the
+    /// cached legal actions, which are recomputed on the next read.
+    pub fn game_state_mut(&mut self) -> &mut GameState {
+        self.invalidate_action_cache();
+        &mut self.game_state
+    }
+
+    /// The rules and observation settings this game is played with.
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    /// Mutable access to the config. Drops the cached legal actions, since rules such as
+    /// `max_attacks_per_bout` change what is legal.
+    pub fn config_mut(&mut self) -> &mut GameConfig {
+        self.invalidate_action_cache();
+        &mut self.config
+    }
+
+    fn invalidate_action_cache(&mut self) {
+        self.legal_actions_cache.take();
+    }
+
+    fn compute_legal_actions(&self) -> ActionList {
+        if self.conceded.is_some() {
+            return ActionList(Vec::new());
+        }
+        let mut actions = match (
             self.game_state.acting_player,
             self.game_state.defending_player,
         ) {
             (a, b) if a == b => self.legal_defenses(),
             _ => self.legal_attacks(),
         };
+        // Emit actions in a canonical order so that action indices do not depend on how the
+        // hands happen to be stored: StopAttack/Take first, then cards by their index
+        actions.sort_by_key(|action| match action {
+            Action::StopAttack | Action::Take => 0,
+            Action::Attack(card) | Action::Defend(card) => 1 + usize::from(*card),
+        });
         ActionList(actions)
     }
 
@@ -356,39 +981,271 @@ impl Game {
         to_writer_pretty(writer, &self.history).unwrap();
     }
 
+    /// `player`'s reward: 1.0 for a win, -1.0 for a loss and 0.0 for a draw or a game still
+    /// in progress. The same as their element of `get_rewards`.
+    pub fn reward_for(&self, player: GamePlayer) -> f32 {
+        let (player1, player2) = self.get_rewards();
+        match player {
+            GamePlayer::Player1 => player1,
+            GamePlayer::Player2 => player2,
+        }
+    }
+
+    /// `player`'s reward for the last step as `rewards` scores it: the game result scaled
+    /// by `rewards.win` once the game is over, less the step penalty, plus the weighted
+    /// change in `player`'s hand-size lead. Zero before the first step.
+    pub fn step_reward(&self, player: GamePlayer, rewards: &RewardConfig) -> f32 {
+        let [.., before, after] = self.history.as_slice() else {
+            return 0.0;
+        };
+        let lead = |state: &GameState| {
+            let (own, other) = match player {
+                GamePlayer::Player1 => (&state.hand1, &state.hand2),
+                GamePlayer::Player2 => (&state.hand2, &state.hand1),
+            };
+            other.0.len() as f32 - own.0.len() as f32
+        };
+        self.reward_for(player) * rewards.win - rewards.step_penalty
+            + rewards.hand_lead_weight * (lead(after) - lead(before))
+    }
+
+    /// Let `opponent` make its moves until it is `me`'s turn or the game is over, e.g. to
+    /// advance a single-player game to the human's next decision. Stops early if the
+    /// opponent has no move to make, and fails if it picks an illegal one.
+    pub fn play_until_turn(
+        &mut self,
+        me: GamePlayer,
+        opponent: &mut dyn Player,
+    ) -> Result<(), StepError> {
+        while self.game_state.acting_player != me && !self.is_over() {
+            let ObservableSnapshot {
+                state,
+                actions,
+                history,
+            } = self.snapshot(self.game_state.acting_player);
+            let Some(action) = opponent.choose_action(state, actions, history) else {
+                break;
+            };
+            self.step(action)?;
+        }
+        Ok(())
+    }
+
+    /// Step through `actions` in order, e.g. to rebuild a game from a saved move list. All
+    /// or nothing: if one is illegal the game is left as it was and the error carries that
+    /// action's index.
+    pub fn apply_actions(&mut self, actions: &[Action]) -> Result<(), (usize, StepError)> {
+        let (history_len, events_len) = (self.history.len(), self.events.len());
+        let last_bout = self.last_bout.clone();
+        for (index, action) in actions.iter().enumerate() {
+            if let Err(err) = self.step(*action) {
+                self.history.truncate(history_len);
+                self.actions.truncate(history_len - 1);
+                self.events.truncate(events_len);
+                self.last_bout = last_bout;
+                self.game_state = self.history[history_len - 1].clone();
+                self.invalidate_action_cache();
+                return Err((index, err));
+            }
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn play(
         &mut self,
         mut player1: Box<dyn Player>,
         mut player2: Box<dyn Player>,
     ) -> Result<(f32, f32), &str> {
-        let mut game_over = false;
-        while !game_over {
-            let pta = self.game_state.acting_player;
-            let actions = self.legal_actions();
-            let player = match pta {
-                GamePlayer::Player1 => &mut player1,
-                GamePlayer::Player2 => &mut player2,
+        while !self.is_over() {
+            match self.play_turn(player1.as_mut(), player2.as_mut()) {
+                Ok(TurnOutcome::NoAction(_)) => return Ok((0.0, 0.0)),
+                Ok(TurnOutcome::Played(_)) | Err(_) => (),
+            }
+        }
+        Ok(self.get_rewards())
+    }
+
+    /// Play one turn: the acting player, `player1` or `player2`, chooses from their snapshot
+    /// and the action is applied. This is the turn of the game loops in `play`, `_run_game`
+    /// and the Python env.
+    pub fn play_turn(
+        &mut self,
+        player1: &mut dyn Player,
+        player2: &mut dyn Player,
+    ) -> Result<TurnOutcome, StepError> {
+        let player = self.game_state.acting_player;
+        let ObservableSnapshot {
+            state,
+            actions,
+            history,
+        } = self.snapshot(player);
+        let choice = match player {
+            GamePlayer::Player1 => player1.choose_action(state, actions, history),
+            GamePlayer::Player2 => player2.choose_action(state, actions, history),
+        };
+        let Some(action) = choice else {
+            log::warn!("{:?} chose no action with legal actions left", player);
+            return Ok(TurnOutcome::NoAction(player));
+        };
+        self.step(action)?;
+        Ok(TurnOutcome::Played(action))
+    }
+
+    /// Play a copy of the game to the end with `policy` on both sides and return the final
+    /// rewards, leaving `self` untouched. See `rollout_with`.
+    pub fn rollout(&self, policy: &mut dyn Player) -> (f32, f32) {
+        self.play_out(|_, state, actions| {
+            policy.choose_action(state, actions, ObservableGameHistory(Vec::new()))
+        })
+    }
+
+    /// Play a copy of the game to the end with a policy per side and return the final
+    /// rewards. This is the playout used by search, so policies get the observation and legal
+    /// actions but an empty history to keep it cheap. A playout that runs longer than
+    /// `MAX_ROLLOUT_STEPS`, or where a policy returns no action, counts as a draw.
+    pub fn rollout_with(&self, player1: &mut dyn Player, player2: &mut dyn Player) -> (f32, f32) {
+        self.play_out(|player, state, actions| {
+            let history = ObservableGameHistory(Vec::new());
+            match player {
+                GamePlayer::Player1 => player1.choose_action(state, actions, history),
+                GamePlayer::Player2 => player2.choose_action(state, actions, history),
+            }
+        })
+    }
+
+    /// Estimate how often each side wins from here, as far as `player` can tell. Every one
+    /// of `rollouts` playouts starts from a fresh `determinize` of `player`'s snapshot, so
+    /// hidden cards are guessed, and is played by two players from `policy_factory`, see
+    /// `rollout_with`. Returns the fractions won by Player1 and Player2; draws count for
+    /// neither, and determinizations that fail are left out.
+    pub fn estimate_win_prob<R: Rng + ?Sized>(
+        &self,
+        player: GamePlayer,
+        rollouts: usize,
+        mut policy_factory: impl FnMut() -> Box<dyn Player>,
+        rng: &mut R,
+    ) -> (f32, f32) {
+        let ObservableSnapshot { state, history, .. } = self.snapshot(player);
+        let (mut player1, mut player2) = (policy_factory(), policy_factory());
+        let (mut wins1, mut wins2, mut played) = (0, 0, 0);
+        for _ in 0..rollouts {
+            let Some(sampled) = determinize(&state, &history, &self.config, rng) else {
+                continue;
             };
-            let history =
-                ObservableGameHistory(self.history.iter().map(|x| x.observe(pta)).collect());
-            let action =
-                player
-                    .as_mut()
-                    .choose_action(self.game_state.observe(pta), actions, history);
-            match self.step(action) {
-                Ok(_) => (),
-                Err(_e) => (),
+            let mut game = Game::from_state(sampled);
+            game.config = self.config.clone();
+            let (reward1, reward2) = game.rollout_with(player1.as_mut(), player2.as_mut());
+            wins1 += usize::from(reward1 > 0.0);
+            wins2 += usize::from(reward2 > 0.0);
+            played += 1;
+        }
+        match played {
+            0 => (0.0, 0.0),
+            n => (wins1 as f32 / n as f32, wins2 as f32 / n as f32),
+        }
+    }
+
+    fn play_out(
+        &self,
+        mut choose: impl FnMut(GamePlayer, ObservableGameState, ActionList) -> Option<Action>,
+    ) -> (f32, f32) {
+        let mut game = Game::from_state(self.game_state.clone());
+        game.config = self.config.clone();
+        for _ in 0..MAX_ROLLOUT_STEPS {
+            if game.is_over() {
+                return game.get_rewards();
+            }
+            let player = game.game_state.acting_player;
+            let state = game.observe(player);
+            let Some(action) = choose(player, state, game.legal_actions()) else {
+                return (0.0, 0.0);
             };
+            if game.step(action).is_err() {
+                return (0.0, 0.0);
+            }
+        }
+        match game.is_over() {
+            true => game.get_rewards(),
+            false => (0.0, 0.0),
+        }
+    }
+}
 
-            game_over = self.is_over();
+/// Whether `card` can cover `attack` when `tsuit` is trumps.
+pub(crate) fn beats(card: &Card, attack: &Card, tsuit: Suit) -> bool {
+    match (card.is_trump(tsuit), attack.is_trump(tsuit)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => card.suit == attack.suit && card.rank > attack.rank,
+    }
+}
+
+/// Whether every card of `attacks` can be covered by a different card of `hand`, found as
+/// a maximum matching with augmenting paths.
+fn covers_all(attacks: &[Card], hand: &[Card], tsuit: Suit) -> bool {
+    // `covering[h]` is the attack that hand card `h` is matched to
+    let mut covering: Vec<Option<usize>> = vec![None; hand.len()];
+    (0..attacks.len()).all(|attack| {
+        let mut seen = vec![false; hand.len()];
+        augment(attack, attacks, hand, tsuit, &mut seen, &mut covering)
+    })
+}
+
+fn augment(
+    attack: usize,
+    attacks: &[Card],
+    hand: &[Card],
+    tsuit: Suit,
+    seen: &mut [bool],
+    covering: &mut [Option<usize>],
+) -> bool {
+    for h in 0..hand.len() {
+        if seen[h] || !beats(&hand[h], &attacks[attack], tsuit) {
+            continue;
+        }
+        seen[h] = true;
+        let free = match covering[h] {
+            None => true,
+            Some(other) => augment(other, attacks, hand, tsuit, seen, covering),
+        };
+        if free {
+            covering[h] = Some(attack);
+            return true;
         }
-        Ok(self.get_rewards())
     }
+    false
+}
+
+/// What happened on a turn of `Game::play_turn`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TurnOutcome {
+    /// The acting player chose this action and it was applied.
+    Played(Action),
+    /// The acting player had no action to give, e.g. after a rule bug left them with no
+    /// legal actions. The game cannot go on, and the game loops score it as a draw.
+    NoAction(GamePlayer),
+}
+
+/// Why `GameLogic::step` refused an action.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepError {
+    /// The action is not among the legal actions of the current state.
+    IllegalAction(Action),
 }
 
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StepError::IllegalAction(action) => write!(f, "Illegal action {:?}", action),
+        }
+    }
+}
+
+impl std::error::Error for StepError {}
+
 pub trait GameLogic {
-    fn step(&mut self, action: Action) -> Result<(), &str>;
+    fn step(&mut self, action: Action) -> Result<(), StepError>;
     fn get_actions(&self) -> ActionList;
     fn get_winner(&self) -> Option<GamePlayer>;
     fn get_rewards(&self) -> (f32, f32);
@@ -396,18 +1253,24 @@ pub trait GameLogic {
 }
 
 impl GameLogic for Game {
-    fn step(&mut self, action: Action) -> Result<(), &str> {
+    fn step(&mut self, action: Action) -> Result<(), StepError> {
         let legal_actions = self.legal_actions();
         if !legal_actions.0.contains(&action) {
-            return Err("Illegal action");
+            return Err(StepError::IllegalAction(action));
         }
+        self.events.push(GameEvent::Action {
+            player: self.game_state.acting_player,
+            action,
+        });
         match action {
             Action::StopAttack => self.handle_stop_attack(),
             Action::Take => self.handle_take(),
             Action::Attack(card) => self.handle_attack(card),
             Action::Defend(card) => self.handle_defense(card),
         }
+        self.invalidate_action_cache();
         self.history.push(self.game_state.clone());
+        self.actions.push(action);
         Ok(())
     }
 
@@ -416,6 +1279,12 @@ impl GameLogic for Game {
     }
 
     fn get_winner(&self) -> Option<GamePlayer> {
+        if let Some(loser) = self.conceded {
+            return Some(loser.other());
+        }
+        if self.awaiting_last_defense() {
+            return None;
+        }
         let sizes = vec![
             self.game_state.hand1.0.len(),
             self.game_state.hand2.0.len(),
@@ -440,6 +1309,12 @@ impl GameLogic for Game {
     }
 
     fn is_over(&self) -> bool {
+        if self.conceded.is_some() {
+            return true;
+        }
+        if self.awaiting_last_defense() {
+            return false;
+        }
         let sizes = vec![
             self.game_state.hand1.0.len(),
             self.game_state.hand2.0.len(),
@@ -455,33 +1330,1202 @@ impl GameLogic for Game {
     }
 }
 
+/// The rewards of each of `games`, computed in parallel. Like summing `_run_game` results,
+/// but keeps every game's rewards, in the order of `games`.
+pub fn batch_rewards(games: &[Game]) -> Vec<(f32, f32)> {
+    games.par_iter().map(|game| game.get_rewards()).collect()
+}
+
 pub fn _run_game() -> (f32, f32) {
-    let mut p1 = Box::new(RandomPlayer::new(None));
-    let mut p2 = Box::new(RandomPlayer::new(None));
+    let mut p1 = RandomPlayer::new(None);
+    let mut p2 = RandomPlayer::new(None);
     let mut game = Game::new();
-    let mut game_over = false;
-    'game_loop: loop {
-        if game_over {
-            break 'game_loop;
-        }
-        let pta = game.game_state.acting_player;
-        let actions = game.get_actions();
-        let player = match pta {
-            GamePlayer::Player1 => p1.as_mut(),
-            GamePlayer::Player2 => p2.as_mut(),
+    while !game.is_over() {
+        if let Ok(TurnOutcome::NoAction(_)) = game.play_turn(&mut p1, &mut p2) {
+            return (0.0, 0.0);
+        }
+    }
+    game.get_rewards()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{cards::Deck, config::OpponentCount, dealer::StackedDealer};
+    use rand::Rng;
+
+    fn card(rank: u8, suit: Suit) -> Card {
+        Card { suit, rank }
+    }
+
+    /// Build a game where Player1 attacks Player2 with the given hands. The remaining cards
+    /// form the deck (a card of the trump suit at the bottom), truncated to `deck_size` with
+    /// the rest put in the graveyard.
+    fn game_with(hand1: Vec<Card>, hand2: Vec<Card>, trump: Suit, deck_size: usize) -> Game {
+        let mut deck = Deck::new(6);
+        deck.cards
+            .retain(|c| !hand1.contains(c) && !hand2.contains(c));
+        let pos = deck.cards.iter().position(|c| c.suit == trump).unwrap();
+        let visible_card = deck.cards.remove(pos);
+        deck.cards.insert(0, visible_card);
+        let graveyard = deck.cards.split_off(deck_size.min(deck.cards.len()));
+        Game::from_state(GameState::new(
+            deck,
+            Vec::new(),
+            Vec::new(),
+            Hand(hand1),
+            Hand(hand2),
+            GamePlayer::Player1,
+            GamePlayer::Player2,
+            visible_card,
+            false,
+            graveyard,
+        ))
+    }
+
+    /// Play a cleared bout with a single card left in the deck and return the hand sizes.
+    fn hand_sizes_after_short_refill(order: RefillOrder, attacker: GamePlayer) -> (usize, usize) {
+        let attack = [6, 8, 9, 10, 11, 12].map(|rank| match rank {
+            6 => card(6, Suit::Spades),
+            _ => card(rank, Suit::Hearts),
+        });
+        let defense = [7, 8, 9, 10, 11, 12].map(|rank| match rank {
+            7 => card(7, Suit::Spades),
+            _ => card(rank, Suit::Diamonds),
+        });
+        let mut game = match attacker {
+            GamePlayer::Player1 => game_with(attack.to_vec(), defense.to_vec(), Suit::Clubs, 1),
+            GamePlayer::Player2 => game_with(defense.to_vec(), attack.to_vec(), Suit::Clubs, 1),
+        };
+        let state = game.game_state_mut();
+        state.acting_player = attacker;
+        state.defending_player = attacker.other();
+        game.config_mut().refill_order = order;
+
+        for action in [
+            Action::Attack(card(6, Suit::Spades)),
+            Action::StopAttack,
+            Action::Defend(card(7, Suit::Spades)),
+            Action::StopAttack,
+        ] {
+            game.step(action).unwrap();
+        }
+        assert_eq!(game.game_state.deck.len(), 0);
+        (game.game_state.hand1.0.len(), game.game_state.hand2.0.len())
+    }
+
+    #[test]
+    fn test_snapshot_matches_accessors() {
+        let mut game = Game::new();
+        let action = game.legal_actions().0[0];
+        game.step(action).unwrap();
+
+        let player = game.game_state.acting_player;
+        let snapshot = game.snapshot(player);
+        assert_eq!(snapshot.state, game.game_state.observe(player));
+        assert_eq!(snapshot.actions, game.legal_actions());
+        assert_eq!(snapshot.history.0.len(), game.history.len());
+        for (observed, state) in snapshot.history.0.iter().zip(game.history.iter()) {
+            assert_eq!(*observed, state.observe(player));
+        }
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: ObservableSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, snapshot);
+    }
+
+    #[test]
+    fn test_attacks_limited_by_defender_capacity() {
+        let mut game = game_with(
+            vec![
+                card(6, Suit::Spades),
+                card(6, Suit::Hearts),
+                card(6, Suit::Diamonds),
+                card(7, Suit::Clubs),
+            ],
+            vec![card(14, Suit::Spades)],
+            Suit::Clubs,
+            0,
+        );
+        assert_eq!(game.legal_actions().0.len(), 4);
+
+        game.step(Action::Attack(card(6, Suit::Spades))).unwrap();
+        // The defender holds a single card, so no second six may be added
+        assert_eq!(game.legal_actions(), ActionList(vec![Action::StopAttack]));
+        assert!(game.step(Action::Attack(card(6, Suit::Hearts))).is_err());
+    }
+
+    #[test]
+    fn test_action_cache_invalidated_on_step() {
+        let mut game = Game::new();
+        assert!(game.legal_actions_cache.get().is_none());
+
+        let actions = game.legal_actions();
+        assert_eq!(game.legal_actions_cache.get(), Some(&actions));
+        assert_eq!(actions, game.compute_legal_actions());
+
+        game.step(actions.0[0]).unwrap();
+        assert!(game.legal_actions_cache.get().is_none());
+        assert_eq!(game.legal_actions(), game.compute_legal_actions());
+    }
+
+    #[test]
+    fn test_action_cache_fresh_after_rollback_and_mutation() {
+        let mut game = Game::new_seeded(8);
+        let initial = game.legal_actions();
+        let mut actions = Vec::new();
+        let mut source = game.clone();
+        for _ in 0..3 {
+            let action = source.legal_actions().0[0];
+            source.step(action).unwrap();
+            actions.push(action);
+        }
+        // The face-up trump stays in the deck, so this fails after three good steps
+        actions.push(Action::Attack(game.game_state().visible_card));
+        assert_eq!(game.apply_actions(&actions).unwrap_err().0, 3);
+        assert_eq!(game.get_actions(), initial);
+        assert_eq!(game.legal_actions(), game.compute_legal_actions());
+
+        let attacker = game.game_state().acting_player;
+        game.game_state_mut().acting_player = attacker.other();
+        assert_ne!(game.legal_actions(), initial);
+        assert_eq!(game.legal_actions(), game.compute_legal_actions());
+    }
+
+    #[test]
+    fn test_seeded_games_deal_identically() {
+        let game1 = Game::new_seeded(7);
+        let game2 = Game::new_seeded(7);
+        assert_eq!(game1.game_state, game2.game_state);
+        assert_ne!(game1.game_state, Game::new_seeded(8).game_state);
+    }
+
+    #[test]
+    fn test_hand_size_used_for_deal_and_refill() {
+        let config = GameConfig {
+            hand_size: 7,
+            ..GameConfig::default()
+        };
+        let mut game = Game::with_config_seeded(config, 3).unwrap();
+        assert_eq!(game.game_state.hand1.0.len(), 7);
+        assert_eq!(game.game_state.hand2.0.len(), 7);
+        assert_eq!(game.game_state.deck.len(), 36 - 14);
+
+        // Open with one card and take it: the attacker is topped back up to 7
+        let attacker = game.game_state.acting_player;
+        let attack = game.legal_actions().0[0];
+        game.step(attack).unwrap();
+        game.step(Action::StopAttack).unwrap();
+        game.step(Action::Take).unwrap();
+        game.step(Action::StopAttack).unwrap();
+        assert!(game.game_state.attack_table.is_empty());
+        let (attacker_hand, defender_hand) = match attacker {
+            GamePlayer::Player1 => (&game.game_state.hand1, &game.game_state.hand2),
+            GamePlayer::Player2 => (&game.game_state.hand2, &game.game_state.hand1),
+        };
+        assert_eq!(attacker_hand.0.len(), 7);
+        assert_eq!(defender_hand.0.len(), 8);
+        assert_eq!(game.game_state.deck.len(), 36 - 15);
+    }
+
+    #[test]
+    fn test_bout_counter_increments_once_per_resolved_bout() {
+        let mut game = Game::new_seeded(11);
+        assert_eq!(game.game_state.bout, 0);
+
+        let mut resolved = 0;
+        while !game.is_over() {
+            let table_was_empty = game.game_state.attack_table.is_empty();
+            let bout = game.game_state.bout;
+            let action = game.legal_actions().0[0];
+            game.step(action).unwrap();
+            if !table_was_empty && game.game_state.attack_table.is_empty() {
+                resolved += 1;
+                assert_eq!(game.game_state.bout, bout + 1);
+            } else {
+                assert_eq!(game.game_state.bout, bout);
+            }
+        }
+        assert!(resolved > 0);
+        assert_eq!(game.game_state.bout, resolved);
+        assert_eq!(game.snapshot(GamePlayer::Player2).state.bout, resolved);
+    }
+
+    #[test]
+    fn test_hand_order_does_not_change_outcomes() {
+        fn sorted(actions: ActionList) -> Vec<Action> {
+            let mut actions = actions.0;
+            actions.sort_by_key(|action| match action {
+                Action::StopAttack => (0, None),
+                Action::Take => (1, None),
+                Action::Attack(card) => (2, Some(*card)),
+                Action::Defend(card) => (3, Some(*card)),
+            });
+            actions
+        }
+
+        // Play the same moves on two copies of a game, one of which has its hands
+        // reordered before every move. Removing cards with swap_remove relies on this.
+        let mut game = Game::new_seeded(21);
+        let mut reordered = Game::new_seeded(21);
+        let mut rng = StdRng::seed_from_u64(4);
+        while !game.is_over() {
+            let state = reordered.game_state_mut();
+            state.hand1.0.reverse();
+            // An attacker may be left empty-handed until the deck refills them
+            if !state.hand2.0.is_empty() {
+                state.hand2.0.rotate_left(1);
+            }
+
+            let actions = sorted(game.legal_actions());
+            assert_eq!(actions, sorted(reordered.legal_actions()));
+            let action = actions[rng.gen_range(0..actions.len())];
+            game.step(action).unwrap();
+            reordered.step(action).unwrap();
+            assert_eq!(game.game_state, reordered.game_state);
+        }
+        assert!(reordered.is_over());
+        assert_eq!(game.get_rewards(), reordered.get_rewards());
+    }
+
+    #[test]
+    fn test_try_from_state_rejects_duplicate_card() {
+        let six = card(6, Suit::Spades);
+        let game = game_with(vec![six], vec![card(7, Suit::Hearts)], Suit::Clubs, 10);
+        assert!(Game::try_from_state(game.game_state.clone(), GameConfig::default()).is_ok());
+
+        let mut duplicated = game.game_state.clone();
+        duplicated.hand2.0.push(six);
+        assert!(Game::try_from_state(duplicated, GameConfig::default()).is_err());
+
+        let mut missing = game.game_state.clone();
+        missing.graveyard.pop();
+        assert!(Game::try_from_state(missing, GameConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_take_marks_opponent_void_in_suit() {
+        let mut game = game_with(
+            vec![card(14, Suit::Hearts), card(9, Suit::Spades)],
+            vec![card(7, Suit::Spades), card(8, Suit::Diamonds)],
+            Suit::Clubs,
+            0,
+        );
+        game.step(Action::Attack(card(14, Suit::Hearts))).unwrap();
+        game.step(Action::StopAttack).unwrap();
+        assert!(game
+            .snapshot(GamePlayer::Player1)
+            .state
+            .opponent_void_suits()
+            .is_empty());
+
+        game.step(Action::Take).unwrap();
+        game.step(Action::StopAttack).unwrap();
+        let state = game.snapshot(GamePlayer::Player1).state;
+        assert_eq!(state.opponent_void_suits(), vec![Suit::Hearts]);
+        assert!(game
+            .snapshot(GamePlayer::Player2)
+            .state
+            .opponent_void_suits()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_stacked_dealer_sets_first_attacker() {
+        // Player2 holds the lowest trump (7♣) while Player1 has no trumps at all
+        let hand1: Vec<Card> = (6..12).map(|rank| card(rank, Suit::Hearts)).collect();
+        let mut hand2 = vec![card(7, Suit::Clubs)];
+        hand2.extend((6..11).map(|rank| card(rank, Suit::Spades)));
+        let mut stack = [hand1.clone(), hand2.clone()].concat();
+        let rest: Vec<Card> = Deck::new(6)
+            .cards
+            .into_iter()
+            .filter(|c| !stack.contains(c) && *c != card(14, Suit::Clubs))
+            .collect();
+        stack.extend(rest);
+        stack.push(card(14, Suit::Clubs));
+
+        let mut dealer = StackedDealer::new(stack).unwrap();
+        let game = Game::with_dealer(GameConfig::default(), &mut dealer).unwrap();
+        assert_eq!(game.game_state.hand1, Hand(hand1));
+        assert_eq!(game.game_state.hand2, Hand(hand2));
+        assert_eq!(game.game_state.visible_card, card(14, Suit::Clubs));
+        assert_eq!(game.game_state.acting_player, GamePlayer::Player2);
+        assert_eq!(game.game_state.defending_player, GamePlayer::Player1);
+        assert!(game
+            .game_state
+            .validate_complete(&GameConfig::default().rank_set())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_legal_actions_independent_of_hand_order() {
+        let game = Game::new_seeded(13);
+        let mut state = game.game_state.clone();
+        state.hand1.0.reverse();
+        state.hand2.0.reverse();
+        let reordered = Game::from_state(state);
+        assert!(game.game_state.logically_eq(&reordered.game_state));
+        assert_eq!(game.legal_actions(), reordered.legal_actions());
+
+        let actions = game.legal_actions().0;
+        let mut sorted = actions.clone();
+        sorted.sort_by_key(|action| match action {
+            Action::Attack(card) | Action::Defend(card) => usize::from(*card),
+            _ => 0,
+        });
+        assert_eq!(actions, sorted);
+    }
+
+    #[test]
+    fn test_decisions_follow_player_turns() {
+        let mut game = Game::new_seeded(17);
+        let mut expected = Vec::new();
+        while !game.is_over() {
+            let action = game.legal_actions().0[0];
+            if game.game_state.acting_player == GamePlayer::Player2 {
+                expected.push((game.game_state.observe(GamePlayer::Player2), action));
+            }
+            game.step(action).unwrap();
+        }
+        assert_eq!(game.decisions(GamePlayer::Player2), expected);
+        assert_eq!(
+            game.decisions(GamePlayer::Player1).len() + expected.len(),
+            game.actions.len()
+        );
+    }
+
+    #[test]
+    fn test_peek_matches_step() {
+        let mut game = Game::new_seeded(23);
+        while !game.is_over() {
+            let before = game.game_state.clone();
+            let action = *game.legal_actions().0.last().unwrap();
+            let peeked = game.peek(action).unwrap();
+            assert_eq!(game.game_state, before);
+            game.step(action).unwrap();
+            assert_eq!(peeked, game.game_state);
+        }
+        let illegal = Action::Defend(game.game_state.visible_card);
+        assert_eq!(game.peek(illegal), Err(StepError::IllegalAction(illegal)));
+    }
+
+    #[test]
+    fn test_bout_resolution_logs_refills_in_order() {
+        let mut game = game_with(
+            vec![card(6, Suit::Spades), card(9, Suit::Hearts)],
+            vec![card(7, Suit::Spades), card(8, Suit::Diamonds)],
+            Suit::Clubs,
+            10,
+        );
+        game.step(Action::Attack(card(6, Suit::Spades))).unwrap();
+        game.step(Action::StopAttack).unwrap();
+        game.step(Action::Take).unwrap();
+        game.step(Action::StopAttack).unwrap();
+
+        // The attacker draws first, then the defender who picked up the table
+        assert_eq!(
+            game.events[game.events.len() - 3..],
+            [
+                GameEvent::Action {
+                    player: GamePlayer::Player1,
+                    action: Action::StopAttack
+                },
+                GameEvent::Refill {
+                    player: GamePlayer::Player1,
+                    count: 5
+                },
+                GameEvent::Refill {
+                    player: GamePlayer::Player2,
+                    count: 3
+                },
+            ]
+        );
+        assert_eq!(game.events.len(), game.actions.len() + 2);
+        assert!(Game::validate_log(&game.log()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_config_is_not_dealt() {
+        let config = GameConfig {
+            hand_size: 20,
+            ..GameConfig::default()
+        };
+        assert_eq!(
+            Game::with_config(config).err(),
+            Some(ConfigError::DeckTooSmall {
+                needed: 41,
+                available: 36
+            })
+        );
+    }
+
+    #[test]
+    fn test_num_legal_actions_matches_action_list() {
+        for seed in 0..50 {
+            let mut game = Game::new_seeded(seed);
+            let mut rng = StdRng::seed_from_u64(seed);
+            while !game.is_over() {
+                let actions = game.legal_actions();
+                // Count on a fresh game so the cached list is not used
+                let uncached = Game::from_state(game.game_state.clone());
+                assert_eq!(uncached.num_legal_actions(), actions.0.len());
+                assert_eq!(game.num_legal_actions(), actions.0.len());
+                let action = actions.0[rng.gen_range(0..actions.0.len())];
+                game.step(action).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_refill_order_decides_who_ends_up_short() {
+        use GamePlayer::{Player1, Player2};
+        let cases = [
+            // The roles have passed on by the time the hands are refilled
+            (RefillOrder::AttackerFirst, Player1, (5, 6)),
+            (RefillOrder::AttackerFirst, Player2, (6, 5)),
+            (RefillOrder::DefenderFirst, Player1, (6, 5)),
+            (RefillOrder::DefenderFirst, Player2, (5, 6)),
+            (RefillOrder::SeatOrder, Player1, (6, 5)),
+            (RefillOrder::SeatOrder, Player2, (6, 5)),
+        ];
+        for (order, attacker, expected) in cases {
+            assert_eq!(
+                hand_sizes_after_short_refill(order, attacker),
+                expected,
+                "{:?} with {:?} attacking",
+                order,
+                attacker
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_refill_gives_next_attacker_first_draw_after_pass() {
+        let mut game = game_with(
+            vec![card(6, Suit::Spades), card(8, Suit::Hearts)],
+            vec![card(7, Suit::Spades), card(8, Suit::Diamonds)],
+            Suit::Clubs,
+            1,
+        );
+        for action in [
+            Action::Attack(card(6, Suit::Spades)),
+            Action::StopAttack,
+            Action::Defend(card(7, Suit::Spades)),
+            Action::StopAttack,
+        ] {
+            game.step(action).unwrap();
+        }
+        // The defender beat the table and attacks next, so they draw the last card first
+        assert_eq!(game.game_state().acting_player, GamePlayer::Player2);
+        let refills: Vec<GameEvent> = game
+            .events
+            .iter()
+            .filter(|event| matches!(event, GameEvent::Refill { .. }))
+            .copied()
+            .collect();
+        assert_eq!(
+            refills,
+            vec![GameEvent::Refill {
+                player: GamePlayer::Player2,
+                count: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rollout_from_forced_position() {
+        // Player1 must lead their last card with the deck gone, which wins the game
+        let game = game_with(
+            vec![card(14, Suit::Spades)],
+            vec![card(6, Suit::Hearts), card(7, Suit::Hearts)],
+            Suit::Spades,
+            0,
+        );
+        let before = game.game_state.clone();
+        let mut policy = RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(0))));
+
+        assert_eq!(game.rollout(&mut policy), (1.0, -1.0));
+        assert_eq!(game.game_state, before);
+        assert!(game.actions.is_empty());
+    }
+
+    #[test]
+    fn test_hand_size_series_covers_every_state() {
+        let mut game = Game::new_seeded(17);
+        while !game.is_over() {
+            let action = game.legal_actions().0[0];
+            game.step(action).unwrap();
+        }
+        let (sizes1, sizes2) = game.hand_size_series();
+
+        assert_eq!(sizes1.len(), game.actions.len() + 1);
+        assert_eq!(sizes2.len(), game.history.len());
+        assert_eq!((sizes1[0], sizes2[0]), (6, 6));
+        assert_eq!(sizes1.last(), Some(&game.game_state.hand1.0.len()));
+        assert_eq!(sizes2.last(), Some(&game.game_state.hand2.0.len()));
+    }
+
+    #[test]
+    fn test_reveal_opponent_shows_the_true_hand() {
+        let mut game = Game::new_seeded(4);
+        assert_eq!(game.observe(GamePlayer::Player1).opponent_hand, None);
+
+        game.config.reveal_opponent = true;
+        for _ in 0..4 {
+            let action = game.legal_actions().0[0];
+            game.step(action).unwrap();
+        }
+        let state = &game.game_state;
+        let p1 = game.observe(GamePlayer::Player1);
+        let p2 = game.snapshot(GamePlayer::Player2).state;
+        assert_eq!(p1.opponent_hand, Some(state.hand2.clone()));
+        assert_eq!(p2.opponent_hand, Some(state.hand1.clone()));
+        // The revealed hand is part of the encoding
+        assert_ne!(
+            p1.to_numpy_f32(),
+            state.observe(GamePlayer::Player1).to_numpy_f32()
+        );
+    }
+
+    #[test]
+    fn test_defense_order_policies() {
+        let hand2 = vec![
+            card(8, Suit::Clubs),
+            card(9, Suit::Hearts),
+            card(6, Suit::Diamonds),
+        ];
+        // In order, only the 7♥ laid first can be answered
+        let game = two_attack_game(DefenseOrder::InOrder, hand2.clone());
+        assert_eq!(
+            game.legal_actions().0,
+            vec![Action::Take, Action::Defend(card(9, Suit::Hearts))]
+        );
+        assert_eq!(game.num_legal_actions(), 2);
+
+        let mut game = two_attack_game(DefenseOrder::AnyOrder, hand2);
+        assert_eq!(
+            game.legal_actions().0,
+            vec![
+                Action::Take,
+                Action::Defend(card(9, Suit::Hearts)),
+                Action::Defend(card(8, Suit::Clubs)),
+            ]
+        );
+        assert_eq!(game.num_legal_actions(), 3);
+        // Beating the 7♣ first pairs it with the defense
+        game.step(Action::Defend(card(8, Suit::Clubs))).unwrap();
+        assert_eq!(
+            game.game_state.attack_table,
+            vec![card(7, Suit::Clubs), card(7, Suit::Hearts)]
+        );
+        assert_eq!(game.game_state.defense_table, vec![card(8, Suit::Clubs)]);
+        assert_eq!(
+            game.legal_actions().0,
+            vec![Action::Take, Action::Defend(card(9, Suit::Hearts))]
+        );
+    }
+
+    #[test]
+    fn test_any_order_defense_keeps_table_answerable() {
+        // The trump beats both attacks but must take the 7♣, since only it is left for
+        // the 8♥ to answer otherwise
+        let hand2 = vec![card(6, Suit::Spades), card(8, Suit::Hearts)];
+        let mut game = two_attack_game(DefenseOrder::AnyOrder, hand2);
+        game.step(Action::Defend(card(6, Suit::Spades))).unwrap();
+        assert_eq!(
+            game.game_state.attack_table,
+            vec![card(7, Suit::Clubs), card(7, Suit::Hearts)]
+        );
+        game.step(Action::Defend(card(8, Suit::Hearts))).unwrap();
+        assert_eq!(game.game_state.bout, 1);
+    }
+
+    #[test]
+    fn test_table_ranks_cover_both_tables() {
+        let hand2 = vec![card(8, Suit::Clubs), card(9, Suit::Hearts)];
+        let mut game = two_attack_game(DefenseOrder::InOrder, hand2);
+        assert_eq!(game.table_ranks(), vec![7]);
+        game.step(Action::Defend(card(9, Suit::Hearts))).unwrap();
+        assert_eq!(game.table_ranks(), vec![7, 9]);
+    }
+
+    /// Player1 lays 7♥ and 7♣ against Player2's `hand2`, spades are trumps.
+    fn two_attack_game(order: DefenseOrder, hand2: Vec<Card>) -> Game {
+        let hand1 = vec![
+            card(7, Suit::Hearts),
+            card(7, Suit::Clubs),
+            card(9, Suit::Diamonds),
+        ];
+        let mut game = game_with(hand1, hand2, Suit::Spades, 5);
+        game.config.defense_order = order;
+        for action in [
+            Action::Attack(card(7, Suit::Hearts)),
+            Action::Attack(card(7, Suit::Clubs)),
+            Action::StopAttack,
+        ] {
+            game.step(action).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_attack_cap_forces_stop() {
+        let hand1 = vec![
+            card(7, Suit::Hearts),
+            card(7, Suit::Clubs),
+            card(7, Suit::Diamonds),
+        ];
+        let hand2 = [6, 8, 9, 10, 11, 12]
+            .map(|rank| card(rank, Suit::Clubs))
+            .to_vec();
+        let mut game = game_with(hand1, hand2, Suit::Spades, 5);
+        game.config.max_attacks_per_bout = Some(2);
+
+        game.step(Action::Attack(card(7, Suit::Hearts))).unwrap();
+        assert_eq!(game.num_legal_actions(), 3);
+        game.step(Action::Attack(card(7, Suit::Clubs))).unwrap();
+        assert_eq!(game.legal_actions().0, vec![Action::StopAttack]);
+        assert_eq!(game.num_legal_actions(), 1);
+    }
+
+    #[test]
+    fn test_rank_set_game_plays_only_those_ranks() {
+        let config = GameConfig {
+            ranks: Some(vec![6, 7, 8, 9, 11, 12, 13, 14]),
+            ..GameConfig::default()
         };
-        let history = ObservableGameHistory(game.history.iter().map(|x| x.observe(pta)).collect());
-        let action = player.choose_action(game.game_state.observe(pta), actions, history);
-        'step_loop: loop {
-            match game.step(action) {
-                Ok(_) => break 'step_loop,
-                Err(e) => {
-                    println!("Error: {}", e);
+        let mut game = Game::with_config_seeded(config, 5).unwrap();
+        assert_eq!(game.game_state.deck.len(), 32 - 12);
+        while !game.is_over() {
+            let action = game.legal_actions().0[0];
+            game.step(action).unwrap();
+            let ranks = game.config.rank_set();
+            assert!(game.game_state.validate_complete(&ranks).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_first_bout_ends_when_it_resolves() {
+        let mut game = Game::new_seeded(2);
+        assert!(game.is_first_bout());
+        while game.game_state.bout == 0 {
+            assert!(game.is_first_bout());
+            let action = game.legal_actions().0[0];
+            game.step(action).unwrap();
+        }
+        assert!(!game.is_first_bout());
+    }
+
+    #[test]
+    fn test_short_stack_is_not_dealt() {
+        let stack = Deck::new(6).cards[..10].to_vec();
+        let mut dealer = StackedDealer::new(stack).unwrap();
+        assert_eq!(
+            Game::with_dealer(GameConfig::default(), &mut dealer).err(),
+            Some(ConfigError::DeckTooSmall {
+                needed: 13,
+                available: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_play_until_turn_stops_on_my_turn() {
+        let me = GamePlayer::Player1;
+        let mut opponent = RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(6))));
+        let mut game = Game::new_seeded(6);
+        while !game.is_over() {
+            let played = game.actions.len();
+            game.play_until_turn(me, &mut opponent).unwrap();
+            assert!(game.is_over() || game.game_state.acting_player == me);
+            // Every move the call made was the opponent's
+            assert!(game.history[played..game.actions.len()]
+                .iter()
+                .all(|state| state.acting_player == !me));
+            if !game.is_over() {
+                let action = game.legal_actions().0[0];
+                game.step(action).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_reward_for_matches_reward_tuple() {
+        let win = game_with(Vec::new(), vec![card(9, Suit::Hearts)], Suit::Spades, 0);
+        let loss = game_with(vec![card(9, Suit::Hearts)], Vec::new(), Suit::Spades, 0);
+        let draw = game_with(Vec::new(), Vec::new(), Suit::Spades, 0);
+        let running = Game::new_seeded(1);
+        for (game, expected) in [(win, 1.0), (loss, -1.0), (draw, 0.0), (running, 0.0)] {
+            let rewards = game.get_rewards();
+            assert_eq!(game.reward_for(GamePlayer::Player1), rewards.0);
+            assert_eq!(game.reward_for(GamePlayer::Player2), rewards.1);
+            assert_eq!(game.reward_for(GamePlayer::Player1), expected);
+        }
+    }
+
+    #[test]
+    fn test_no_trump_lead_until_deck_is_empty() {
+        let hand1 = vec![card(9, Suit::Spades), card(7, Suit::Hearts)];
+        let hand2 = vec![card(8, Suit::Clubs)];
+        let opening = |deck_size| {
+            let mut game = game_with(hand1.clone(), hand2.clone(), Suit::Spades, deck_size);
+            game.config.no_trump_lead = true;
+            (game.legal_actions().0, game.num_legal_actions())
+        };
+
+        let (actions, num_actions) = opening(5);
+        assert_eq!(actions, vec![Action::Attack(card(7, Suit::Hearts))]);
+        assert_eq!(num_actions, 1);
+
+        let (actions, num_actions) = opening(0);
+        assert!(actions.contains(&Action::Attack(card(9, Suit::Spades))));
+        assert_eq!(num_actions, 2);
+    }
+
+    #[test]
+    fn test_apply_actions_is_all_or_nothing() {
+        let mut source = Game::new_seeded(8);
+        for _ in 0..6 {
+            let action = source.legal_actions().0[0];
+            source.step(action).unwrap();
+        }
+
+        let mut game = Game::new_seeded(8);
+        game.apply_actions(&source.actions).unwrap();
+        assert_eq!(game.game_state, source.game_state);
+        assert_eq!(game.actions, source.actions);
+
+        let mut game = Game::new_seeded(8);
+        // The face-up trump stays in the deck, so nobody can play it yet
+        let mut actions = source.actions[..3].to_vec();
+        actions.push(Action::Attack(game.game_state.visible_card));
+        actions.extend_from_slice(&source.actions[3..]);
+        let before = game.game_state.clone();
+        let (index, _) = game.apply_actions(&actions).unwrap_err();
+        assert_eq!(index, 3);
+        assert_eq!(game.game_state, before);
+        assert!(game.actions.is_empty());
+        assert_eq!(game.history.len(), 1);
+        assert_eq!(game.legal_actions(), Game::new_seeded(8).legal_actions());
+    }
+
+    #[test]
+    fn test_total_cards_match_expected_throughout() {
+        let config = GameConfig {
+            ranks: Some(vec![6, 9, 10, 11, 12, 13, 14]),
+            ..GameConfig::default()
+        };
+        for mut game in [
+            Game::new_seeded(3),
+            Game::with_config_seeded(config, 3).unwrap(),
+        ] {
+            while !game.is_over() {
+                assert_eq!(game.total_cards(), game.expected_total());
+                let action = game.legal_actions().0[0];
+                game.step(action).unwrap();
+            }
+            assert_eq!(game.total_cards(), game.expected_total());
+        }
+    }
+
+    #[test]
+    fn test_next_attacker_after_defense() {
+        use GamePlayer::{Player1, Player2};
+        for (next_attacker, expected) in [
+            (NextAttacker::Defender, Player2),
+            (NextAttacker::Attacker, Player1),
+        ] {
+            // Beaten off with the attacker stopping, and by covering a sixth attack
+            // Each defense brings the rank of the next attack onto the table
+            let suits = [Suit::Hearts, Suit::Spades, Suit::Diamonds];
+            let hand1: Vec<Card> = (6..12)
+                .map(|rank| card(rank, suits[rank as usize % 3]))
+                .collect();
+            let hand2: Vec<Card> = (6..12)
+                .map(|rank| card(rank + 1, suits[rank as usize % 3]))
+                .collect();
+            for attacks in [1, 6] {
+                let mut game = game_with(hand1.clone(), hand2.clone(), Suit::Clubs, 10);
+                // Past the first bout, where only five attacks are allowed
+                game.game_state.bout = 1;
+                game.config.next_attacker = next_attacker;
+                for i in 0..attacks {
+                    game.step(Action::Attack(hand1[i])).unwrap();
+                    game.step(Action::StopAttack).unwrap();
+                    game.step(Action::Defend(hand2[i])).unwrap();
                 }
-            };
+                if attacks < 6 {
+                    game.step(Action::StopAttack).unwrap();
+                }
+                assert_eq!(game.game_state.bout, 2, "{:?}", next_attacker);
+                assert_eq!(game.game_state.acting_player, expected);
+                assert_eq!(game.game_state.defending_player, !expected);
+            }
         }
-        game_over = game.is_over();
     }
-    game.get_rewards()
-    // println!("Rewards: {:?}", rewards);
+
+    #[test]
+    fn test_bout_summary_of_a_take() {
+        let hand1 = vec![
+            card(6, Suit::Hearts),
+            card(7, Suit::Spades),
+            card(9, Suit::Diamonds),
+        ];
+        let hand2 = vec![card(7, Suit::Hearts), card(12, Suit::Diamonds)];
+        let mut game = game_with(hand1, hand2, Suit::Clubs, 10);
+        assert_eq!(game.last_bout, None);
+        for action in [
+            Action::Attack(card(6, Suit::Hearts)),
+            Action::StopAttack,
+            Action::Defend(card(7, Suit::Hearts)),
+            Action::Attack(card(7, Suit::Spades)),
+            Action::StopAttack,
+            Action::Take,
+        ] {
+            game.step(action).unwrap();
+        }
+        let summary = game.last_bout.clone().unwrap();
+        assert_eq!(
+            summary,
+            BoutSummary {
+                attacker: GamePlayer::Player1,
+                defender: GamePlayer::Player2,
+                attacks: vec![card(6, Suit::Hearts), card(7, Suit::Spades)],
+                defenses: vec![card(7, Suit::Hearts)],
+                took: true,
+                next_attacker: GamePlayer::Player1,
+            }
+        );
+        for taken in summary.attacks.iter().chain(summary.defenses.iter()) {
+            assert!(game.game_state.hand2.0.contains(taken));
+        }
+    }
+
+    #[test]
+    fn test_batch_rewards_match_each_game() {
+        let games: Vec<Game> = (0..8)
+            .map(|seed| {
+                let mut game = Game::new_seeded(seed);
+                let mut player = RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(seed))));
+                while !game.is_over() {
+                    let action = game.legal_actions().0[0];
+                    game.step(action).unwrap();
+                    game.play_until_turn(GamePlayer::Player1, &mut player)
+                        .unwrap();
+                }
+                game
+            })
+            .collect();
+        let expected: Vec<(f32, f32)> = games.iter().map(|game| game.get_rewards()).collect();
+        assert!(expected.iter().any(|rewards| *rewards != (0.0, 0.0)));
+        assert_eq!(batch_rewards(&games), expected);
+    }
+
+    #[test]
+    fn test_both_players_out_at_once_is_a_draw() {
+        for order in [RefillOrder::AttackerFirst, RefillOrder::DefenderFirst] {
+            let mut game = game_with(
+                vec![card(6, Suit::Hearts)],
+                vec![card(7, Suit::Hearts)],
+                Suit::Clubs,
+                0,
+            );
+            game.config.refill_order = order;
+            game.step(Action::Attack(card(6, Suit::Hearts))).unwrap();
+            // The attacker is out, but the defender can still go out too
+            assert!(!game.is_over());
+            game.step(Action::StopAttack).unwrap();
+            assert!(!game.is_over());
+            game.step(Action::Defend(card(7, Suit::Hearts))).unwrap();
+            assert!(game.is_over(), "{:?}", order);
+            assert_eq!(game.get_winner(), None);
+            assert_eq!(game.get_rewards(), (0.0, 0.0));
+        }
+
+        // With a card to spare the defender is left holding it and loses
+        let mut game = game_with(
+            vec![card(6, Suit::Hearts)],
+            vec![card(7, Suit::Hearts), card(8, Suit::Spades)],
+            Suit::Clubs,
+            0,
+        );
+        for action in [
+            Action::Attack(card(6, Suit::Hearts)),
+            Action::StopAttack,
+            Action::Defend(card(7, Suit::Hearts)),
+        ] {
+            game.step(action).unwrap();
+        }
+        assert_eq!(game.get_winner(), Some(GamePlayer::Player1));
+    }
+
+    /// Plays the first legal action and remembers the longest history it was given.
+    #[derive(Clone)]
+    struct RecallProbe(std::rc::Rc<std::cell::Cell<usize>>);
+
+    impl Player for RecallProbe {
+        fn choose_action(
+            &mut self,
+            _state: ObservableGameState,
+            actions: ActionList,
+            history: ObservableGameHistory,
+        ) -> Option<Action> {
+            self.0.set(self.0.get().max(history.0.len()));
+            actions.0.first().copied()
+        }
+
+        fn clone_box(&self) -> Box<dyn Player> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_last_k_recall_limits_history() {
+        for (policy, longest) in [
+            (ObservationPolicy::LastK(1), Some(1)),
+            (ObservationPolicy::LastK(3), Some(3)),
+            (ObservationPolicy::FullRecall, None),
+        ] {
+            let mut game = Game::new_seeded(9);
+            game.config.observation.history = policy;
+            let probe = RecallProbe(Default::default());
+            game.play(Box::new(probe.clone()), Box::new(probe.clone()))
+                .unwrap();
+            assert_eq!(
+                probe.0.get(),
+                longest.unwrap_or(game.history.len() - 1),
+                "{:?}",
+                policy
+            );
+        }
+    }
+
+    #[test]
+    fn test_turn_kind_tells_forced_from_optional_moves() {
+        let hand1 = vec![card(6, Suit::Hearts), card(6, Suit::Spades)];
+        let hand2 = vec![card(7, Suit::Diamonds), card(12, Suit::Diamonds)];
+        let mut game = game_with(hand1, hand2, Suit::Clubs, 10);
+        assert_eq!(game.turn_kind(), Some(TurnKind::Opening));
+        assert!(!game.legal_actions().is_voluntary_stop_available());
+
+        game.step(Action::Attack(card(6, Suit::Hearts))).unwrap();
+        assert_eq!(game.turn_kind(), Some(TurnKind::FollowUp));
+        assert!(game.legal_actions().is_voluntary_stop_available());
+
+        game.step(Action::StopAttack).unwrap();
+        // Nothing beats the six of hearts, so the defender can only take
+        assert_eq!(game.turn_kind(), Some(TurnKind::Defense));
+        assert_eq!(game.legal_actions(), ActionList(vec![Action::Take]));
+        assert!(!game.legal_actions().is_voluntary_stop_available());
+
+        game.concede(GamePlayer::Player2);
+        assert_eq!(game.turn_kind(), None);
+    }
+
+    #[test]
+    fn test_experiences_round_trip_with_history() {
+        let mut game = Game::new_seeded(11);
+        game.play(
+            Box::new(RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(1))))),
+            Box::new(RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(2))))),
+        )
+        .unwrap();
+        assert!(game.is_over());
+        let experiences = game.experiences(GamePlayer::Player1, true);
+        assert_eq!(experiences.len(), game.decisions(GamePlayer::Player1).len());
+        let json = serde_json::to_string(&experiences).unwrap();
+        let loaded: Vec<Experience> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, experiences);
+        let mut seen = 0;
+        for experience in loaded.iter() {
+            let history = experience.history.as_ref().unwrap();
+            // The history ends with the state the decision was made in
+            assert_eq!(history.0.last(), Some(&experience.state));
+            assert!(history.0.len() > seen);
+            seen = history.0.len();
+        }
+        assert_eq!(loaded.last().unwrap().done, game.history.len() - 1 == seen);
+
+        let plain = serde_json::to_string(&game.experiences(GamePlayer::Player1, false)).unwrap();
+        assert!(!plain.contains("history"));
+    }
+
+    #[test]
+    fn test_undefended_count_follows_the_table() {
+        let hand1 = vec![card(6, Suit::Hearts), card(6, Suit::Spades)];
+        let hand2 = vec![card(7, Suit::Hearts), card(7, Suit::Spades)];
+        let mut game = game_with(hand1, hand2, Suit::Clubs, 10);
+        let mut counts = Vec::new();
+        for action in [
+            Action::Attack(card(6, Suit::Hearts)),
+            Action::Attack(card(6, Suit::Spades)),
+            Action::StopAttack,
+            Action::Defend(card(7, Suit::Hearts)),
+            Action::Defend(card(7, Suit::Spades)),
+        ] {
+            game.step(action).unwrap();
+            let state = &game.game_state;
+            for player in [GamePlayer::Player1, GamePlayer::Player2] {
+                assert_eq!(
+                    game.observe(player).undefended_count as usize,
+                    state.attack_table.len() - state.defense_table.len()
+                );
+            }
+            counts.push(game.observe(GamePlayer::Player1).undefended_count);
+        }
+        assert_eq!(counts, vec![1, 2, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_bucketed_opponent_count_reports_the_bucket() {
+        let mut game = Game::new_seeded(13);
+        game.config.observation.opponent_count = OpponentCount::Bucketed(3);
+        let mut rng = StdRng::seed_from_u64(13);
+        let mut buckets = HashSet::new();
+        while !game.is_over() {
+            let state = game.observe(GamePlayer::Player1);
+            let count = game.game_state.hand2.0.len() as u8;
+            assert_eq!(state.cards_in_opponent, count / 3 * 3);
+            let (low, high) = state.cards_in_opponent_range();
+            assert!((low..=high).contains(&count) && high - low == 2);
+            // The count follows the 163 values before it in the encoding
+            assert_eq!(state.to_numpy().unwrap()[163], count / 3 * 3);
+            buckets.insert(count / 3);
+
+            let actions = game.legal_actions().0;
+            game.step(actions[rng.gen_range(0..actions.len())]).unwrap();
+        }
+        assert!(buckets.len() > 1);
+
+        game.config.observation.opponent_count = OpponentCount::Exact;
+        let state = game.observe(GamePlayer::Player1);
+        assert_eq!(
+            state.cards_in_opponent as usize,
+            game.game_state.hand2.0.len()
+        );
+    }
+
+    #[test]
+    fn test_replay_compare_finds_first_divergence() {
+        let mut game = Game::new_seeded(17);
+        game.play(
+            Box::new(RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(1))))),
+            Box::new(RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(2))))),
+        )
+        .unwrap();
+        let log = game.log();
+        let golden = game.history.clone();
+        assert_eq!(Game::replay_compare(&log, &golden), Ok(()));
+
+        let mut tampered = golden.clone();
+        tampered[5].defender_has_taken = !tampered[5].defender_has_taken;
+        tampered[9].bout += 1;
+        assert_eq!(Game::replay_compare(&log, &tampered), Err(5));
+        assert_eq!(Game::replay_compare(&log, &golden[..7]), Err(7));
+
+        // An action that was legal when logged but no longer applies
+        let mut diverged = log.clone();
+        diverged.actions.insert(3, Action::Take);
+        assert!(matches!(Game::replay_compare(&diverged, &golden), Err(i) if i <= 4));
+    }
+
+    #[test]
+    fn test_empty_handed_attacker_passes() {
+        // The attacker has no cards but the deck is not empty, which play never reaches
+        let hand2 = vec![card(7, Suit::Hearts), card(9, Suit::Spades)];
+        let mut game = game_with(Vec::new(), hand2, Suit::Clubs, 10);
+        assert!(!game.is_over());
+        assert_eq!(game.turn_kind(), Some(TurnKind::Pass));
+        // Counted before the list is built, so the cached list is not used
+        assert_eq!(game.num_legal_actions(), 1);
+        assert_eq!(game.legal_actions().0, vec![Action::StopAttack]);
+
+        game.step(Action::StopAttack).unwrap();
+        // The attacker drew a fresh hand and the defender opens the next bout
+        assert_eq!(game.game_state.hand1.0.len(), 6);
+        assert_eq!(game.game_state.hand2.0.len(), 6);
+        assert_eq!(game.game_state.acting_player, GamePlayer::Player2);
+        assert_eq!(game.game_state.defending_player, GamePlayer::Player1);
+        assert_eq!(game.turn_kind(), Some(TurnKind::Opening));
+
+        game.play(
+            Box::new(RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(1))))),
+            Box::new(RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(2))))),
+        )
+        .unwrap();
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn test_play_ends_as_draw_without_an_action() {
+        use crate::game::player::ReplayPlayer;
+        // Every state that is not over has a legal action now that an empty-handed attacker
+        // passes, so the draw comes from a player with no action to give
+        let mut game = game_with(Vec::new(), vec![card(7, Suit::Hearts)], Suit::Clubs, 8);
+        let rewards = game.play(
+            Box::new(ReplayPlayer::new([Action::StopAttack])),
+            Box::new(ReplayPlayer::new([])),
+        );
+        assert_eq!(rewards, Ok((0.0, 0.0)));
+        assert_eq!(game.actions, vec![Action::StopAttack]);
+        assert!(!game.is_over());
+
+        let turn = game.play_turn(&mut ReplayPlayer::new([]), &mut ReplayPlayer::new([]));
+        assert_eq!(turn, Ok(TurnOutcome::NoAction(GamePlayer::Player2)));
+    }
+
+    #[test]
+    fn test_step_reward_follows_reward_config() {
+        let hand1 = vec![card(6, Suit::Hearts)];
+        let hand2 = vec![card(7, Suit::Hearts), card(8, Suit::Spades)];
+        let mut game = game_with(hand1, hand2, Suit::Clubs, 0);
+        let rewards = RewardConfig {
+            win: 2.0,
+            step_penalty: 0.1,
+            hand_lead_weight: 0.5,
+        };
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert_eq!(game.step_reward(GamePlayer::Player1, &rewards), 0.0);
+
+        // Player1 sheds its last card, but the game waits for the defense
+        game.step(Action::Attack(card(6, Suit::Hearts))).unwrap();
+        assert!(close(game.step_reward(GamePlayer::Player1, &rewards), 0.4));
+        assert!(close(
+            game.step_reward(GamePlayer::Player1, &RewardConfig::default()),
+            0.0
+        ));
+        game.step(Action::StopAttack).unwrap();
+        assert!(close(game.step_reward(GamePlayer::Player1, &rewards), -0.1));
+
+        game.step(Action::Defend(card(7, Suit::Hearts))).unwrap();
+        assert!(game.is_over());
+        assert!(close(game.step_reward(GamePlayer::Player1, &rewards), 1.4));
+        assert!(close(game.step_reward(GamePlayer::Player2, &rewards), -1.6));
+        assert_eq!(
+            game.step_reward(GamePlayer::Player1, &RewardConfig::default()),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_estimate_win_prob_favors_winning_side() {
+        // Player1's two top trumps cannot be beaten, so every playout ends in their win
+        let game = game_with(
+            vec![card(14, Suit::Spades), card(13, Suit::Spades)],
+            [6, 7, 8, 9, 10, 11]
+                .map(|rank| card(rank, Suit::Hearts))
+                .to_vec(),
+            Suit::Spades,
+            0,
+        );
+        let before = game.game_state.clone();
+        let mut rng = StdRng::seed_from_u64(0);
+        let policy = || Box::new(RandomPlayer::new(None)) as Box<dyn Player>;
+
+        let (win1, win2) = game.estimate_win_prob(GamePlayer::Player1, 50, policy, &mut rng);
+        assert!(win1 > 0.9, "{}", win1);
+        assert_eq!(win2, 0.0);
+        assert_eq!(game.game_state, before);
+    }
 }
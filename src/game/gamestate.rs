@@ -1,13 +1,38 @@
 use core::fmt;
-use std::ops;
+use std::{
+    collections::{HashMap, HashSet},
+    ops,
+    str::FromStr,
+};
 
 use super::{
-    cards::{Card, Deck, Hand},
+    actions::Action,
+    card_set::CardSet,
+    cards::{Card, Deck, Hand, Suit, NUM_CARDS},
+    config::{HandEncoding, OpponentCount},
+    game::beats,
     utils::indices_to_bitmap_as_array1,
 };
 use ndarray::{concatenate, Array1, Array2};
 use serde::{Deserialize, Serialize};
 
+/// Version of the `ObservableGameState::to_numpy` layout. Bump it, and `ENCODED_STATE_LEN`
+/// if the length changes, whenever the layout changes so that saved data and trained models
+/// can detect that they no longer match. Each version has one layout per `HandEncoding`, so
+/// saved data must record the hand encoding alongside it.
+pub const ENCODING_VERSION: u32 = 4;
+
+/// Length of the `ObservableGameState::to_numpy` encoding for `ENCODING_VERSION`, with the
+/// default `HandEncoding::Full`.
+pub const ENCODED_STATE_LEN: usize = 205;
+
+/// The most attacks there can be on the table, and so the number of pairs in the encoding.
+const TABLE_SLOTS: usize = 6;
+
+/// Length of the encoding with `HandEncoding::Aggregate`: the 36-card hand bitmap is
+/// replaced by 14 counts.
+pub const AGGREGATE_STATE_LEN: usize = ENCODED_STATE_LEN - 36 + 14;
+
 #[derive(Clone, PartialEq, Copy, Debug, Serialize, Deserialize)]
 pub enum GamePlayer {
     Player1,
@@ -31,8 +56,21 @@ impl ops::Not for GamePlayer {
     }
 }
 
+impl FromStr for GamePlayer {
+    type Err = String;
+
+    /// Parse a player from its name as written by `Debug`, e.g. `"Player1"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Player1" => Ok(GamePlayer::Player1),
+            "Player2" => Ok(GamePlayer::Player2),
+            _ => Err(format!("Unknown player {:?}", s)),
+        }
+    }
+}
+
 // ignore unused variable for now
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ObservableGameState {
     pub player: GamePlayer,
     pub num_cards_in_deck: u8,
@@ -43,46 +81,301 @@ pub struct ObservableGameState {
     pub defender_has_taken: bool,
     pub acting_player: GamePlayer,
     pub defender: GamePlayer,
+    /// The opponent's hand size, or the lowest count of its bucket if `opponent_count`
+    /// buckets it.
     pub cards_in_opponent: u8,
+    #[serde(default)]
+    pub bout: u32,
+    #[serde(default)]
+    pub opponent_void_suits: Vec<Suit>,
+    /// The opponent's actual hand, only set in open-hands (cheat/debug) mode, see
+    /// `GameConfig::reveal_opponent`.
+    #[serde(default)]
+    pub opponent_hand: Option<Hand>,
+    /// The face-up trump card while it is still at the bottom of the stock, for UIs.
+    /// `visible_card` keeps showing the trump after it has been drawn.
+    #[serde(default)]
+    pub face_up_card: Option<Card>,
+    /// How `to_numpy` encodes `hand`, see `GameConfig::observation`.
+    #[serde(default)]
+    pub hand_encoding: HandEncoding,
+    /// The attacks on the table still waiting to be beaten, see `GameState::num_undefended`.
+    #[serde(default)]
+    pub undefended_count: u8,
+    /// How precisely `cards_in_opponent` is reported, see `GameConfig::observation`.
+    #[serde(default)]
+    pub opponent_count: OpponentCount,
+    /// The ranks in the deck, see `GameConfig::rank_set`.
+    #[serde(default = "full_deck_ranks")]
+    pub deck_ranks: Vec<u8>,
+}
+
+/// The ranks of the full 36-card deck, for observations made without a config.
+pub(crate) fn full_deck_ranks() -> Vec<u8> {
+    (6..15).collect()
 }
 
 impl ObservableGameState {
-    #[allow(dead_code)]
-    pub fn to_numpy(self) -> Result<Array1<u8>, String> {
-        let hand_arr = <Hand as Into<Array1<u8>>>::into(self.hand);
+    /// Suits in which the opponent is believed unable to beat an attack, deduced from them
+    /// taking against a card of that suit since they last drew from the deck. A player may
+    /// choose to take when they could have defended, so this is a strong hint rather than
+    /// a certainty.
+    pub fn opponent_void_suits(&self) -> Vec<Suit> {
+        self.opponent_void_suits.clone()
+    }
+
+    /// The cards the opponent could be holding as far as this observation shows: every card
+    /// of the deck's ranks except our hand, the table and the face-up trump while it is still
+    /// under the deck. Cards discarded in earlier bouts can't be told apart from this state alone,
+    /// so they are included. In open-hands mode it is just the opponent's hand.
+    pub fn opponent_possible_cards(&self) -> CardSet {
+        if let Some(hand) = &self.opponent_hand {
+            return CardSet::from(hand);
+        }
+        let mut seen: CardSet = self
+            .attack_table
+            .iter()
+            .chain(self.defense_table.iter())
+            .copied()
+            .collect();
+        seen = seen.union(CardSet::from(&self.hand));
+        if let Some(card) = self.face_up_card {
+            seen.insert(card);
+        }
+        CardSet::of_ranks(&self.deck_ranks).difference(seen)
+    }
+
+    /// How much is unknown about the opponent's hand, in bits: log2 of the number of ways
+    /// to pick their cards from `opponent_possible_cards`, summed over every hand size in
+    /// `cards_in_opponent_range` when the count is bucketed.
+    pub fn hidden_entropy(&self) -> f64 {
+        let n = self.opponent_possible_cards().len();
+        let (low, high) = self.cards_in_opponent_range();
+        let (low, high) = ((low as usize).min(n), (high as usize).min(n));
+        // log2 C(n, k) as a sum, so large counts don't overflow. C(n, k) = C(n, n - k), and
+        // the shorter sum is exactly 0 when the hand is known.
+        let log2_choose = |k: usize| -> f64 {
+            let k = k.min(n - k);
+            (0..k)
+                .map(|i| ((n - i) as f64 / (i + 1) as f64).log2())
+                .sum()
+        };
+        if low == high {
+            return log2_choose(low);
+        }
+        // Summed relative to the largest term, so the powers stay in range
+        let terms: Vec<f64> = (low..=high).map(log2_choose).collect();
+        let largest = terms.iter().copied().fold(f64::MIN, f64::max);
+        largest
+            + terms
+                .iter()
+                .map(|t| (t - largest).exp2())
+                .sum::<f64>()
+                .log2()
+    }
+
+    /// The smallest and largest number of cards the opponent may be holding, which are the
+    /// same unless `cards_in_opponent` is bucketed.
+    pub fn cards_in_opponent_range(&self) -> (u8, u8) {
+        self.opponent_count.range(self.cards_in_opponent)
+    }
+
+    /// The table as (attack, defense) pairs in the order the attacks were laid, with `None`
+    /// for attacks that are still undefended.
+    pub fn table(&self) -> Vec<(Card, Option<Card>)> {
+        self.attack_table
+            .iter()
+            .enumerate()
+            .map(|(i, attack)| (*attack, self.defense_table.get(i).copied()))
+            .collect()
+    }
+
+    /// For each undefended attack, the cheapest card in our hand that beats it: the lowest
+    /// non-trump if there is one, else the lowest trump, and `None` if nothing does. Each
+    /// attack is answered on its own, so one card may be suggested for several.
+    pub fn min_defense_for_each(&self) -> Vec<(Card, Option<Card>)> {
+        let trump = self.visible_card.suit;
+        self.attack_table
+            .iter()
+            .skip(self.defense_table.len())
+            .map(|attack| {
+                let defense = self
+                    .hand
+                    .0
+                    .iter()
+                    .filter(|card| beats(card, attack, trump))
+                    .min_by_key(|card| (card.is_trump(trump), card.rank))
+                    .copied();
+                (*attack, defense)
+            })
+            .collect()
+    }
+
+    /// The table pairs for the encoding: six (attack, defense) slots of card index + 1, with
+    /// 0 for an empty slot or a missing defense.
+    fn table_pairs(&self) -> Array1<u8> {
+        let mut pairs = Array1::zeros(2 * TABLE_SLOTS);
+        let slot = |card: Card| usize::from(card) as u8 + 1;
+        for (i, (attack, defense)) in self.table().into_iter().take(TABLE_SLOTS).enumerate() {
+            pairs[2 * i] = slot(attack);
+            pairs[2 * i + 1] = defense.map_or(0, slot);
+        }
+        pairs
+    }
+
+    /// The pieces of the encoding in order, each with the factor that scales it into [0, 1].
+    /// Bitmaps are already 0/1; counts are scaled by the size of the deck.
+    ///
+    /// The layout, for `HandEncoding::Full`: acting player (2), hand (36), attack table (36),
+    /// defense table (36), table pairs (12, see `table_pairs`), undefended attacks (1), cards
+    /// in deck (1), trump card (36), defender has taken (1), defender (2), cards in opponent's
+    /// hand (1, the lowest count of its bucket if bucketed), bout (1), opponent void suits
+    /// (4) and opponent hand (36), 205 values in all.
+    fn encoded_parts(&self) -> Vec<(Array1<u8>, f32)> {
+        let count_scale = 1.0 / NUM_CARDS as f32;
+        let hand_part = match self.hand_encoding {
+            HandEncoding::Full => (<Hand as Into<Array1<u8>>>::into(self.hand.clone()), 1.0),
+            HandEncoding::Aggregate => (self.hand_counts(), count_scale),
+        };
         let player_acting_arr = indices_to_bitmap_as_array1(vec![self.acting_player as usize], 2);
-        let attack_table_arr = <Hand as Into<Array1<u8>>>::into(Hand(self.attack_table));
-        let defense_table_arr = <Hand as Into<Array1<u8>>>::into(Hand(self.defense_table));
-        let visible_card_arr =
-            <Hand as Into<Array1<u8>>>::into(Hand(vec![<Card as Clone>::clone(
-                &self.visible_card,
-            )]));
+        let attack_table_arr = <Hand as Into<Array1<u8>>>::into(Hand(self.attack_table.clone()));
+        let defense_table_arr = <Hand as Into<Array1<u8>>>::into(Hand(self.defense_table.clone()));
+        let visible_card_arr = <Hand as Into<Array1<u8>>>::into(Hand(vec![self.visible_card]));
         let defender_arr = indices_to_bitmap_as_array1(vec![self.defender as usize], 2);
         let defender_has_taken_arr = Array1::from_vec(vec![self.defender_has_taken as u8]);
+        let undefended_arr = Array1::from_vec(vec![self.undefended_count]);
         let deck_size_arr = Array1::from_vec(vec![self.num_cards_in_deck]);
         let cards_in_opp_arr = Array1::from_vec(vec![self.cards_in_opponent]);
+        let bout_arr = Array1::from_vec(vec![self.bout.min(u8::MAX as u32) as u8]);
+        let void_suits_arr = indices_to_bitmap_as_array1(
+            self.opponent_void_suits
+                .iter()
+                .map(|suit| u8::from(*suit) as usize)
+                .collect(),
+            4,
+        );
+        // All zeros while the opponent's hand is hidden
+        let opponent_hand_arr = <Hand as Into<Array1<u8>>>::into(
+            self.opponent_hand.clone().unwrap_or(Hand(Vec::new())),
+        );
+        vec![
+            (player_acting_arr, 1.0),
+            hand_part,
+            (attack_table_arr, 1.0),
+            (defense_table_arr, 1.0),
+            (self.table_pairs(), count_scale),
+            (undefended_arr, count_scale),
+            (deck_size_arr, count_scale),
+            (visible_card_arr, 1.0),
+            (defender_has_taken_arr, 1.0),
+            (defender_arr, 1.0),
+            (cards_in_opp_arr, count_scale),
+            (bout_arr, count_scale),
+            (void_suits_arr, 1.0),
+            (opponent_hand_arr, 1.0),
+        ]
+    }
+
+    /// The `HandEncoding::Aggregate` hand: cards per suit, cards per rank from 6 up, then
+    /// the number of trumps.
+    fn hand_counts(&self) -> Array1<u8> {
+        let mut counts = Array1::zeros(14);
+        for card in self.hand.0.iter() {
+            counts[u8::from(card.suit) as usize] += 1;
+            counts[4 + (card.rank - 6) as usize] += 1;
+        }
+        counts[13] = self.hand.split_trumps(self.visible_card.suit).0.len() as u8;
+        counts
+    }
+
+    #[allow(dead_code)]
+    pub fn to_numpy(self) -> Result<Array1<u8>, String> {
+        let parts = self.encoded_parts();
         let cat = concatenate(
             ndarray::Axis(0),
-            &[
-                player_acting_arr.view(),
-                hand_arr.view(),
-                attack_table_arr.view(),
-                defense_table_arr.view(),
-                deck_size_arr.view(),
-                visible_card_arr.view(),
-                defender_has_taken_arr.view(),
-                defender_arr.view(),
-                cards_in_opp_arr.view(),
-            ],
+            &parts.iter().map(|(arr, _)| arr.view()).collect::<Vec<_>>(),
         );
         match cat {
             Ok(a) => Ok(a as Array1<u8>),
             Err(_e) => Err(String::from("Shape Error")),
         }
     }
+
+    /// The same layout as `to_numpy`, as `f32` features for neural networks: bitmaps become
+    /// 0.0/1.0 and counts are divided by the size of the deck.
+    pub fn to_numpy_f32(&self) -> Array1<f32> {
+        self.encoded_parts()
+            .into_iter()
+            .flat_map(|(arr, scale)| arr.into_iter().map(move |x| (x as f32 * scale).min(1.0)))
+            .collect()
+    }
 }
 
-#[derive(Clone, Debug)]
+impl ObservableGameState {
+    /// A readable board for debugging, e.g.
+    ///
+    /// ```text
+    /// Bout 2, Player1 attacks, Player1 to act
+    /// Trump: 10♠  Deck: 12  Opponent holds: 6
+    /// Table: 7♥/9♥ 7♣/--
+    /// Hand:  6♦ 8♣ J♠
+    /// ```
+    ///
+    /// With `ascii` set, suits are written as letters (`10S`) instead of symbols.
+    pub fn render(&self, ascii: bool) -> String {
+        let label = |card: &Card| match ascii {
+            true => format!("{}{}", card.rank_name(), &card.suit_name()[..1]),
+            false => format!("{:?}", card),
+        };
+        let cards = |cards: &[Card]| {
+            let mut sorted = cards.to_vec();
+            sorted.sort();
+            sorted.iter().map(label).collect::<Vec<_>>().join(" ")
+        };
+        let table = self
+            .attack_table
+            .iter()
+            .enumerate()
+            .map(|(i, attack)| match self.defense_table.get(i) {
+                Some(defense) => format!("{}/{}", label(attack), label(defense)),
+                None => format!("{}/--", label(attack)),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut lines = vec![
+            format!(
+                "Bout {}, {:?} attacks, {:?} to act{}",
+                self.bout,
+                self.defender.other(),
+                self.acting_player,
+                match self.defender_has_taken {
+                    true => " (defender takes)",
+                    false => "",
+                }
+            ),
+            format!(
+                "Trump: {}  Deck: {}  Opponent holds: {}",
+                label(&self.visible_card),
+                self.num_cards_in_deck,
+                self.cards_in_opponent
+            ),
+            format!("Table: {}", table),
+            format!("Hand:  {}", cards(&self.hand.0)),
+        ];
+        if let Some(hand) = &self.opponent_hand {
+            lines.push(format!("Opponent: {}", cards(&hand.0)));
+        }
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for ObservableGameState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ObservableGameHistory(pub Vec<ObservableGameState>);
 
 impl ObservableGameHistory {
@@ -122,13 +415,22 @@ pub struct GameState {
     pub visible_card: Card,
     pub defender_has_taken: bool,
     pub graveyard: Vec<Card>,
+    /// The number of bouts resolved so far, so the first bout is bout 0.
+    #[serde(default)]
+    pub bout: u32,
+    /// Suits each player has taken against since they last drew, see
+    /// `ObservableGameState::opponent_void_suits`.
+    #[serde(default)]
+    pub void_suits1: Vec<Suit>,
+    #[serde(default)]
+    pub void_suits2: Vec<Suit>,
 }
 
 impl fmt::Debug for GameState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "{{\n\tDeck: {:?}\n\tAttack: {:?}\n\tDefense: {:?}\n\tHand1: {:?}\n\tHand2: {:?}\n\tActing: {:?}\n\tDefending: {:?}\n\tVisible: {:?}\n\tDefender has taken: {}\n\tGraveyard: {:?}\n}}",
+            "{{\n\tDeck: {:?}\n\tAttack: {:?}\n\tDefense: {:?}\n\tHand1: {:?}\n\tHand2: {:?}\n\tActing: {:?}\n\tDefending: {:?}\n\tVisible: {:?}\n\tDefender has taken: {}\n\tGraveyard: {:?}\n\tBout: {}\n}}",
             self.deck,
             self.attack_table,
             self.defense_table,
@@ -139,6 +441,7 @@ impl fmt::Debug for GameState {
             self.visible_card,
             self.defender_has_taken,
             self.graveyard,
+            self.bout,
         )
     }
 }
@@ -167,6 +470,9 @@ impl GameState {
             visible_card,
             defender_has_taken,
             graveyard,
+            bout: 0,
+            void_suits1: Vec::new(),
+            void_suits2: Vec::new(),
         }
     }
 
@@ -213,16 +519,22 @@ impl GameState {
     }
 
     pub fn observe(&self, player: GamePlayer) -> ObservableGameState {
-        let hand = match player {
-            GamePlayer::Player1 => self.hand1.clone(),
-            GamePlayer::Player2 => self.hand2.clone(),
+        self.observe_with(player, false)
+    }
+
+    /// Observe as `player`, including the opponent's hand if `reveal_opponent` is set. This
+    /// is the open-hands cheat/debug mode; normal play uses `observe`.
+    pub fn observe_with(&self, player: GamePlayer, reveal_opponent: bool) -> ObservableGameState {
+        let (hand, opponent_hand) = match player {
+            GamePlayer::Player1 => (&self.hand1, &self.hand2),
+            GamePlayer::Player2 => (&self.hand2, &self.hand1),
         };
         ObservableGameState {
             player,
             num_cards_in_deck: self.deck.len() as u8,
             attack_table: self.attack_table.clone(),
             defense_table: self.defense_table.clone(),
-            hand,
+            hand: hand.clone(),
             visible_card: self.visible_card.clone(),
             defender_has_taken: self.defender_has_taken,
             acting_player: self.acting_player.clone(),
@@ -231,13 +543,167 @@ impl GameState {
                 GamePlayer::Player1 => self.hand2.0.len() as u8,
                 GamePlayer::Player2 => self.hand1.0.len() as u8,
             },
+            bout: self.bout,
+            opponent_void_suits: match player {
+                GamePlayer::Player1 => self.void_suits2.clone(),
+                GamePlayer::Player2 => self.void_suits1.clone(),
+            },
+            opponent_hand: reveal_opponent.then(|| opponent_hand.clone()),
+            face_up_card: self.deck.peek_bottom().copied(),
+            hand_encoding: HandEncoding::default(),
+            undefended_count: self.num_undefended(),
+            opponent_count: OpponentCount::default(),
+            deck_ranks: full_deck_ranks(),
         }
     }
 
     pub fn num_undefended(&self) -> u8 {
         let num_attack = self.attack_table.len() as u8;
         let num_defend = self.defense_table.len() as u8;
-        num_attack - num_defend
+        num_attack.saturating_sub(num_defend)
+    }
+
+    /// Compare two states as game positions. Hands, the graveyard and the deck are compared
+    /// as sets of cards, while the tables, the trump card and the turn flags must match exactly.
+    ///
+    /// Use this when two states should count as the same position even though they were
+    /// reached differently (e.g. in tests). Use `==` when the exact deck order matters, such
+    /// as when checking that a replay reproduces the same future draws.
+    pub fn logically_eq(&self, other: &GameState) -> bool {
+        fn sorted(cards: &[Card]) -> Vec<Card> {
+            let mut cards = cards.to_vec();
+            cards.sort();
+            cards
+        }
+        self.hand1 == other.hand1
+            && self.hand2 == other.hand2
+            && self.attack_table == other.attack_table
+            && self.defense_table == other.defense_table
+            && sorted(&self.graveyard) == sorted(&other.graveyard)
+            && sorted(&self.deck.cards) == sorted(&other.deck.cards)
+            && self.visible_card == other.visible_card
+            && self.acting_player == other.acting_player
+            && self.defending_player == other.defending_player
+            && self.defender_has_taken == other.defender_has_taken
+            && self.bout == other.bout
+    }
+
+    /// The number of cards anywhere in the state: deck, tables, hands and graveyard.
+    pub fn total_cards(&self) -> usize {
+        self.deck.len()
+            + self.attack_table.len()
+            + self.defense_table.len()
+            + self.hand1.0.len()
+            + self.hand2.0.len()
+            + self.graveyard.len()
+    }
+
+    /// Check the structural invariants of the state: no card may appear in more than one
+    /// place and the defense table can never be longer than the attack table.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.defense_table.len() > self.attack_table.len() {
+            return Err(format!(
+                "Defense table has {} cards but attack table only {}",
+                self.defense_table.len(),
+                self.attack_table.len()
+            ));
+        }
+        let mut seen = HashMap::new();
+        for (place, card) in self.placed_cards() {
+            if let Some(first) = seen.insert(*card, place) {
+                return Err(format!(
+                    "Duplicate card {:?} in {} and {}",
+                    card, first, place
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `validate`, but also check that the state holds exactly the cards of a deck of
+    /// `ranks`, with none missing and none from outside it, and that the trump card lies
+    /// at the bottom of the deck while there is one.
+    pub fn validate_complete(&self, ranks: &[u8]) -> Result<(), String> {
+        self.validate()?;
+        if let Some(bottom) = self.deck.peek_bottom() {
+            if *bottom != self.visible_card {
+                return Err(format!(
+                    "Trump {:?} is not the bottom card of the deck {:?}",
+                    self.visible_card, bottom
+                ));
+            }
+        }
+        let cards: HashSet<Card> = self.all_cards().copied().collect();
+        let expected = Deck::from_rank_set(ranks).cards;
+        if let Some(card) = expected.iter().find(|card| !cards.contains(card)) {
+            return Err(format!("Missing card {:?}", card));
+        }
+        if let Some(card) = cards.iter().find(|card| !ranks.contains(&card.rank)) {
+            return Err(format!("Card {:?} is not in the deck", card));
+        }
+        Ok(())
+    }
+
+    /// The action that turned `prev` into `next`, for consecutive states of a game's
+    /// history. A card leaving the acting player's hand gives away an attack or a defense,
+    /// since refills only ever add cards; otherwise the attacker stopped or the defender
+    /// took. Returns None if `next` cannot follow `prev` in a single step.
+    pub fn infer_action(prev: &GameState, next: &GameState) -> Option<Action> {
+        let actor = prev.acting_player;
+        let defending = actor == prev.defending_player;
+        let after = next.hand_of(actor);
+        let played: Vec<Card> = prev
+            .hand_of(actor)
+            .0
+            .iter()
+            .filter(|card| !after.0.contains(card))
+            .copied()
+            .collect();
+        let bout_resolved = next.bout > prev.bout;
+        match (defending, played.as_slice()) {
+            (false, [card]) if next.attack_table.last() == Some(card) => {
+                Some(Action::Attack(*card))
+            }
+            (true, [card])
+                if next.defense_table.contains(card) || next.graveyard.contains(card) =>
+            {
+                Some(Action::Defend(*card))
+            }
+            (false, []) if bout_resolved || next.acting_player != actor => Some(Action::StopAttack),
+            (true, [])
+                if !prev.defender_has_taken && (bout_resolved || next.defender_has_taken) =>
+            {
+                Some(Action::Take)
+            }
+            _ => None,
+        }
+    }
+
+    fn hand_of(&self, player: GamePlayer) -> &Hand {
+        match player {
+            GamePlayer::Player1 => &self.hand1,
+            GamePlayer::Player2 => &self.hand2,
+        }
+    }
+
+    fn all_cards(&self) -> impl Iterator<Item = &Card> {
+        self.placed_cards().map(|(_, card)| card)
+    }
+
+    /// Every card in the state together with where it lies.
+    fn placed_cards(&self) -> impl Iterator<Item = (&'static str, &Card)> {
+        fn place<'a>(
+            name: &'static str,
+            cards: &'a [Card],
+        ) -> impl Iterator<Item = (&'static str, &'a Card)> {
+            cards.iter().map(move |card| (name, card))
+        }
+        place("the deck", &self.deck.cards)
+            .chain(place("the attack table", &self.attack_table))
+            .chain(place("the defense table", &self.defense_table))
+            .chain(place("Player1's hand", &self.hand1.0))
+            .chain(place("Player2's hand", &self.hand2.0))
+            .chain(place("the graveyard", &self.graveyard))
     }
 
     fn _defender_hand(&self) -> &Hand {
@@ -254,3 +720,283 @@ impl GameState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::game::card_set::CardSet;
+    use crate::game::cards::{Card, Suit};
+    use crate::game::config::{GameConfig, HandEncoding, OpponentCount};
+    use crate::game::game::{Game, GameLogic};
+    use crate::game::gamestate::{GamePlayer, GameState};
+
+    #[test]
+    fn test_to_numpy_f32_matches_layout_and_is_normalized() {
+        let game = Game::new_seeded(8);
+        let mut state = game.game_state().observe(GamePlayer::Player1);
+        state.bout = 500;
+        let encoded = state.clone().to_numpy().unwrap();
+        let features = state.to_numpy_f32();
+
+        assert_eq!(features.len(), encoded.len());
+        assert!(features.iter().all(|x| (0.0..=1.0).contains(x)));
+        // Bitmaps carry over unchanged, e.g. the acting player comes first
+        assert_eq!(features[0], encoded[0] as f32);
+    }
+
+    #[test]
+    fn test_infer_action_recovers_every_action_type() {
+        let mut seen = HashSet::new();
+        for seed in 0..20 {
+            let mut game = Game::new_seeded(seed);
+            let mut rng = StdRng::seed_from_u64(seed);
+            while !game.is_over() {
+                let actions = game.legal_actions().0;
+                let action = actions[rng.gen_range(0..actions.len())];
+                game.step(action).unwrap();
+            }
+            for (i, action) in game.actions.iter().enumerate() {
+                let (prev, next) = (&game.history[i], &game.history[i + 1]);
+                assert_eq!(GameState::infer_action(prev, next), Some(*action));
+                seen.insert(std::mem::discriminant(action));
+            }
+        }
+        assert_eq!(seen.len(), 4);
+
+        let state = Game::new_seeded(0).game_state().clone();
+        assert_eq!(GameState::infer_action(&state, &state), None);
+    }
+
+    #[test]
+    fn test_encoding_length_matches_version() {
+        // If this fails, the layout changed: update ENCODED_STATE_LEN and bump ENCODING_VERSION
+        let game = Game::new_seeded(8);
+        let state = game.game_state().observe(GamePlayer::Player1);
+        assert_eq!(state.to_numpy().unwrap().len(), super::ENCODED_STATE_LEN);
+        assert_eq!(super::ENCODING_VERSION, 4);
+    }
+
+    #[test]
+    fn test_table_pairs_partially_defended() {
+        let card = |rank, suit| Card { suit, rank };
+        let mut state = Game::new_seeded(8)
+            .game_state()
+            .observe(GamePlayer::Player1);
+        state.attack_table = vec![card(7, Suit::Hearts), card(7, Suit::Clubs)];
+        state.defense_table = vec![card(9, Suit::Hearts)];
+        assert_eq!(
+            state.table(),
+            vec![
+                (card(7, Suit::Hearts), Some(card(9, Suit::Hearts))),
+                (card(7, Suit::Clubs), None),
+            ]
+        );
+
+        // The pairs follow the acting player (2), hand and both table bitmaps (3 * 36)
+        let encoded = state.to_numpy().unwrap();
+        let pairs = encoded.slice(ndarray::s![110..122]).to_vec();
+        let slot = |card: Card| usize::from(card) as u8 + 1;
+        let mut expected = vec![
+            slot(card(7, Suit::Hearts)),
+            slot(card(9, Suit::Hearts)),
+            slot(card(7, Suit::Clubs)),
+            0,
+        ];
+        expected.resize(12, 0);
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn test_aggregate_hand_encoding_counts_suits() {
+        let mut game = Game::new_seeded(8);
+        game.config_mut().observation.hand = HandEncoding::Aggregate;
+        let state = game.observe(GamePlayer::Player1);
+        let encoded = state.clone().to_numpy().unwrap();
+        assert_eq!(encoded.len(), super::AGGREGATE_STATE_LEN);
+        assert!(encoded.len() < super::ENCODED_STATE_LEN);
+
+        // The hand follows the 2-value acting player bitmap
+        let counts = encoded.slice(ndarray::s![2..16]);
+        for suit in [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs] {
+            let in_suit = state.hand.0.iter().filter(|card| card.suit == suit).count();
+            assert_eq!(counts[u8::from(suit) as usize] as usize, in_suit);
+        }
+        let trumps = state
+            .hand
+            .0
+            .iter()
+            .filter(|card| card.suit == state.visible_card.suit);
+        assert_eq!(counts[13] as usize, trumps.count());
+        assert_eq!(
+            counts.slice(ndarray::s![4..13]).sum() as usize,
+            state.hand.0.len()
+        );
+    }
+
+    #[test]
+    fn test_debug_output_names_the_fields() {
+        let state = Game::new_seeded(8)
+            .game_state()
+            .observe(GamePlayer::Player1);
+        let debug = format!("{:?}", state);
+        assert!(debug.starts_with("ObservableGameState {"));
+        assert!(debug.contains(&format!("visible_card: {:?}", state.visible_card)));
+    }
+
+    #[test]
+    fn test_min_defense_for_each_prefers_low_non_trumps() {
+        let card = |rank, suit| Card { suit, rank };
+        let mut state = Game::new_seeded(1)
+            .game_state()
+            .observe(GamePlayer::Player2);
+        state.visible_card = card(9, Suit::Clubs);
+        state.hand.0 = vec![
+            card(6, Suit::Clubs),
+            card(12, Suit::Hearts),
+            card(14, Suit::Clubs),
+            card(9, Suit::Hearts),
+        ];
+        // 6♠ is already beaten, and only trumps answer 8♠ and K♥
+        state.attack_table = vec![
+            card(6, Suit::Spades),
+            card(7, Suit::Hearts),
+            card(8, Suit::Spades),
+            card(10, Suit::Clubs),
+            card(13, Suit::Hearts),
+        ];
+        state.defense_table = vec![card(7, Suit::Spades)];
+        assert_eq!(
+            state.min_defense_for_each(),
+            vec![
+                (card(7, Suit::Hearts), Some(card(9, Suit::Hearts))),
+                (card(8, Suit::Spades), Some(card(6, Suit::Clubs))),
+                (card(10, Suit::Clubs), Some(card(14, Suit::Clubs))),
+                (card(13, Suit::Hearts), Some(card(6, Suit::Clubs))),
+            ]
+        );
+
+        state.hand.0 = vec![card(12, Suit::Hearts)];
+        assert_eq!(
+            state.min_defense_for_each(),
+            vec![
+                (card(7, Suit::Hearts), Some(card(12, Suit::Hearts))),
+                (card(8, Suit::Spades), None),
+                (card(10, Suit::Clubs), None),
+                (card(13, Suit::Hearts), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hidden_entropy_counts_opponent_hands() {
+        let game = Game::new_seeded(4);
+        let obs = game.game_state().observe(GamePlayer::Player1);
+        // 36 cards less our 6 and the face-up trump leave 29 the opponent's 6 come from
+        assert_eq!(obs.opponent_possible_cards().len(), 29);
+        let hands: f64 = (0..6).map(|i| (29 - i) as f64 / (i + 1) as f64).product();
+        assert_eq!(hands.round(), 475_020.0);
+        assert!((obs.hidden_entropy() - hands.log2()).abs() < 1e-9);
+
+        let revealed = game.game_state().observe_with(GamePlayer::Player1, true);
+        assert_eq!(revealed.hidden_entropy(), 0.0);
+    }
+
+    #[test]
+    fn test_opponent_possible_cards_follow_the_deck_ranks() {
+        let config = GameConfig {
+            lowest_rank: 10,
+            ..GameConfig::default()
+        };
+        let game = Game::with_config_seeded(config, 4).unwrap();
+        let obs = game.observe(GamePlayer::Player1);
+        // 20 cards less our 6 and the face-up trump leave 13 the opponent's 6 come from
+        let possible = obs.opponent_possible_cards();
+        assert_eq!(possible.len(), 13);
+        assert_eq!(possible.difference(CardSet::full(10)), CardSet::EMPTY);
+        let hands: f64 = (0..6).map(|i| (13 - i) as f64 / (i + 1) as f64).product();
+        assert_eq!(hands.round(), 1716.0);
+        assert!((obs.hidden_entropy() - hands.log2()).abs() < 1e-9);
+
+        // A bucketed count leaves every hand size in the bucket possible
+        let mut bucketed = obs.clone();
+        bucketed.opponent_count = OpponentCount::Bucketed(3);
+        let sizes: f64 = [1716.0, 1716.0, 1287.0].iter().sum();
+        assert!((bucketed.hidden_entropy() - sizes.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_render_shows_trump_and_hand() {
+        let game = Game::new_seeded(8);
+        let state = game.game_state().observe(GamePlayer::Player1);
+        let board = state.to_string();
+        assert!(board.contains(&format!("Trump: {:?}", state.visible_card)));
+        assert!(board.contains(&format!("{:?}", state.hand.0[0])));
+        assert!(state.render(true).is_ascii());
+    }
+
+    #[test]
+    fn test_logically_eq_ignores_deck_and_hand_order() {
+        let game = Game::new();
+        let state = game.game_state().clone();
+        let mut reordered = state.clone();
+        reordered.deck.cards.reverse();
+        reordered.hand1.0.reverse();
+        reordered.graveyard.reverse();
+
+        assert_ne!(state, reordered);
+        assert!(state.logically_eq(&reordered));
+    }
+
+    #[test]
+    fn test_logically_eq_detects_different_positions() {
+        let game = Game::new();
+        let state = game.game_state().clone();
+        let mut different = state.clone();
+        let card = different.hand1.0.pop().unwrap();
+        different.attack_table.push(card);
+
+        assert_ne!(state, different);
+        assert!(!state.logically_eq(&different));
+
+        let mut other_turn = state.clone();
+        other_turn.acting_player = !other_turn.acting_player;
+        assert!(!state.logically_eq(&other_turn));
+    }
+
+    #[test]
+    fn test_validate_names_duplicated_table_card() {
+        let ranks = GameConfig::default().rank_set();
+        let mut state = Game::new_seeded(3).game_state().clone();
+        assert_eq!(state.validate_complete(&ranks), Ok(()));
+
+        let card = state.hand1.0[0];
+        state.attack_table.push(card);
+        assert_eq!(
+            state.validate_complete(&ranks),
+            Err(format!(
+                "Duplicate card {:?} in the attack table and Player1's hand",
+                card
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_complete_names_missing_card() {
+        let ranks = GameConfig::default().rank_set();
+        let mut state = Game::new_seeded(3).game_state().clone();
+        let card = state.hand2.0.pop().unwrap();
+        assert_eq!(state.validate(), Ok(()));
+        assert_eq!(
+            state.validate_complete(&ranks),
+            Err(format!("Missing card {:?}", card))
+        );
+
+        // The trump has to be the card under the deck
+        let mut state = Game::new_seeded(3).game_state().clone();
+        state.visible_card = state.hand2.0[0];
+        assert!(state.validate_complete(&ranks).is_err());
+    }
+}
@@ -0,0 +1,509 @@
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng, SeedableRng};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use super::{
+    actions::{Action, ActionList},
+    card_set::CardSet,
+    cards::{Card, Hand},
+    config::GameConfig,
+    game::{Game, GameLogic},
+    gamestate::{GamePlayer, GameState, ObservableGameHistory, ObservableGameState},
+    player::{Player, RandomPlayer},
+};
+
+/// Search parameters for `MctsPlayer`.
+#[derive(Clone, Debug)]
+pub struct MctsConfig {
+    /// Number of sampled deals of the hidden cards to search.
+    pub determinizations: usize,
+    /// UCT iterations per determinization.
+    pub iterations: usize,
+    /// UCT exploration constant.
+    pub exploration: f64,
+    /// Seed for the player's own RNG, from which every search is seeded.
+    pub seed: u64,
+    /// Search determinizations on a rayon pool with this many threads. Ignored unless the
+    /// `parallel` feature is enabled; `None` searches serially.
+    pub threads: Option<usize>,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            determinizations: 8,
+            iterations: 200,
+            exploration: 1.4,
+            seed: 0,
+            threads: None,
+        }
+    }
+}
+
+/// Visit count and total value (from the searching player's point of view) of one root action,
+/// summed over all determinizations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionStats {
+    pub action: Action,
+    pub visits: u32,
+    pub value: f64,
+}
+
+/// What a search found, for debugging and logging, see `Player::last_search_stats`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchStats {
+    /// Tree nodes built, summed over all determinizations.
+    pub nodes: usize,
+    /// The statistics of every legal action.
+    pub actions: Vec<ActionStats>,
+    /// The share of root visits that went to the most visited action.
+    pub confidence: f64,
+}
+
+/// Determinized Monte Carlo tree search: sample deals of the cards the player cannot see,
+/// run UCT on each as if it were a perfect-information game and pick the most visited action.
+pub struct MctsPlayer {
+    game_config: GameConfig,
+    config: MctsConfig,
+    rng: StdRng,
+    #[cfg(feature = "parallel")]
+    pool: Option<rayon::ThreadPool>,
+    last_stats: Option<SearchStats>,
+}
+
+impl MctsPlayer {
+    /// `game_config` must match the rules of the game being played, since the search
+    /// simulates refills and needs to know the full deck.
+    pub fn new(game_config: GameConfig, config: MctsConfig) -> MctsPlayer {
+        MctsPlayer {
+            rng: StdRng::seed_from_u64(config.seed),
+            #[cfg(feature = "parallel")]
+            pool: config.threads.map(|threads| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .unwrap()
+            }),
+            game_config,
+            config,
+            last_stats: None,
+        }
+    }
+
+    /// Search the position and return the statistics of every legal action. Determinization
+    /// `i` is seeded with `seed + i`, so the result only depends on `seed`, not on whether
+    /// the determinizations ran in parallel.
+    pub fn search(
+        &self,
+        state: &ObservableGameState,
+        actions: &ActionList,
+        history: &ObservableGameHistory,
+        seed: u64,
+    ) -> Vec<ActionStats> {
+        self.search_stats(state, actions, history, seed).actions
+    }
+
+    /// Like `search`, with the size of the search and how clear its choice was.
+    pub fn search_stats(
+        &self,
+        state: &ObservableGameState,
+        actions: &ActionList,
+        history: &ObservableGameHistory,
+        seed: u64,
+    ) -> SearchStats {
+        let seeds: Vec<u64> = (0..self.config.determinizations as u64)
+            .map(|i| seed.wrapping_add(i))
+            .collect();
+        let run = |seed: &u64| self.search_determinization(state, history, *seed);
+
+        #[cfg(feature = "parallel")]
+        let results: Vec<(Vec<ActionStats>, usize)> = match &self.pool {
+            Some(pool) => pool.install(|| seeds.par_iter().map(run).collect()),
+            None => seeds.iter().map(run).collect(),
+        };
+        #[cfg(not(feature = "parallel"))]
+        let results: Vec<(Vec<ActionStats>, usize)> = seeds.iter().map(run).collect();
+
+        // Sum in determinization order so serial and parallel runs agree exactly
+        let mut totals: Vec<ActionStats> = actions
+            .0
+            .iter()
+            .map(|action| ActionStats {
+                action: *action,
+                visits: 0,
+                value: 0.0,
+            })
+            .collect();
+        for stats in results.iter().flat_map(|(stats, _)| stats) {
+            if let Some(total) = totals.iter_mut().find(|t| t.action == stats.action) {
+                total.visits += stats.visits;
+                total.value += stats.value;
+            }
+        }
+        let visits: u32 = totals.iter().map(|t| t.visits).sum();
+        let best = totals.iter().map(|t| t.visits).max().unwrap_or(0);
+        SearchStats {
+            nodes: results.iter().map(|(_, nodes)| nodes).sum(),
+            confidence: match visits {
+                0 => 0.0,
+                _ => best as f64 / visits as f64,
+            },
+            actions: totals,
+        }
+    }
+
+    fn search_determinization(
+        &self,
+        state: &ObservableGameState,
+        history: &ObservableGameHistory,
+        seed: u64,
+    ) -> (Vec<ActionStats>, usize) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        match determinize(state, history, &self.game_config, &mut rng) {
+            Some(root) => uct(root, &self.game_config, &self.config, &mut rng),
+            None => (Vec::new(), 0),
+        }
+    }
+}
+
+impl Player for MctsPlayer {
+    fn choose_action(
+        &mut self,
+        state: ObservableGameState,
+        actions: ActionList,
+        history: ObservableGameHistory,
+    ) -> Option<Action> {
+        if actions.0.len() <= 1 {
+            self.last_stats = None;
+            return actions.0.first().copied();
+        }
+        let seed = self.rng.gen();
+        let stats = self.search_stats(&state, &actions, &history, seed);
+        let action = stats
+            .actions
+            .iter()
+            .max_by_key(|s| s.visits)
+            .filter(|s| s.visits > 0)
+            .map(|s| s.action)
+            .or_else(|| actions.0.first().copied());
+        self.last_stats = Some(stats);
+        action
+    }
+
+    /// `None` until the first search, and after a move with only one legal action.
+    fn last_search_stats(&self) -> Option<SearchStats> {
+        self.last_stats.clone()
+    }
+
+    /// The copy searches with a seed drawn from `thread_rng`.
+    fn clone_box(&self) -> Box<dyn Player> {
+        Box::new(MctsPlayer::new(
+            self.game_config.clone(),
+            MctsConfig {
+                seed: thread_rng().gen(),
+                ..self.config.clone()
+            },
+        ))
+    }
+}
+
+/// Sample a full game state consistent with what `state.player` has observed. Cards seen
+/// going to the graveyard or into the opponent's hand are placed there; the remaining unseen
+/// cards are dealt at random to the opponent's hand, the deck and the graveyard (cards
+/// the opponent defended with at the very end of a bout are never observed).
+pub fn determinize<R: Rng + ?Sized>(
+    state: &ObservableGameState,
+    history: &ObservableGameHistory,
+    game_config: &GameConfig,
+    rng: &mut R,
+) -> Option<GameState> {
+    let me = state.player;
+    let mut graveyard = CardSet::EMPTY;
+    let mut known_opp = CardSet::EMPTY;
+    // Every card outside the graveyard is seen or counted
+    let in_play = |obs: &ObservableGameState| {
+        obs.num_cards_in_deck as usize
+            + obs.hand.0.len()
+            + obs.cards_in_opponent as usize
+            + obs.attack_table.len()
+            + obs.defense_table.len()
+    };
+    for pair in history.0.windows(2) {
+        let (prev, next) = (&pair[0], &pair[1]);
+        let next_table: CardSet = next
+            .attack_table
+            .iter()
+            .chain(next.defense_table.iter())
+            .copied()
+            .collect();
+        known_opp = known_opp.difference(next_table);
+        if next.bout > prev.bout {
+            let table: CardSet = prev
+                .attack_table
+                .iter()
+                .chain(prev.defense_table.iter())
+                .copied()
+                .collect();
+            // A cleared bout goes to the graveyard, a taken one into the defender's hand
+            match in_play(next) < in_play(prev) {
+                true => graveyard = graveyard.union(table),
+                false if prev.defender != me => known_opp = known_opp.union(table),
+                false => {}
+            }
+        }
+    }
+
+    if let Some(hand) = &state.opponent_hand {
+        // Open-hands mode: nothing about the opponent's hand needs guessing
+        known_opp = CardSet::from(hand);
+    }
+
+    let on_table: CardSet = state
+        .attack_table
+        .iter()
+        .chain(state.defense_table.iter())
+        .copied()
+        .collect();
+    let mine = CardSet::from(&state.hand);
+    let deck_size = state.num_cards_in_deck as usize;
+    let placed = mine.union(on_table).union(graveyard);
+    if deck_size == 0
+        && !placed.contains(state.visible_card)
+        && known_opp.len() < state.cards_in_opponent as usize
+    {
+        // With the deck gone, an unseen trump card can only have been drawn by the opponent
+        known_opp.insert(state.visible_card);
+    }
+
+    let mut known = placed.union(known_opp);
+    if deck_size > 0 {
+        known.insert(state.visible_card);
+    }
+    let mut pool: Vec<Card> = CardSet::of_ranks(&game_config.rank_set())
+        .difference(known)
+        .iter()
+        .collect();
+    let opp_unknown = (state.cards_in_opponent as usize).checked_sub(known_opp.len())?;
+    let deck_unknown = deck_size.saturating_sub(1);
+    if pool.len() < opp_unknown + deck_unknown {
+        return None;
+    }
+    pool.shuffle(rng);
+
+    let mut opp_hand: Vec<Card> = known_opp.iter().collect();
+    opp_hand.extend(pool.drain(..opp_unknown));
+    let mut deck: Vec<Card> = Vec::with_capacity(deck_size);
+    if deck_size > 0 {
+        deck.push(state.visible_card);
+    }
+    deck.extend(pool.drain(..deck_unknown));
+    let mut graveyard: Vec<Card> = graveyard.iter().collect();
+    graveyard.extend(pool);
+
+    let (hand1, hand2) = match me {
+        GamePlayer::Player1 => (state.hand.clone(), Hand(opp_hand)),
+        GamePlayer::Player2 => (Hand(opp_hand), state.hand.clone()),
+    };
+    let mut game_state = GameState::new(
+        deck.into_iter().collect(),
+        state.attack_table.clone(),
+        state.defense_table.clone(),
+        hand1,
+        hand2,
+        state.acting_player,
+        state.defender,
+        state.visible_card,
+        state.defender_has_taken,
+        graveyard,
+    );
+    game_state.bout = state.bout;
+    match me {
+        GamePlayer::Player1 => game_state.void_suits2 = state.opponent_void_suits.clone(),
+        GamePlayer::Player2 => game_state.void_suits1 = state.opponent_void_suits.clone(),
+    }
+    Some(game_state)
+}
+
+struct Node {
+    action: Option<Action>,
+    /// The player who took `action` to reach this node; values are from their point of view.
+    player: Option<GamePlayer>,
+    children: Vec<usize>,
+    untried: Vec<Action>,
+    visits: u32,
+    value: f64,
+}
+
+/// Plain UCT on a perfect-information state, returning the statistics of the root's children
+/// and the number of nodes in the tree.
+fn uct<R: Rng + ?Sized>(
+    root: GameState,
+    game_config: &GameConfig,
+    config: &MctsConfig,
+    rng: &mut R,
+) -> (Vec<ActionStats>, usize) {
+    let mut root_game = Game::from_state(root.clone());
+    *root_game.config_mut() = game_config.clone();
+    let mut rollout_policy = RandomPlayer::new(Some(Box::new(StdRng::seed_from_u64(rng.gen()))));
+    let mut nodes = vec![Node {
+        action: None,
+        player: None,
+        children: Vec::with_capacity(root_game.num_legal_actions()),
+        untried: root_game.legal_actions().0,
+        visits: 0,
+        value: 0.0,
+    }];
+
+    for _ in 0..config.iterations {
+        let mut game = Game::from_state(root.clone());
+        *game.config_mut() = game_config.clone();
+        let mut path = vec![0];
+        let mut node = 0;
+
+        // Selection
+        while nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+            let parent_visits = nodes[node].visits.max(1) as f64;
+            node = *nodes[node]
+                .children
+                .iter()
+                .max_by(|a, b| {
+                    let ucb = |i: usize| {
+                        let n = &nodes[i];
+                        let visits = n.visits.max(1) as f64;
+                        n.value / visits + config.exploration * (parent_visits.ln() / visits).sqrt()
+                    };
+                    ucb(**a).total_cmp(&ucb(**b))
+                })
+                .unwrap();
+            game.step(nodes[node].action.unwrap()).unwrap();
+            path.push(node);
+        }
+
+        // Expansion
+        if !game.is_over() && !nodes[node].untried.is_empty() {
+            let index = rng.gen_range(0..nodes[node].untried.len());
+            let action = nodes[node].untried.swap_remove(index);
+            let player = game.game_state().acting_player;
+            game.step(action).unwrap();
+            let (children, untried) = match game.is_over() {
+                true => (Vec::new(), Vec::new()),
+                false => (
+                    Vec::with_capacity(game.num_legal_actions()),
+                    game.legal_actions().0,
+                ),
+            };
+            nodes.push(Node {
+                action: Some(action),
+                player: Some(player),
+                children,
+                untried,
+                visits: 0,
+                value: 0.0,
+            });
+            let child = nodes.len() - 1;
+            nodes[node].children.push(child);
+            path.push(child);
+        }
+
+        // Rollout
+        let (r1, r2) = game.rollout(&mut rollout_policy);
+
+        // Backpropagation
+        for &i in path.iter() {
+            nodes[i].visits += 1;
+            nodes[i].value += match nodes[i].player {
+                Some(GamePlayer::Player1) => r1 as f64,
+                Some(GamePlayer::Player2) => r2 as f64,
+                None => 0.0,
+            };
+        }
+    }
+
+    let stats = nodes[0]
+        .children
+        .iter()
+        .map(|&i| ActionStats {
+            action: nodes[i].action.unwrap(),
+            visits: nodes[i].visits,
+            value: nodes[i].value,
+        })
+        .collect();
+    (stats, nodes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::game::ObservableSnapshot;
+
+    fn small_config(threads: Option<usize>) -> MctsConfig {
+        MctsConfig {
+            determinizations: 4,
+            iterations: 30,
+            threads,
+            ..MctsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_determinization_matches_observation() {
+        let mut game = Game::new_seeded(6);
+        for _ in 0..10 {
+            let action = game.legal_actions().0[0];
+            game.step(action).unwrap();
+        }
+        let player = game.game_state().acting_player;
+        let snapshot = game.snapshot(player);
+        let mut rng = StdRng::seed_from_u64(1);
+        let sampled = determinize(
+            &snapshot.state,
+            &snapshot.history,
+            &GameConfig::default(),
+            &mut rng,
+        )
+        .unwrap();
+
+        assert!(sampled
+            .validate_complete(&GameConfig::default().rank_set())
+            .is_ok());
+        assert_eq!(sampled.observe(player), snapshot.state);
+    }
+
+    #[test]
+    fn test_mcts_player_picks_legal_action() {
+        let game = Game::new_seeded(2);
+        let ObservableSnapshot {
+            state,
+            actions,
+            history,
+        } = game.snapshot(game.game_state().acting_player);
+        let mut player = MctsPlayer::new(GameConfig::default(), small_config(None));
+        assert_eq!(player.last_search_stats(), None);
+        let action = player
+            .choose_action(state, actions.clone(), history)
+            .unwrap();
+        assert!(actions.0.contains(&action));
+
+        let stats = player.last_search_stats().unwrap();
+        assert!(stats.nodes > 0);
+        assert_eq!(stats.actions.len(), actions.0.len());
+        let best = stats.actions.iter().max_by_key(|s| s.visits).unwrap();
+        assert_eq!(best.action, action);
+        assert!(stats.confidence > 0.0 && stats.confidence <= 1.0);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_search_matches_serial() {
+        let game = Game::new_seeded(4);
+        let snapshot = game.snapshot(game.game_state().acting_player);
+        let serial = MctsPlayer::new(GameConfig::default(), small_config(None));
+        let parallel = MctsPlayer::new(GameConfig::default(), small_config(Some(4)));
+
+        let expected = serial.search(&snapshot.state, &snapshot.actions, &snapshot.history, 99);
+        let actual = parallel.search(&snapshot.state, &snapshot.actions, &snapshot.history, 99);
+        assert_eq!(actual, expected);
+        assert!(expected.iter().map(|s| s.visits).sum::<u32>() > 0);
+    }
+}
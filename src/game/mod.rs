@@ -1,7 +1,13 @@
 // whatever to do in mod.rs
 pub mod actions;
+pub mod card_set;
 pub mod cards;
+pub mod config;
+pub mod deal_code;
+pub mod dealer;
 pub mod game;
 pub mod gamestate;
+pub mod mcts;
 pub mod player;
+pub mod puzzle;
 pub mod utils;
@@ -1,19 +1,67 @@
-use rand::{thread_rng, Rng, RngCore};
+use std::collections::VecDeque;
+
+use rand::{rngs::StdRng, thread_rng, Rng, RngCore, SeedableRng};
 
 use crate::game::gamestate::ObservableGameHistory;
 
 use super::{
     actions::{Action, ActionList},
-    gamestate::ObservableGameState,
+    cards::{Card, Hand},
+    game::{GameEvent, GameLog},
+    gamestate::{GamePlayer, ObservableGameState},
+    mcts::SearchStats,
 };
 
 pub trait Player {
+    /// Pick one of `actions`. Returns `None` only if there is nothing to choose from, which
+    /// game loops treat as the end of the game.
     fn choose_action(
         &mut self,
         game_state: ObservableGameState,
         actions: ActionList,
         history: ObservableGameHistory,
-    ) -> Action;
+    ) -> Option<Action>;
+
+    /// A copy of this player for use elsewhere, e.g. as an opponent model in search.
+    /// Players with their own RNG give the copy a fresh RNG seeded from `thread_rng`, so
+    /// the copy does not repeat the original's random choices and is not reproducible.
+    fn clone_box(&self) -> Box<dyn Player>;
+
+    /// What the player's search found when it last chose an action, for debugging. `None`
+    /// for players that don't search.
+    fn last_search_stats(&self) -> Option<SearchStats> {
+        None
+    }
+}
+
+impl Clone for Box<dyn Player> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl Player for Box<dyn Player> {
+    fn choose_action(
+        &mut self,
+        game_state: ObservableGameState,
+        actions: ActionList,
+        history: ObservableGameHistory,
+    ) -> Option<Action> {
+        (**self).choose_action(game_state, actions, history)
+    }
+
+    fn clone_box(&self) -> Box<dyn Player> {
+        (**self).clone_box()
+    }
+
+    fn last_search_stats(&self) -> Option<SearchStats> {
+        (**self).last_search_stats()
+    }
+}
+
+/// A fresh RNG for a cloned player, see `Player::clone_box`.
+fn reseeded() -> Box<dyn RngCore> {
+    Box::new(StdRng::from_rng(thread_rng()).expect("thread_rng does not fail"))
 }
 
 pub struct RandomPlayer {
@@ -37,12 +85,396 @@ impl Player for RandomPlayer {
         _state: ObservableGameState,
         actions: ActionList,
         _history: ObservableGameHistory,
-    ) -> Action {
-        let choice = match actions.0.len() {
-            0 => panic!("No actions available"),
-            1 => 0,
-            _ => self.rng.gen_range(0..actions.0.len()),
+    ) -> Option<Action> {
+        random_action(&mut self.rng, &actions)
+    }
+
+    fn clone_box(&self) -> Box<dyn Player> {
+        Box::new(RandomPlayer::new(Some(reseeded())))
+    }
+}
+
+fn random_action(rng: &mut Box<dyn RngCore>, actions: &ActionList) -> Option<Action> {
+    let choice = match actions.0.len() {
+        0 => return None,
+        1 => 0,
+        _ => rng.gen_range(0..actions.0.len()),
+    };
+    Some(actions.0[choice])
+}
+
+/// Wraps another player and, with probability `epsilon`, plays a uniformly random legal
+/// action instead of asking it. Useful for exploration when collecting training data.
+pub struct EpsilonGreedyPlayer<P: Player> {
+    inner: P,
+    epsilon: f64,
+    rng: Box<dyn RngCore>,
+}
+
+impl<P: Player> EpsilonGreedyPlayer<P> {
+    pub fn new(inner: P, epsilon: f64, rng: Option<Box<dyn RngCore>>) -> EpsilonGreedyPlayer<P> {
+        EpsilonGreedyPlayer {
+            inner,
+            epsilon: epsilon.clamp(0.0, 1.0),
+            rng: rng.unwrap_or_else(|| Box::new(thread_rng())),
+        }
+    }
+}
+
+impl<P: Player> Player for EpsilonGreedyPlayer<P> {
+    fn choose_action(
+        &mut self,
+        state: ObservableGameState,
+        actions: ActionList,
+        history: ObservableGameHistory,
+    ) -> Option<Action> {
+        if self.rng.gen_bool(self.epsilon) {
+            random_action(&mut self.rng, &actions)
+        } else {
+            self.inner.choose_action(state, actions, history)
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Player> {
+        Box::new(EpsilonGreedyPlayer::new(
+            self.inner.clone_box(),
+            self.epsilon,
+            Some(reseeded()),
+        ))
+    }
+}
+
+/// Gets rid of its cheapest card whenever it can: attacks and defends with the lowest
+/// card, non-trumps before trumps, and only stops attacking or takes when it has no card
+/// to play. A simple baseline that is clearly stronger than random play.
+pub struct GreedyPlayer;
+
+impl Player for GreedyPlayer {
+    fn choose_action(
+        &mut self,
+        state: ObservableGameState,
+        actions: ActionList,
+        _history: ObservableGameHistory,
+    ) -> Option<Action> {
+        let playable: Hand = actions
+            .0
+            .iter()
+            .filter_map(|action| match action {
+                Action::Attack(card) | Action::Defend(card) => Some(*card),
+                Action::StopAttack | Action::Take => None,
+            })
+            .collect();
+        let (trumps, others) = playable.split_trumps(state.visible_card.suit);
+        let lowest = |cards: Vec<Card>| cards.into_iter().min_by_key(|card| card.rank);
+        match lowest(others).or_else(|| lowest(trumps)) {
+            Some(card) => actions
+                .0
+                .iter()
+                .find(
+                    |action| matches!(action, Action::Attack(c) | Action::Defend(c) if *c == card),
+                )
+                .copied(),
+            None => actions.0.first().copied(),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Player> {
+        Box::new(GreedyPlayer)
+    }
+}
+
+/// Plays as `early` while the deck is larger than `threshold` and as `endgame` once it is
+/// down to `threshold` cards or fewer, e.g. a cheap heuristic until the deck runs low and
+/// a search for the endgame.
+pub struct PhaseSwitchingPlayer {
+    pub early: Box<dyn Player>,
+    pub endgame: Box<dyn Player>,
+    pub threshold: u8,
+    /// Whether the last action came from `endgame`, for `last_search_stats`.
+    in_endgame: bool,
+}
+
+impl PhaseSwitchingPlayer {
+    pub fn new(
+        early: Box<dyn Player>,
+        endgame: Box<dyn Player>,
+        threshold: u8,
+    ) -> PhaseSwitchingPlayer {
+        PhaseSwitchingPlayer {
+            early,
+            endgame,
+            threshold,
+            in_endgame: false,
+        }
+    }
+}
+
+impl Player for PhaseSwitchingPlayer {
+    fn choose_action(
+        &mut self,
+        state: ObservableGameState,
+        actions: ActionList,
+        history: ObservableGameHistory,
+    ) -> Option<Action> {
+        self.in_endgame = state.num_cards_in_deck <= self.threshold;
+        match self.in_endgame {
+            true => self.endgame.choose_action(state, actions, history),
+            false => self.early.choose_action(state, actions, history),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Player> {
+        Box::new(PhaseSwitchingPlayer::new(
+            self.early.clone_box(),
+            self.endgame.clone_box(),
+            self.threshold,
+        ))
+    }
+
+    fn last_search_stats(&self) -> Option<SearchStats> {
+        match self.in_endgame {
+            true => self.endgame.last_search_stats(),
+            false => self.early.last_search_stats(),
+        }
+    }
+}
+
+/// Plays back one player's side of a recorded game, one logged action per turn. Meant for
+/// checking externally recorded games against the rules, so a logged action that is not
+/// legal when its turn comes up panics instead of being skipped.
+#[derive(Clone)]
+pub struct ReplayPlayer {
+    actions: VecDeque<Action>,
+}
+
+impl ReplayPlayer {
+    pub fn new(actions: impl IntoIterator<Item = Action>) -> ReplayPlayer {
+        ReplayPlayer {
+            actions: actions.into_iter().collect(),
+        }
+    }
+
+    /// Split a log into the replay players for Player1 and Player2. The log must record
+    /// events, since its plain action list does not say who took each action.
+    pub fn from_log(log: &GameLog) -> Result<(ReplayPlayer, ReplayPlayer), String> {
+        if log.events.is_empty() && !log.actions.is_empty() {
+            return Err("Log has no events to tell the players' actions apart".to_string());
+        }
+        let actions_of = |seat: GamePlayer| {
+            log.events.iter().filter_map(move |event| match event {
+                GameEvent::Action { player, action } if *player == seat => Some(*action),
+                _ => None,
+            })
         };
-        actions.0[choice]
+        Ok((
+            ReplayPlayer::new(actions_of(GamePlayer::Player1)),
+            ReplayPlayer::new(actions_of(GamePlayer::Player2)),
+        ))
+    }
+
+    /// The logged actions not played yet.
+    pub fn remaining(&self) -> usize {
+        self.actions.len()
+    }
+}
+
+impl Player for ReplayPlayer {
+    /// Returns `None` once the log runs out.
+    fn choose_action(
+        &mut self,
+        state: ObservableGameState,
+        actions: ActionList,
+        _history: ObservableGameHistory,
+    ) -> Option<Action> {
+        let action = self.actions.pop_front()?;
+        if !actions.0.contains(&action) {
+            panic!(
+                "Logged action {:?} is illegal for {:?} in bout {}, legal actions: {:?}",
+                action, state.player, state.bout, actions.0
+            );
+        }
+        Some(action)
+    }
+
+    fn clone_box(&self) -> Box<dyn Player> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::game::game::{Game, GameLogic, ObservableSnapshot};
+    use crate::game::gamestate::GamePlayer;
+
+    struct LastActionPlayer;
+
+    impl Player for LastActionPlayer {
+        fn choose_action(
+            &mut self,
+            _state: ObservableGameState,
+            actions: ActionList,
+            _history: ObservableGameHistory,
+        ) -> Option<Action> {
+            actions.0.last().copied()
+        }
+
+        fn clone_box(&self) -> Box<dyn Player> {
+            Box::new(LastActionPlayer)
+        }
+    }
+
+    fn seeded(seed: u64) -> Option<Box<dyn RngCore>> {
+        Some(Box::new(StdRng::seed_from_u64(seed)))
+    }
+
+    /// Drive a seeded game with `reference`, checking that `player` picks the same action
+    /// at every decision.
+    fn assert_plays_like(mut player: impl Player, mut reference: impl Player) {
+        let mut game = Game::new_seeded(5);
+        while !game.is_over() {
+            let ObservableSnapshot {
+                state,
+                actions,
+                history,
+            } = game.snapshot(game.game_state().acting_player);
+            let expected = reference.choose_action(state.clone(), actions.clone(), history.clone());
+            assert_eq!(player.choose_action(state, actions, history), expected);
+            game.step(expected.unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_epsilon_one_plays_like_random_player() {
+        assert_plays_like(
+            EpsilonGreedyPlayer::new(LastActionPlayer, 1.0, seeded(9)),
+            RandomPlayer::new(seeded(9)),
+        );
+    }
+
+    #[test]
+    fn test_random_player_handles_no_actions() {
+        let game = Game::new_seeded(1);
+        let ObservableSnapshot { state, history, .. } = game.snapshot(GamePlayer::Player1);
+        let mut player = RandomPlayer::new(seeded(2));
+        assert_eq!(
+            player.choose_action(state, ActionList(Vec::new()), history),
+            None
+        );
+    }
+
+    #[test]
+    fn test_epsilon_zero_plays_like_inner() {
+        assert_plays_like(
+            EpsilonGreedyPlayer::new(LastActionPlayer, 0.0, seeded(9)),
+            LastActionPlayer,
+        );
+    }
+
+    #[test]
+    fn test_cloned_players_play_legal_actions() {
+        let game = Game::new_seeded(6);
+        let players: Vec<Box<dyn Player>> = vec![
+            Box::new(RandomPlayer::new(seeded(1))),
+            Box::new(GreedyPlayer),
+            Box::new(EpsilonGreedyPlayer::new(GreedyPlayer, 0.5, seeded(2))),
+        ];
+        for mut original in players {
+            let mut copy = original.clone();
+            for player in [&mut original, &mut copy] {
+                let ObservableSnapshot {
+                    state,
+                    actions,
+                    history,
+                } = game.snapshot(game.game_state().acting_player);
+                let action = player.choose_action(state, actions.clone(), history);
+                assert!(actions.0.contains(&action.unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_greedy_player_plays_cheapest_card() {
+        let mut game = Game::new_seeded(3);
+        let player = game.game_state().acting_player;
+        let trump = game.game_state().visible_card.suit;
+        let ObservableSnapshot {
+            state,
+            actions,
+            history,
+        } = game.snapshot(player);
+        let Some(Action::Attack(card)) = GreedyPlayer.choose_action(state, actions, history) else {
+            panic!("greedy player did not open with an attack");
+        };
+        let hand = match player {
+            GamePlayer::Player1 => &game.game_state().hand1,
+            GamePlayer::Player2 => &game.game_state().hand2,
+        };
+        let key = |c: &crate::game::cards::Card| (c.suit == trump, c.rank);
+        assert_eq!(hand.0.iter().map(key).min(), Some(key(&card)));
+
+        game.play(Box::new(GreedyPlayer), Box::new(GreedyPlayer))
+            .unwrap();
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn test_phase_switching_player_delegates_by_deck_size() {
+        let game = Game::new_seeded(4);
+        let ObservableSnapshot {
+            state,
+            actions,
+            history,
+        } = game.snapshot(game.game_state().acting_player);
+        let deck = state.num_cards_in_deck;
+        let greedy = GreedyPlayer.choose_action(state.clone(), actions.clone(), history.clone());
+        let last = actions.0.last().copied();
+        assert_ne!(greedy, last);
+
+        for (threshold, expected) in [(deck - 1, greedy), (deck, last), (deck + 1, last)] {
+            let mut player = PhaseSwitchingPlayer::new(
+                Box::new(GreedyPlayer),
+                Box::new(LastActionPlayer),
+                threshold,
+            );
+            let action = player.choose_action(state.clone(), actions.clone(), history.clone());
+            assert_eq!(action, expected, "threshold {}", threshold);
+        }
+    }
+
+    #[test]
+    fn test_replay_reproduces_logged_game() {
+        let mut original = Game::new_seeded(21);
+        original
+            .play(
+                Box::new(RandomPlayer::new(seeded(1))),
+                Box::new(RandomPlayer::new(seeded(2))),
+            )
+            .unwrap();
+        let log = original.log();
+
+        let (player1, player2) = ReplayPlayer::from_log(&log).unwrap();
+        let mut replay = Game::from_state(log.initial_state.clone());
+        *replay.config_mut() = log.config.clone();
+        replay.play(Box::new(player1), Box::new(player2)).unwrap();
+
+        assert!(replay.is_over());
+        assert_eq!(replay.get_winner(), original.get_winner());
+        assert_eq!(replay.actions, log.actions);
+    }
+
+    #[test]
+    #[should_panic(expected = "is illegal")]
+    fn test_replay_panics_on_illegal_action() {
+        let game = Game::new_seeded(21);
+        let ObservableSnapshot {
+            state,
+            actions,
+            history,
+        } = game.snapshot(game.game_state().acting_player);
+        // Nothing is on the table yet, so the attacker cannot stop
+        ReplayPlayer::new([Action::StopAttack]).choose_action(state, actions, history);
     }
 }
@@ -0,0 +1,215 @@
+use super::{
+    cards::{Card, Deck, Hand},
+    config::GameConfig,
+    game::Game,
+    gamestate::{GamePlayer, GameState},
+};
+
+/// Sets up an arbitrary mid-game position, e.g. for puzzles. Every card of the configured
+/// deck must be placed somewhere: in a hand, the deck, on the table or in the graveyard.
+///
+/// Player1 attacks and acts by default.
+#[derive(Clone, Debug)]
+pub struct PuzzleBuilder {
+    config: GameConfig,
+    hand1: Vec<Card>,
+    hand2: Vec<Card>,
+    deck: Vec<Card>,
+    trump: Option<Card>,
+    attack_table: Vec<Card>,
+    defense_table: Vec<Card>,
+    graveyard: Vec<Card>,
+    acting_player: GamePlayer,
+    defending_player: GamePlayer,
+    defender_has_taken: bool,
+}
+
+impl PuzzleBuilder {
+    pub fn new(config: GameConfig) -> Self {
+        Self {
+            config,
+            hand1: Vec::new(),
+            hand2: Vec::new(),
+            deck: Vec::new(),
+            trump: None,
+            attack_table: Vec::new(),
+            defense_table: Vec::new(),
+            graveyard: Vec::new(),
+            acting_player: GamePlayer::Player1,
+            defending_player: GamePlayer::Player2,
+            defender_has_taken: false,
+        }
+    }
+
+    pub fn hands(mut self, hand1: Vec<Card>, hand2: Vec<Card>) -> Self {
+        self.hand1 = hand1;
+        self.hand2 = hand2;
+        self
+    }
+
+    /// The cards left to draw, in draw order. The last card is the visible trump.
+    pub fn deck(mut self, cards: Vec<Card>) -> Self {
+        self.deck = cards;
+        self
+    }
+
+    /// The trump card, which must be given once the deck is empty. If the deck is not
+    /// empty it has to be the deck's last card.
+    pub fn trump(mut self, card: Card) -> Self {
+        self.trump = Some(card);
+        self
+    }
+
+    /// The cards on the table; `defense[i]` covers `attack[i]`.
+    pub fn table(mut self, attack: Vec<Card>, defense: Vec<Card>) -> Self {
+        self.attack_table = attack;
+        self.defense_table = defense;
+        self
+    }
+
+    pub fn graveyard(mut self, cards: Vec<Card>) -> Self {
+        self.graveyard = cards;
+        self
+    }
+
+    /// Whose turn it is and who defends in the current bout.
+    pub fn turn(mut self, acting: GamePlayer, defending: GamePlayer) -> Self {
+        self.acting_player = acting;
+        self.defending_player = defending;
+        self
+    }
+
+    /// Whether the defender has already decided to take the table.
+    pub fn defender_has_taken(mut self, taken: bool) -> Self {
+        self.defender_has_taken = taken;
+        self
+    }
+
+    /// Check the position and start a game from it.
+    pub fn build(self) -> Result<Game, String> {
+        self.config.validate().map_err(|e| e.to_string())?;
+        let visible_card = match (self.deck.last(), self.trump) {
+            (Some(last), Some(trump)) if *last != trump => {
+                return Err(format!(
+                    "Trump {:?} is not the last card of the deck {:?}",
+                    trump, last
+                ))
+            }
+            (Some(last), _) => *last,
+            (None, Some(trump)) => trump,
+            (None, None) => return Err("An empty deck needs an explicit trump card".to_string()),
+        };
+
+        let undefended = self.attack_table.len() > self.defense_table.len();
+        if self.acting_player == self.defending_player && !undefended {
+            return Err("The defender is to act but there is nothing to defend".to_string());
+        }
+        if self.acting_player != self.defending_player && undefended && !self.defender_has_taken {
+            return Err(
+                "The attacker is to act while the defender still has to answer".to_string(),
+            );
+        }
+        if self.defender_has_taken && self.attack_table.is_empty() {
+            return Err("The defender cannot have taken an empty table".to_string());
+        }
+
+        let state = GameState::new(
+            // The deck draws from the end of its list, so reverse the draw order
+            self.deck.iter().rev().copied().collect::<Deck>(),
+            self.attack_table,
+            self.defense_table,
+            Hand(self.hand1),
+            Hand(self.hand2),
+            self.acting_player,
+            self.defending_player,
+            visible_card,
+            self.defender_has_taken,
+            self.graveyard,
+        );
+        Game::try_from_state(state, self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{actions::Action, cards::Suit};
+
+    fn card(rank: u8, suit: Suit) -> Card {
+        Card { suit, rank }
+    }
+
+    /// Everything of the standard deck that is not in `placed`.
+    fn rest(placed: &[Card]) -> Vec<Card> {
+        Deck::new(6)
+            .cards
+            .into_iter()
+            .filter(|card| !placed.contains(card))
+            .collect()
+    }
+
+    #[test]
+    fn test_puzzle_defense_position() {
+        // Endgame, spades are trumps: Player2 must answer the ten of hearts
+        let hand1 = vec![card(8, Suit::Clubs), card(9, Suit::Clubs)];
+        let hand2 = vec![
+            card(7, Suit::Hearts),
+            card(12, Suit::Hearts),
+            card(6, Suit::Spades),
+        ];
+        let attack = vec![card(10, Suit::Hearts)];
+        let trump = card(14, Suit::Spades);
+        let placed: Vec<Card> =
+            [hand1.clone(), hand2.clone(), attack.clone(), vec![trump]].concat();
+
+        let game = PuzzleBuilder::new(GameConfig::default())
+            .hands(hand1, hand2)
+            .table(attack, Vec::new())
+            .trump(trump)
+            .graveyard([rest(&placed), vec![trump]].concat())
+            .turn(GamePlayer::Player2, GamePlayer::Player2)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            game.legal_actions().0,
+            vec![
+                Action::Take,
+                Action::Defend(card(6, Suit::Spades)),
+                Action::Defend(card(12, Suit::Hearts)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_puzzle_rejects_inconsistent_positions() {
+        let hand1 = vec![card(8, Suit::Clubs)];
+        let hand2 = vec![card(9, Suit::Clubs)];
+        let deck = vec![card(10, Suit::Clubs), card(14, Suit::Spades)];
+        let placed: Vec<Card> = [hand1.clone(), hand2.clone(), deck.clone()].concat();
+        let puzzle = PuzzleBuilder::new(GameConfig::default())
+            .hands(hand1, hand2)
+            .deck(deck);
+
+        assert!(puzzle.clone().graveyard(rest(&placed)).build().is_ok());
+        // A card is missing
+        assert!(puzzle
+            .clone()
+            .graveyard(rest(&placed)[1..].to_vec())
+            .build()
+            .is_err());
+        // The defender cannot act on an empty table
+        assert!(puzzle
+            .clone()
+            .graveyard(rest(&placed))
+            .turn(GamePlayer::Player2, GamePlayer::Player2)
+            .build()
+            .is_err());
+        // The trump has to be the bottom card of the deck
+        assert!(puzzle
+            .graveyard(rest(&placed))
+            .trump(card(10, Suit::Clubs))
+            .build()
+            .is_err());
+    }
+}
@@ -11,9 +11,11 @@ use pyo3::prelude::*;
 use python::{
     actions_py::ActionListPy,
     card_py::CardPy,
-    env_py::GameEnvPy,
+    config_py::GameConfigPy,
+    env_py::{GameEnvPy, StateHandlePy},
+    game_py::GamePy,
     gamestate_py::{ObservableGameHistoryPy, ObservableGameStatePy},
-    player_py::GamePlayerPy,
+    player_py::{GamePlayerPy, RandomPlayerPy},
 };
 
 #[cfg(feature = "python")]
@@ -21,9 +23,14 @@ use python::{
 fn rust(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<CardPy>()?;
     m.add_class::<GameEnvPy>()?;
+    m.add_class::<StateHandlePy>()?;
     m.add_class::<ObservableGameStatePy>()?;
     m.add_class::<ActionListPy>()?;
     m.add_class::<GamePlayerPy>()?;
+    m.add_class::<RandomPlayerPy>()?;
     m.add_class::<ObservableGameHistoryPy>()?;
+    m.add_class::<GameConfigPy>()?;
+    m.add_class::<GamePy>()?;
+    m.add("ENCODING_VERSION", game::gamestate::ENCODING_VERSION)?;
     Ok(())
 }
@@ -1,5 +1,5 @@
 use numpy::{ndarray::Array1, PyArray1};
-use pyo3::{exceptions::PyException, pyclass, pymethods, PyErr, PyResult, Python};
+use pyo3::{exceptions::PyIndexError, pyclass, pymethods, PyResult, Python};
 
 use crate::game::actions::{Action, ActionList};
 
@@ -41,10 +41,19 @@ impl ActionListPy {
         Ok(format!("ActionList({:?})", self.0.to_strings()))
     }
 
+    /// The action at `idx`, counting from the end for negative indices like a list.
     pub fn __getitem__(&self, idx: isize) -> PyResult<ActionPy> {
-        match (idx as usize) < self.0 .0.len() {
-            false => Err(PyErr::new::<PyException, _>("Index out of bounds")),
-            _ => Ok(ActionPy(self.0 .0[idx as usize])),
+        let len = self.0 .0.len();
+        let index = match idx < 0 {
+            true => len.checked_sub(idx.unsigned_abs()),
+            false => Some(idx as usize).filter(|i| *i < len),
+        };
+        match index {
+            Some(i) => Ok(ActionPy(self.0 .0[i])),
+            None => Err(PyIndexError::new_err(format!(
+                "Action index {} out of range for {} actions",
+                idx, len
+            ))),
         }
     }
 
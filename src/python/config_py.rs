@@ -0,0 +1,213 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, PyResult};
+
+use crate::game::config::{
+    DefenseOrder, GameConfig, HandEncoding, NextAttacker, ObservationConfig, ObservationPolicy,
+    OpponentCount, RefillOrder,
+};
+
+/// Python wrapper for the game configuration.
+#[pyclass(name = "GameConfig")]
+#[derive(Clone)]
+pub struct GameConfigPy {
+    pub config: GameConfig,
+}
+
+#[pymethods]
+impl GameConfigPy {
+    /// Create a game configuration.
+    ///
+    /// Args:
+    ///     lowest_rank: The lowest rank in the deck. 6 gives the standard 36-card deck.
+    ///     hand_size: The number of cards each player is dealt and refilled up to.
+    ///     refill_order: Who draws first after a bout: "attacker_first", "defender_first"
+    ///         or "seat_order" (Player1 first).
+    ///     defense_order: Whether several attacks must be beaten "in_order" they were laid
+    ///         or in "any_order".
+    ///     max_attacks_per_bout: If set, attackers must stop after this many attacks in a
+    ///         bout. None plays by the standard rules.
+    ///     no_trump_lead: House rule forbidding opening a bout with a trump while the deck
+    ///         has cards, unless the attacker holds only trumps.
+    ///     reveal_opponent: Cheat/debug mode. Observations include the opponent's actual
+    ///         hand, for teaching and for checking search against perfect information.
+    ///     ranks: The ranks in the deck, e.g. without the tens. Overrides lowest_rank.
+    ///     hand_encoding: How observations encode the player's own hand: "full" as a
+    ///         36-card bitmap or "aggregate" as 14 counts (per suit, per rank, trumps).
+    ///     next_attacker: Who attacks after a bout is beaten off: the "defender" (standard)
+    ///         or the "attacker" again.
+    ///     recall: If set, players are only given this many of the latest states as their
+    ///         history, for imperfect-recall experiments. None gives the whole game.
+    ///     opponent_count_bucket: If set, observations only report which bucket of this
+    ///         many counts the opponent's hand size falls in, as the bucket's lowest count.
+    ///         None reports the exact count.
+    ///
+    /// Raises:
+    ///     ValueError: If the options are unknown or the deck cannot deal both hands.
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (lowest_rank=6, hand_size=6, refill_order="attacker_first", defense_order="in_order", max_attacks_per_bout=None, no_trump_lead=false, reveal_opponent=false, ranks=None, hand_encoding="full", next_attacker="defender", recall=None, opponent_count_bucket=None))]
+    pub fn new(
+        lowest_rank: u8,
+        hand_size: usize,
+        refill_order: &str,
+        defense_order: &str,
+        max_attacks_per_bout: Option<usize>,
+        no_trump_lead: bool,
+        reveal_opponent: bool,
+        ranks: Option<Vec<u8>>,
+        hand_encoding: &str,
+        next_attacker: &str,
+        recall: Option<usize>,
+        opponent_count_bucket: Option<u8>,
+    ) -> PyResult<Self> {
+        let refill_order = match refill_order {
+            "attacker_first" => RefillOrder::AttackerFirst,
+            "defender_first" => RefillOrder::DefenderFirst,
+            "seat_order" => RefillOrder::SeatOrder,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown refill order {:?}",
+                    other
+                )))
+            }
+        };
+        let defense_order = match defense_order {
+            "in_order" => DefenseOrder::InOrder,
+            "any_order" => DefenseOrder::AnyOrder,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown defense order {:?}",
+                    other
+                )))
+            }
+        };
+        let hand = match hand_encoding {
+            "full" => HandEncoding::Full,
+            "aggregate" => HandEncoding::Aggregate,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown hand encoding {:?}",
+                    other
+                )))
+            }
+        };
+        let next_attacker = match next_attacker {
+            "defender" => NextAttacker::Defender,
+            "attacker" => NextAttacker::Attacker,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown next attacker {:?}",
+                    other
+                )))
+            }
+        };
+        let config = GameConfig {
+            lowest_rank,
+            ranks,
+            hand_size,
+            refill_order,
+            defense_order,
+            next_attacker,
+            max_attacks_per_bout,
+            no_trump_lead,
+            reveal_opponent,
+            observation: ObservationConfig {
+                hand,
+                history: recall.map_or(ObservationPolicy::FullRecall, ObservationPolicy::LastK),
+                opponent_count: opponent_count_bucket
+                    .map_or(OpponentCount::Exact, OpponentCount::Bucketed),
+            },
+        };
+        config
+            .validate()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self { config })
+    }
+
+    #[getter(lowest_rank)]
+    pub fn lowest_rank(&self) -> u8 {
+        self.config.lowest_rank
+    }
+
+    #[getter(ranks)]
+    pub fn ranks(&self) -> Vec<u8> {
+        self.config.rank_set()
+    }
+
+    #[getter(hand_size)]
+    pub fn hand_size(&self) -> usize {
+        self.config.hand_size
+    }
+
+    #[getter(refill_order)]
+    pub fn refill_order(&self) -> &'static str {
+        match self.config.refill_order {
+            RefillOrder::AttackerFirst => "attacker_first",
+            RefillOrder::DefenderFirst => "defender_first",
+            RefillOrder::SeatOrder => "seat_order",
+        }
+    }
+
+    #[getter(defense_order)]
+    pub fn defense_order(&self) -> &'static str {
+        match self.config.defense_order {
+            DefenseOrder::InOrder => "in_order",
+            DefenseOrder::AnyOrder => "any_order",
+        }
+    }
+
+    #[getter(next_attacker)]
+    pub fn next_attacker(&self) -> &'static str {
+        match self.config.next_attacker {
+            NextAttacker::Defender => "defender",
+            NextAttacker::Attacker => "attacker",
+        }
+    }
+
+    #[getter(max_attacks_per_bout)]
+    pub fn max_attacks_per_bout(&self) -> Option<usize> {
+        self.config.max_attacks_per_bout
+    }
+
+    #[getter(no_trump_lead)]
+    pub fn no_trump_lead(&self) -> bool {
+        self.config.no_trump_lead
+    }
+
+    #[getter(reveal_opponent)]
+    pub fn reveal_opponent(&self) -> bool {
+        self.config.reveal_opponent
+    }
+
+    #[getter(hand_encoding)]
+    pub fn hand_encoding(&self) -> &'static str {
+        match self.config.observation.hand {
+            HandEncoding::Full => "full",
+            HandEncoding::Aggregate => "aggregate",
+        }
+    }
+
+    #[getter(recall)]
+    pub fn recall(&self) -> Option<usize> {
+        match self.config.observation.history {
+            ObservationPolicy::FullRecall => None,
+            ObservationPolicy::LastK(k) => Some(k),
+        }
+    }
+
+    #[getter(opponent_count_bucket)]
+    pub fn opponent_count_bucket(&self) -> Option<u8> {
+        match self.config.observation.opponent_count {
+            OpponentCount::Exact => None,
+            OpponentCount::Bucketed(width) => Some(width),
+        }
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.config))
+    }
+
+    pub fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.config))
+    }
+}
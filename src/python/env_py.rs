@@ -1,15 +1,26 @@
 use std::path::PathBuf;
 
 use crate::game::actions::num_actions;
-use crate::game::game::{Game, GameLogic};
-use crate::game::gamestate::{GamePlayer, ObservableGameHistory};
+use crate::game::config::{GameConfig, RewardConfig};
+use crate::game::game::{Game, GameLogic, TurnOutcome};
+use crate::game::gamestate::{GamePlayer, ENCODING_VERSION};
 use crate::game::player::{Player, RandomPlayer};
+use crate::python::config_py::GameConfigPy;
+use crate::python::game_py::{player_from_index, trajectory_arrays, TrajectoryArrays};
 use crate::python::player_py::PlayerPy;
 use pyo3::exceptions::{PyIndexError, PyValueError};
 use pyo3::types::PyType;
-use pyo3::{pyclass, pymethods, types::PyString, IntoPy, Py, PyResult, Python};
+use pyo3::{pyclass, pymethods, types::PyString, IntoPy, Py, PyAny, PyResult, Python};
 use rand::{rngs::StdRng, SeedableRng};
 
+/// An opaque snapshot of a `GameEnv`'s game, see `GameEnv.clone_state`.
+#[pyclass(name = "StateHandle")]
+#[derive(Clone)]
+pub struct StateHandlePy {
+    /// The game serialized as JSON.
+    snapshot: String,
+}
+
 /// Python wrapper for the game environment.
 ///
 /// This class provides a gym-like interface for the Durak card game, allowing
@@ -18,6 +29,9 @@ use rand::{rngs::StdRng, SeedableRng};
 #[pyclass(name = "GameEnv", unsendable)]
 pub struct GameEnvPy {
     game: Box<Game>,
+    config: GameConfig,
+    /// How `step` scores each step.
+    rewards: RewardConfig,
     player1: Option<Box<PlayerPy>>,
     player2: Option<Box<PlayerPy>>,
     random_player2: Option<Box<RandomPlayer>>,
@@ -29,6 +43,8 @@ impl GameEnvPy {
     pub fn from_file(_cls: Py<PyType>, file_path: Py<PyString>) -> PyResult<Self> {
         let game = Game::from_file(&PathBuf::from(file_path.to_string()));
         Ok(GameEnvPy {
+            config: game.config().clone(),
+            rewards: RewardConfig::default(),
             game: Box::new(game),
             player1: None,
             player2: None,
@@ -39,58 +55,89 @@ impl GameEnvPy {
     /// Create a new game environment.
     ///
     /// Args:
-    ///     player1: The first player (required). Must be a subclass of GamePlayer.
+    ///     player1: The first player (required). Must be a subclass of GamePlayer that
+    ///         implements choose_action.
     ///     player2: Optional second player. If None, a random player will be used.
     ///     seed: Optional random seed for reproducible games.
+    ///     config: Optional game configuration, kept for every reset. Defaults to the standard game.
+    ///     win_reward: The reward step gives for winning; losing gives its negative.
+    ///     step_penalty: Taken off the reward of every step, to favor shorter games.
+    ///     hand_lead_weight: Weight of the change in the acting player's hand-size lead
+    ///         (opponent's cards minus their own) over a step, added to its reward.
+    ///
+    /// Raises:
+    ///     TypeError: If a player has no choose_action method of its own.
     #[new]
-    #[pyo3(signature = (player1, player2=None, seed=None))]
+    #[pyo3(signature = (player1, player2=None, seed=None, config=None, win_reward=1.0, step_penalty=0.0, hand_lead_weight=0.0))]
     pub fn new(
-        player1: Py<crate::python::player_py::GamePlayerPy>,
-        player2: Option<Py<crate::python::player_py::GamePlayerPy>>,
+        player1: &PyAny,
+        player2: Option<&PyAny>,
         seed: Option<u64>,
+        config: Option<GameConfigPy>,
+        win_reward: f32,
+        step_penalty: f32,
+        hand_lead_weight: f32,
     ) -> PyResult<Self> {
-        let game = Box::new(Game::new());
-
-        // Apply seed if provided
-        if let Some(_seed_val) = seed {
-            // Note: This requires modifying Game::new() to accept a seed
-            // For now, we'll create the game and note that full seed support
-            // requires changes to the Game struct
+        let player1_wrapped = Box::new(PlayerPy::new(player1)?);
+        let player2_wrapped = player2.map(PlayerPy::new).transpose()?.map(Box::new);
+        let config = config.map(|c| c.config).unwrap_or_default();
+        let game = match seed {
+            Some(seed) => Game::with_config_seeded(config.clone(), seed),
+            None => Game::with_config(config.clone()),
         }
+        .map(Box::new)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
 
-        let player1_wrapped = Box::new(PlayerPy(player1));
-        let (player2_wrapped, random_p2) = match player2 {
-            Some(p2) => (Some(Box::new(PlayerPy(p2))), None),
+        let random_p2 = match player2_wrapped {
+            Some(_) => None,
             None => {
                 let rng =
                     seed.map(|s| Box::new(StdRng::seed_from_u64(s)) as Box<dyn rand::RngCore>);
-                (None, Some(Box::new(RandomPlayer::new(rng))))
+                Some(Box::new(RandomPlayer::new(rng)))
             }
         };
 
         Ok(GameEnvPy {
             game,
+            config,
+            rewards: RewardConfig {
+                win: win_reward,
+                step_penalty,
+                hand_lead_weight,
+            },
             player1: Some(player1_wrapped),
             player2: player2_wrapped,
             random_player2: random_p2,
         })
     }
 
-    /// Reset the game to its initial state.
+    /// Reset the game to its initial state, reusing the environment's configuration.
     ///
     /// Args:
-    ///     seed: Optional random seed for reproducible games (not yet fully supported).
+    ///     seed: Optional random seed for a reproducible deal.
+    ///     config: Optional configuration replacing the stored one for this and later resets.
     ///
     /// Returns:
     ///     The initial observable game state for player 1.
+    #[pyo3(signature = (seed=None, config=None))]
     pub fn reset(
         &mut self,
-        _seed: Option<u64>,
+        seed: Option<u64>,
+        config: Option<GameConfigPy>,
     ) -> PyResult<super::gamestate_py::ObservableGameStatePy> {
-        self.game = Box::new(Game::new());
-        // TODO: Apply seed when Game::new() supports it
+        let config = config
+            .map(|c| c.config)
+            .unwrap_or_else(|| self.config.clone());
+        let game = match seed {
+            Some(seed) => Game::with_config_seeded(config.clone(), seed),
+            None => Game::with_config(config.clone()),
+        }
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        // Only keep a new config once a game has been dealt with it
+        self.config = config;
+        *self.game = game;
         Ok(super::gamestate_py::ObservableGameStatePy {
-            game_state: self.game.game_state.observe(GamePlayer::Player1),
+            game_state: self.game.observe(GamePlayer::Player1),
         })
     }
 
@@ -102,7 +149,9 @@ impl GameEnvPy {
     /// Returns:
     ///     Tuple of (observation, reward, done, info) where:
     ///     - observation: The new observable game state
-    ///     - reward: The reward for the current player (0.0 during game, ±1.0 at end)
+    ///     - reward: The reward for the player who acted: by default 0.0 during the game and
+    ///       ±1.0 at the end, otherwise as the env's win_reward, step_penalty and
+    ///       hand_lead_weight score it
     ///     - done: Whether the game is over
     ///     - info: Dictionary with additional information
     pub fn step(
@@ -114,7 +163,7 @@ impl GameEnvPy {
         bool,
         Py<pyo3::types::PyDict>,
     )> {
-        let acting_player = self.game.game_state.acting_player;
+        let acting_player = self.game.game_state().acting_player;
         let legal_actions = self.game.legal_actions();
 
         if action_index as usize >= legal_actions.0.len() {
@@ -131,21 +180,17 @@ impl GameEnvPy {
         match self.game.step(action) {
             Ok(_) => {}
             Err(e) => {
-                return Err(PyValueError::new_err(format!("Illegal action: {}", e)));
+                return Err(PyValueError::new_err(e.to_string()));
             }
         }
 
         let is_done = self.game.is_over();
-        let rewards = self.game.get_rewards();
-        let current_reward = match acting_player {
-            GamePlayer::Player1 => rewards.0,
-            GamePlayer::Player2 => rewards.1,
-        };
+        let current_reward = self.game.step_reward(acting_player, &self.rewards);
 
         // Get the new observation for the next acting player
-        let next_acting_player = self.game.game_state.acting_player;
+        let next_acting_player = self.game.game_state().acting_player;
         let observation = super::gamestate_py::ObservableGameStatePy {
-            game_state: self.game.game_state.observe(next_acting_player),
+            game_state: self.game.observe(next_acting_player),
         };
 
         // Create info dict
@@ -161,6 +206,49 @@ impl GameEnvPy {
         Ok((observation, current_reward, is_done, info))
     }
 
+    /// Snapshot the current game, to come back to it with `restore_state`, e.g. while
+    /// searching from Python.
+    ///
+    /// The snapshot is the whole game, the deck order and the opponent's hidden cards
+    /// included, so this is a cheat-capable API for search and debugging. The players'
+    /// own state, such as a random player's RNG, is not part of it.
+    ///
+    /// Returns:
+    ///     An opaque handle to pass to restore_state.
+    pub fn clone_state(&self) -> PyResult<StateHandlePy> {
+        let snapshot =
+            serde_json::to_string(&self.game).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(StateHandlePy { snapshot })
+    }
+
+    /// Put the game back to a snapshot from `clone_state`. The configuration used for
+    /// later resets is left as it is.
+    ///
+    /// Args:
+    ///     handle: A handle returned by clone_state.
+    ///
+    /// Returns:
+    ///     The observable game state of the restored game for the acting player.
+    pub fn restore_state(
+        &mut self,
+        handle: &StateHandlePy,
+    ) -> PyResult<super::gamestate_py::ObservableGameStatePy> {
+        let game: Game = serde_json::from_str(&handle.snapshot)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        *self.game = game;
+        Ok(super::gamestate_py::ObservableGameStatePy {
+            game_state: self.game.observe(self.game.game_state().acting_player),
+        })
+    }
+
+    /// The configuration used to build games in this environment.
+    #[getter]
+    pub fn config(&self) -> GameConfigPy {
+        GameConfigPy {
+            config: self.config.clone(),
+        }
+    }
+
     /// Get the current observable game state for a player.
     ///
     /// Args:
@@ -176,28 +264,37 @@ impl GameEnvPy {
             Some(0) => GamePlayer::Player1,
             Some(1) => GamePlayer::Player2,
             Some(_) => return Err(PyValueError::new_err("Player must be 0 or 1")),
-            None => self.game.game_state.acting_player,
+            None => self.game.game_state().acting_player,
         };
         Ok(super::gamestate_py::ObservableGameStatePy {
-            game_state: self.game.game_state.observe(player_enum),
+            game_state: self.game.observe(player_enum),
         })
     }
 
     // Get the actual game state
     pub fn get_game_state(&self) -> super::gamestate_py::GameStatePy {
         super::gamestate_py::GameStatePy {
-            game_state: self.game.game_state.clone(),
+            game_state: self.game.game_state().clone(),
         }
     }
 
-    /// Get the legal actions for the current state.
+    /// Get the legal actions of the acting player in the current state, for masking a
+    /// policy between steps. `step` takes an index into this list.
     ///
     /// Returns:
     ///     The list of legal actions.
-    pub fn get_legal_actions(&self) -> super::actions_py::ActionListPy {
+    pub fn legal_actions(&self) -> super::actions_py::ActionListPy {
         super::actions_py::ActionListPy(self.game.legal_actions())
     }
 
+    /// Get the legal actions for the current state. Same as `legal_actions`.
+    ///
+    /// Returns:
+    ///     The list of legal actions.
+    pub fn get_legal_actions(&self) -> super::actions_py::ActionListPy {
+        self.legal_actions()
+    }
+
     /// Check if the game is over.
     ///
     /// Returns:
@@ -224,7 +321,8 @@ impl GameEnvPy {
 
     /// Play a full game to completion.
     ///
-    /// This method runs the game loop until completion, using the configured players.
+    /// This method runs the game loop until completion, using the configured players. If a
+    /// player has no action to give, the game stops there as a draw.
     ///
     /// Returns:
     ///     Tuple of (player1_reward, player2_reward).
@@ -234,46 +332,56 @@ impl GameEnvPy {
             .as_mut()
             .ok_or_else(|| PyValueError::new_err("Player1 is not set"))?;
 
-        let mut game_over = false;
-        while !game_over {
-            let pta = self.game.game_state.acting_player;
-            let actions = self.game.legal_actions();
-            let history =
-                ObservableGameHistory(self.game.history.iter().map(|x| x.observe(pta)).collect());
+        let p2: &mut dyn Player = match (&mut self.player2, &mut self.random_player2) {
+            (Some(p2_py), _) => p2_py.as_mut(),
+            (None, Some(random)) => random.as_mut(),
+            (None, None) => return Err(PyValueError::new_err("No player2 available")),
+        };
 
-            let action = match pta {
-                GamePlayer::Player1 => {
-                    p1.choose_action(self.game.game_state.observe(pta), actions.clone(), history)
-                }
-                GamePlayer::Player2 => match &mut self.player2 {
-                    Some(p2_py) => p2_py.choose_action(
-                        self.game.game_state.observe(pta),
-                        actions.clone(),
-                        history,
-                    ),
-                    None => self
-                        .random_player2
-                        .as_mut()
-                        .ok_or_else(|| PyValueError::new_err("No player2 available"))?
-                        .choose_action(self.game.game_state.observe(pta), actions.clone(), history),
-                },
-            };
-
-            match self.game.step(action) {
-                Ok(_) => (),
+        while !self.game.is_over() {
+            match self.game.play_turn(p1.as_mut(), p2) {
+                Ok(TurnOutcome::Played(_)) => (),
+                Ok(TurnOutcome::NoAction(_)) => return Ok((0.0, 0.0)),
                 Err(e) => {
                     return Err(PyValueError::new_err(format!(
                         "Error during game step: {}",
                         e
                     )));
                 }
-            };
-
-            game_over = self.game.is_over();
+            }
         }
         Ok(self.game.get_rewards())
     }
 
+    /// The observations and actions of `player` (0 or 1) at each of their decisions in the
+    /// current game, e.g. after `play()`.
+    ///
+    /// Returns:
+    ///     Tuple of (observations, actions) with shapes (T, state_dim) and (T,).
+    pub fn trajectory(&self, player: u8) -> PyResult<TrajectoryArrays> {
+        trajectory_arrays(&self.game, player_from_index(player)?)
+    }
+
+    /// A readable board of the current game as seen by player 1, for debugging e.g. in
+    /// notebooks.
+    ///
+    /// Args:
+    ///     mode: "unicode" for suit symbols or "ascii" for suit letters.
+    #[pyo3(signature = (mode="unicode"))]
+    pub fn render(&self, mode: &str) -> PyResult<String> {
+        let ascii = match mode {
+            "unicode" => false,
+            "ascii" => true,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown render mode {:?}",
+                    other
+                )))
+            }
+        };
+        Ok(self.game.observe(GamePlayer::Player1).render(ascii))
+    }
+
     #[pyo3(signature = (file_path))]
     pub fn save_game(&self, file_path: Py<PyString>) -> PyResult<()> {
         self.game.save_game(&PathBuf::from(file_path.to_string()));
@@ -286,11 +394,46 @@ impl GameEnvPy {
         num_actions()
     }
 
-    /// Get the shape of the game state as a numpy array.
+    /// The version of the observation layout returned by `to_numpy` and `state_shape`.
+    /// Store it with saved observations to detect layout changes when loading them.
+    #[staticmethod]
+    pub fn encoding_version() -> u32 {
+        ENCODING_VERSION
+    }
+
+    /// Describe the action space for gym-style wrappers: a discrete space with one index
+    /// per possible action.
+    pub fn action_space(&self) -> Py<pyo3::types::PyDict> {
+        Python::with_gil(|py| {
+            let dict = pyo3::types::PyDict::new(py);
+            dict.set_item("type", "discrete").unwrap();
+            dict.set_item("n", num_actions()).unwrap();
+            dict.into_py(py)
+        })
+    }
+
+    /// Describe the observation space for gym-style wrappers: a box of `state_shape()`
+    /// bytes under this environment's config.
+    pub fn observation_space(&self) -> PyResult<Py<pyo3::types::PyDict>> {
+        let shape = Self::state_shape(Some(self.config()))?;
+        Ok(Python::with_gil(|py| {
+            let dict = pyo3::types::PyDict::new(py);
+            dict.set_item("type", "box").unwrap();
+            dict.set_item("shape", shape).unwrap();
+            dict.set_item("low", 0).unwrap();
+            dict.set_item("high", 255).unwrap();
+            dict.into_py(py)
+        }))
+    }
+
+    /// Get the shape of the game state as a numpy array, under `config` if given. The
+    /// shape depends on the config's `hand_encoding`.
     #[staticmethod]
-    pub fn state_shape() -> PyResult<Vec<usize>> {
-        let game = Game::new();
-        let state = game.game_state.observe(GamePlayer::Player1);
+    #[pyo3(signature = (config=None))]
+    pub fn state_shape(config: Option<GameConfigPy>) -> PyResult<Vec<usize>> {
+        let config = config.map(|c| c.config).unwrap_or_default();
+        let game = Game::with_config(config).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let state = game.observe(GamePlayer::Player1);
         match state.to_numpy() {
             Ok(arr) => Ok(arr.shape().to_vec()),
             Err(e) => Err(pyo3::exceptions::PyException::new_err(format!(
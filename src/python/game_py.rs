@@ -0,0 +1,152 @@
+use ndarray::{Array1, Array2};
+use numpy::{PyArray1, PyArray2};
+use pyo3::exceptions::{PyException, PyIndexError, PyValueError};
+use pyo3::{pyclass, pymethods, Py, PyResult, Python};
+
+use crate::game::cards::Card;
+use crate::game::game::{Game, GameLogic};
+use crate::game::gamestate::{GamePlayer, ObservableGameHistory};
+
+use super::actions_py::ActionListPy;
+use super::card_py::CardPy;
+use super::config_py::GameConfigPy;
+use super::gamestate_py::ObservableGameStatePy;
+
+/// Direct, fully observable access to a game.
+///
+/// This is a test/debug API: unlike GameEnv it exposes both players' hands, so it must
+/// not be used to feed observations to an agent.
+#[pyclass(name = "Game")]
+pub struct GamePy {
+    pub game: Game,
+}
+
+/// A player's trajectory as `(states, actions)` numpy arrays, see `trajectory_arrays`.
+pub(crate) type TrajectoryArrays = (Py<PyArray2<u8>>, Py<PyArray1<u8>>);
+
+/// Stack the observations `player` had at each of their decisions into a `(T, state_dim)`
+/// array, alongside a `(T,)` array of the actions they took, encoded as action indices.
+pub(crate) fn trajectory_arrays(game: &Game, player: GamePlayer) -> PyResult<TrajectoryArrays> {
+    let (states, actions): (Vec<_>, Vec<_>) = game.decisions(player).into_iter().unzip();
+    let observations = match states.is_empty() {
+        true => {
+            let state_dim = game
+                .observe(player)
+                .to_numpy()
+                .map_err(PyException::new_err)?
+                .len();
+            Array2::zeros((0, state_dim))
+        }
+        false => ObservableGameHistory(states)
+            .to_numpy()
+            .map_err(PyException::new_err)?,
+    };
+    let labels = Array1::from_vec(actions.into_iter().map(u8::from).collect());
+    Ok(Python::with_gil(|py| {
+        (
+            PyArray2::from_array(py, &observations).to_owned(),
+            PyArray1::from_array(py, &labels).to_owned(),
+        )
+    }))
+}
+
+pub(crate) fn player_from_index(player: u8) -> PyResult<GamePlayer> {
+    match player {
+        0 => Ok(GamePlayer::Player1),
+        1 => Ok(GamePlayer::Player2),
+        _ => Err(PyValueError::new_err("Player must be 0 or 1")),
+    }
+}
+
+#[pymethods]
+impl GamePy {
+    /// Create a new game with a random deal.
+    ///
+    /// Args:
+    ///     config: Optional game configuration. Defaults to the standard game.
+    #[new]
+    #[pyo3(signature = (config=None))]
+    pub fn new(config: Option<GameConfigPy>) -> PyResult<Self> {
+        let game = Game::with_config(config.map(|c| c.config).unwrap_or_default())
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self { game })
+    }
+
+    /// Create a new game whose deal is determined by `seed`.
+    #[staticmethod]
+    #[pyo3(signature = (seed, config=None))]
+    pub fn new_seeded(seed: u64, config: Option<GameConfigPy>) -> PyResult<Self> {
+        let game = Game::with_config_seeded(config.map(|c| c.config).unwrap_or_default(), seed)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self { game })
+    }
+
+    /// The player whose turn it is (0 for Player1, 1 for Player2).
+    #[getter]
+    pub fn acting_player(&self) -> u8 {
+        u8::from(self.game.game_state().acting_player)
+    }
+
+    /// The legal actions in the current state.
+    pub fn legal_actions(&self) -> ActionListPy {
+        ActionListPy(self.game.legal_actions())
+    }
+
+    /// Take the action at `action_index` in `legal_actions()`.
+    pub fn step(&mut self, action_index: usize) -> PyResult<()> {
+        let legal_actions = self.game.legal_actions();
+        let action = *legal_actions.0.get(action_index).ok_or_else(|| {
+            PyIndexError::new_err(format!(
+                "Action index {} out of range. Legal actions: {}",
+                action_index,
+                legal_actions.0.len()
+            ))
+        })?;
+        self.game
+            .step(action)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// The observable state for a player (0 for Player1, 1 for Player2).
+    pub fn observe(&self, player: u8) -> PyResult<ObservableGameStatePy> {
+        Ok(ObservableGameStatePy {
+            game_state: self.game.observe(player_from_index(player)?),
+        })
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.game.is_over()
+    }
+
+    /// Tuple of (player1_reward, player2_reward).
+    pub fn get_rewards(&self) -> (f32, f32) {
+        self.game.get_rewards()
+    }
+
+    /// The winner (0 for Player1, 1 for Player2) or None if the game is not over or tied.
+    pub fn get_winner(&self) -> Option<u8> {
+        self.game.get_winner().map(u8::from)
+    }
+
+    /// The observations and actions of `player` (0 or 1) at each of their decisions so far.
+    ///
+    /// Returns:
+    ///     Tuple of (observations, actions): a (T, state_dim) array of observations and the
+    ///     (T,) action indices (see GameEnv.num_actions) taken from them.
+    pub fn trajectory(&self, player: u8) -> PyResult<TrajectoryArrays> {
+        trajectory_arrays(&self.game, player_from_index(player)?)
+    }
+
+    /// Both players' full hands as (player1_hand, player2_hand). Debug only.
+    pub fn debug_hands(&self) -> (Vec<CardPy>, Vec<CardPy>) {
+        let cards = |hand: &[Card]| hand.iter().map(|c| CardPy { card: *c }).collect();
+        (
+            cards(&self.game.game_state().hand1.0),
+            cards(&self.game.game_state().hand2.0),
+        )
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("Game: {:?}", self.game.game_state()))
+    }
+}
@@ -135,11 +135,48 @@ impl ObservableGameStatePy {
         Ok(u8::from(self.game_state.defender))
     }
 
+    /// The attacks on the table still waiting to be beaten.
+    #[getter]
+    fn get_undefended_count(&self) -> PyResult<u8> {
+        Ok(self.game_state.undefended_count)
+    }
+
     #[getter]
     fn get_cards_in_opp_hand(&self) -> PyResult<u8> {
         Ok(self.game_state.cards_in_opponent)
     }
 
+    #[getter]
+    fn get_bout(&self) -> PyResult<u32> {
+        Ok(self.game_state.bout)
+    }
+
+    /// The opponent's hand, only available in open-hands (cheat/debug) mode.
+    #[getter]
+    fn get_opponent_hand(&self) -> PyResult<Option<Vec<CardPy>>> {
+        Ok(self.game_state.opponent_hand.as_ref().map(|hand| get_cards_py(hand.0.clone())))
+    }
+
+    /// The table as (attack, defense) pairs in laying order; defense is None while the
+    /// attack is undefended.
+    #[getter]
+    fn get_table(&self) -> PyResult<Vec<(CardPy, Option<CardPy>)>> {
+        Ok(self
+            .game_state
+            .table()
+            .into_iter()
+            .map(|(attack, defense)| {
+                (CardPy { card: attack }, defense.map(|card| CardPy { card }))
+            })
+            .collect())
+    }
+
+    /// The trump card lying face up under the deck, None once it has been drawn.
+    #[getter]
+    fn get_face_up_card(&self) -> PyResult<Option<CardPy>> {
+        Ok(self.game_state.face_up_card.map(|card| CardPy { card }))
+    }
+
     pub fn to_numpy(&self) -> PyResult<pyo3::Py<PyArray<u8, Ix1>>> {
         match self.game_state.clone().to_numpy() {
             Ok(a) => Ok(Python::with_gil(|py| {
@@ -148,6 +185,19 @@ impl ObservableGameStatePy {
             Err(s) => Err(PyErr::new::<PyException, _>(s))
         }
     }
+
+    /// How much is unknown about the opponent's hand, in bits.
+    pub fn hidden_entropy(&self) -> f64 {
+        self.game_state.hidden_entropy()
+    }
+
+    /// The same layout as `to_numpy` as float32, with counts scaled into [0, 1].
+    pub fn to_numpy_f32(&self) -> pyo3::Py<PyArray<f32, Ix1>> {
+        let a = self.game_state.to_numpy_f32();
+        Python::with_gil(|py| {
+            PyArray1::from_array(py, &a).to_owned()
+        })
+    }
 }
 
 #[pymethods]
@@ -1,6 +1,8 @@
 pub mod actions_py;
 pub mod card_py;
+pub mod config_py;
 pub mod env_py;
+pub mod game_py;
 pub mod gamestate_py;
 pub mod player_py;
 pub mod utils;
@@ -1,5 +1,6 @@
-use pyo3::{exceptions::PyNotImplementedError, types::PyList, Py, PyAny, Python};
+use pyo3::exceptions::{PyNotImplementedError, PyTypeError, PyValueError};
 use pyo3::{pyclass, pymethods, IntoPy, PyErr, PyResult};
+use pyo3::{types::PyList, Py, PyAny, Python};
 
 use crate::{
     game::{
@@ -11,6 +12,8 @@ use crate::{
     ObservableGameStatePy,
 };
 
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
 use super::actions_py::ActionListPy;
 
 /// Base class for game players that can be subclassed in Python.
@@ -46,8 +49,72 @@ impl GamePlayerPy {
     }
 }
 
+/// A player that picks a uniformly random legal action, reproducibly when seeded. The
+/// same policy `GameEnv` uses when it gets no second player.
+#[pyclass(name = "RandomPlayer", extends = GamePlayerPy)]
+pub struct RandomPlayerPy {
+    rng: StdRng,
+}
+
+#[pymethods]
+impl RandomPlayerPy {
+    /// Create a random player.
+    ///
+    /// Args:
+    ///     seed: Optional random seed. Players with the same seed make the same choices.
+    #[new]
+    #[pyo3(signature = (seed=None))]
+    fn new(seed: Option<u64>) -> (Self, GamePlayerPy) {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        (Self { rng }, GamePlayerPy)
+    }
+
+    /// Choose the index of a uniformly random action from `actions`.
+    #[pyo3(signature = (_state, actions, _history=None))]
+    fn choose_action(
+        &mut self,
+        _state: &ObservableGameStatePy,
+        actions: &ActionListPy,
+        _history: Option<&ObservableGameHistoryPy>,
+    ) -> PyResult<u8> {
+        match actions.0 .0.len() {
+            0 => Err(PyValueError::new_err("No legal actions to choose from")),
+            n => Ok(self.rng.gen_range(0..n) as u8),
+        }
+    }
+}
+
 /// Internal wrapper that implements the Rust Player trait for Python GamePlayer instances
-pub struct PlayerPy(pub Py<GamePlayerPy>);
+pub struct PlayerPy(pub Py<PyAny>);
+
+impl PlayerPy {
+    /// Wrap `player` if it has a `choose_action` method of its own, so a bad player is
+    /// reported when it is passed in rather than by a panic on its first move.
+    pub fn new(player: &PyAny) -> PyResult<Self> {
+        let py = player.py();
+        let method = player
+            .getattr("choose_action")
+            .ok()
+            .filter(|method| method.is_callable());
+        if method.is_none() {
+            return Err(PyTypeError::new_err(format!(
+                "{} has no callable choose_action method; pass a GamePlayer subclass",
+                player.get_type().name()?
+            )));
+        }
+        let base = py.get_type::<GamePlayerPy>().getattr("choose_action")?;
+        if player.get_type().getattr("choose_action")?.is(base) {
+            return Err(PyTypeError::new_err(format!(
+                "{} does not override GamePlayer.choose_action",
+                player.get_type().name()?
+            )));
+        }
+        Ok(Self(player.into()))
+    }
+}
 
 impl Player for PlayerPy {
     fn choose_action(
@@ -55,7 +122,10 @@ impl Player for PlayerPy {
         state: ObservableGameState,
         actions: ActionList,
         history: ObservableGameHistory,
-    ) -> Action {
+    ) -> Option<Action> {
+        if actions.0.is_empty() {
+            return None;
+        }
         let state_py = ObservableGameStatePy { game_state: state };
         let actions_py = ActionListPy(actions.clone());
         let history_py = ObservableGameHistoryPy { history };
@@ -77,6 +147,11 @@ impl Player for PlayerPy {
                 actions.0.len() - 1
             );
         }
-        actions.0[res as usize]
+        Some(actions.0[res as usize])
+    }
+
+    /// Clones share the same Python object, and so any state it keeps.
+    fn clone_box(&self) -> Box<dyn Player> {
+        Box::new(PlayerPy(Python::with_gil(|py| self.0.clone_ref(py))))
     }
 }
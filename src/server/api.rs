@@ -1,33 +1,236 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::Json,
+    async_trait,
+    extract::{rejection::JsonRejection, FromRef, FromRequest, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::game::actions::Action;
-use crate::game::cards::{Card, Suit};
-use crate::game::game::GameLogic;
-use crate::game::gamestate::{GamePlayer, ObservableGameState};
-use crate::server::{game_session::GameSessions, GameSession};
+use crate::game::cards::{Card, Color, Hand, Suit};
+use crate::game::config::{GameConfig, HandEncoding, OpponentCount};
+use crate::game::deal_code::{deal_code, parse_deal_code};
+use crate::game::game::{BoutSummary, Game, GameLogic};
+use crate::game::gamestate::{
+    full_deck_ranks, GamePlayer, GameState, ObservableGameState, ENCODING_VERSION,
+};
+use crate::game::player::{Player, RandomPlayer};
+use crate::server::{
+    clock::{SharedClock, SystemClock},
+    difficulty::{AiKind, DifficultyTuner},
+    game_session::GameSessions,
+    idempotency::IdempotencyKeys,
+    rate_limit::{RateLimitConfig, RateLimiter},
+    seeds::DealSeeds,
+    websocket, GameSession,
+};
+
+/// Shared state of the API router. Handlers extract the part they need, e.g.
+/// `State<GameSessions>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub sessions: GameSessions,
+    /// Throttles moves and win probability estimates per client; other reads are not
+    /// limited.
+    pub move_limiter: Arc<RateLimiter>,
+    /// Picks the AI for players who identify themselves when creating a game.
+    pub difficulty: Arc<DifficultyTuner>,
+    /// The time source of new sessions.
+    pub clock: SharedClock,
+    /// Games already created per `Idempotency-Key`, so retried creates are not duplicated.
+    pub idempotency_keys: Arc<IdempotencyKeys>,
+    /// The seeds of games created without a deal code.
+    pub deal_seeds: Arc<DealSeeds>,
+}
+
+impl AppState {
+    pub fn new(sessions: GameSessions, move_limit: RateLimitConfig) -> Self {
+        Self {
+            sessions,
+            move_limiter: Arc::new(RateLimiter::new(move_limit)),
+            difficulty: Arc::new(DifficultyTuner::default()),
+            clock: Arc::new(SystemClock),
+            idempotency_keys: Arc::new(IdempotencyKeys::default()),
+            deal_seeds: Arc::new(DealSeeds::default()),
+        }
+    }
+}
+
+impl FromRef<AppState> for GameSessions {
+    fn from_ref(state: &AppState) -> Self {
+        state.sessions.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RateLimiter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.move_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<DifficultyTuner> {
+    fn from_ref(state: &AppState) -> Self {
+        state.difficulty.clone()
+    }
+}
+
+impl FromRef<AppState> for SharedClock {
+    fn from_ref(state: &AppState) -> Self {
+        state.clock.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<IdempotencyKeys> {
+    fn from_ref(state: &AppState) -> Self {
+        state.idempotency_keys.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<DealSeeds> {
+    fn from_ref(state: &AppState) -> Self {
+        state.deal_seeds.clone()
+    }
+}
+
+/// An error response with a JSON body `{"error": "..."}`, plus `"code"` if set.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+    /// Seconds until the request may be retried, sent as `Retry-After`.
+    pub retry_after: Option<u64>,
+    /// A stable name for the kind of error, for clients to match on.
+    pub code: Option<&'static str>,
+}
+
+/// The `ApiError` code of a request body that is not valid JSON for the endpoint.
+pub const BAD_REQUEST_BODY: &str = "BAD_REQUEST_BODY";
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        Self {
+            status,
+            message: status.canonical_reason().unwrap_or("Error").to_string(),
+            retry_after: None,
+            code: None,
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self {
+            status: rejection.status(),
+            message: rejection.body_text(),
+            retry_after: None,
+            code: Some(BAD_REQUEST_BODY),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = serde_json::json!({ "error": self.message });
+        if let Some(code) = self.code {
+            body["code"] = code.into();
+        }
+        let body = Json(body);
+        match self.retry_after {
+            Some(secs) => {
+                (self.status, [(header::RETRY_AFTER, secs.to_string())], body).into_response()
+            }
+            None => (self.status, body).into_response(),
+        }
+    }
+}
+
+/// Like `Json`, but a body that cannot be parsed is rejected with an `ApiError` coded
+/// `BAD_REQUEST_BODY` instead of axum's plain-text response.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(request, state).await?;
+        Ok(Self(value))
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateGameResponse {
     pub game_id: String,
+    /// The AI playing Player2 until someone joins.
+    pub ai: AiKind,
+}
+
+#[derive(Deserialize)]
+pub struct CreateGameQuery {
+    /// Seats the caller as Player1 and tunes the AI to their recent results.
+    pub player_id: Option<String>,
+    /// Deals the game a `deal_code` describes instead of a random one.
+    pub deal_code: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct GameStateResponse {
     pub game_id: String,
     pub game_state: ObservableGameStateDto,
+    /// The legal actions for the seat the state is for; empty when it is not their turn.
     pub legal_actions: Vec<ActionDto>,
     pub is_over: bool,
     pub winner: Option<String>,
     pub action_history: Vec<ActionHistoryEntryDto>,
+    /// The sorted ranks on the table; only cards of these ranks can be added to an attack.
+    pub table_ranks: Vec<u8>,
+    /// What the requester's move did: `attacked`, `defended`, `took`, `bout_cleared`,
+    /// `won`, `lost` or `draw`. Only set in the response to `make_move`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub move_effect: Option<String>,
+    /// The most recently finished bout. Only set in the responses to `make_move` and
+    /// `make_moves`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_bout: Option<BoutSummaryDto>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BoutSummaryDto {
+    pub attacker: String,
+    pub defender: String,
+    pub attacks: Vec<CardDto>,
+    pub defenses: Vec<CardDto>,
+    pub took: bool,
+    pub next_attacker: String,
+}
+
+impl BoutSummaryDto {
+    /// The summary with its cards flagged as trumps or not for `trump`.
+    pub fn with_trump(summary: &BoutSummary, trump: Suit) -> Self {
+        let cards = |cards: &[Card]| {
+            cards
+                .iter()
+                .map(|c| CardDto::with_trump(*c, trump))
+                .collect()
+        };
+        BoutSummaryDto {
+            attacker: format!("{:?}", summary.attacker),
+            defender: format!("{:?}", summary.defender),
+            attacks: cards(&summary.attacks),
+            defenses: cards(&summary.defenses),
+            took: summary.took,
+            next_attacker: format!("{:?}", summary.next_attacker),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,241 +252,1950 @@ pub struct ObservableGameStateDto {
     pub acting_player: String,
     pub defender: String,
     pub cards_in_opponent: u8,
+    pub bout: u32,
+    #[serde(default)]
+    pub opponent_void_suits: Vec<Suit>,
+    /// Only present in open-hands (cheat/debug) games.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opponent_hand: Option<Vec<CardDto>>,
+    /// The trump card lying face up under the deck; null once it has been drawn.
+    #[serde(default)]
+    pub face_up_card: Option<CardDto>,
+    /// The table as attacks in laying order, each with the card that beat it if any.
+    /// Derived from the two tables, so it is ignored in requests.
+    #[serde(default)]
+    pub table: Vec<TablePairDto>,
+    /// The attacks still waiting to be beaten. Derived from the two tables, so it is
+    /// ignored in requests.
+    #[serde(default)]
+    pub undefended_count: u8,
+    /// The ranks the game's deck is made of.
+    #[serde(default = "full_deck_ranks")]
+    pub deck_ranks: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TablePairDto {
+    pub attack: CardDto,
+    pub defense: Option<CardDto>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// A card on the wire. `color`, `rank_name` and `is_trump` are derived for UIs and ignored
+/// in requests.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(from = "CardRequestDto", into = "CardResponseDto")]
 pub struct CardDto {
-    pub suit: String,
+    pub suit: Suit,
     pub rank: u8,
+    pub color: Color,
+    /// Whether the card is a trump in the game it was sent for. Always false for cards
+    /// built without a game, see `CardDto::with_trump`.
+    pub is_trump: bool,
+}
+
+impl CardDto {
+    /// The card as sent for a game where `trump` is trumps.
+    pub fn with_trump(card: Card, trump: Suit) -> Self {
+        CardDto {
+            is_trump: card.is_trump(trump),
+            ..CardDto::from(card)
+        }
+    }
+}
+
+/// The fields sent for a card.
+#[derive(Serialize)]
+struct CardResponseDto {
+    suit: Suit,
+    rank: u8,
+    color: Color,
+    /// The rank as printed on the card, see `Card::rank_name`.
+    rank_name: &'static str,
+    is_trump: bool,
+}
+
+impl From<CardDto> for CardResponseDto {
+    fn from(card: CardDto) -> Self {
+        CardResponseDto {
+            suit: card.suit,
+            rank: card.rank,
+            color: card.color,
+            rank_name: Card::from(card).rank_name(),
+            is_trump: card.is_trump,
+        }
+    }
+}
+
+/// The fields clients send for a card.
+#[derive(Deserialize)]
+struct CardRequestDto {
+    suit: Suit,
+    rank: u8,
+}
+
+impl From<CardRequestDto> for CardDto {
+    fn from(card: CardRequestDto) -> Self {
+        CardDto::from(Card {
+            suit: card.suit,
+            rank: card.rank,
+        })
+    }
 }
 
 impl From<Card> for CardDto {
     fn from(card: Card) -> Self {
         CardDto {
-            suit: match card.suit {
-                Suit::Spades => "Spades".to_string(),
-                Suit::Hearts => "Hearts".to_string(),
-                Suit::Diamonds => "Diamonds".to_string(),
-                Suit::Clubs => "Clubs".to_string(),
-            },
+            suit: card.suit,
             rank: card.rank,
+            color: card.suit.color(),
+            is_trump: false,
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ActionDto {
-    pub action_type: String,
-    pub card: Option<CardDto>,
+impl From<CardDto> for Card {
+    fn from(card: CardDto) -> Self {
+        Card {
+            suit: card.suit,
+            rank: card.rank,
+        }
+    }
 }
 
-impl From<Action> for ActionDto {
-    fn from(action: Action) -> Self {
+/// An action on the wire: `{"action_type": "Attack", "card": {...}}`. Unknown action types
+/// and card actions without a card are rejected when the request is parsed.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "action_type")]
+pub enum ActionDto {
+    StopAttack,
+    Take,
+    Attack { card: CardDto },
+    Defend { card: CardDto },
+}
+
+impl ActionDto {
+    /// The action as sent for a game where `trump` is trumps, see `CardDto::with_trump`.
+    pub fn with_trump(action: Action, trump: Suit) -> Self {
         match action {
-            Action::StopAttack => ActionDto {
-                action_type: "StopAttack".to_string(),
-                card: None,
-            },
-            Action::Take => ActionDto {
-                action_type: "Take".to_string(),
-                card: None,
-            },
-            Action::Attack(card) => ActionDto {
-                action_type: "Attack".to_string(),
-                card: Some(CardDto::from(card)),
+            Action::Attack(card) => ActionDto::Attack {
+                card: CardDto::with_trump(card, trump),
             },
-            Action::Defend(card) => ActionDto {
-                action_type: "Defend".to_string(),
-                card: Some(CardDto::from(card)),
+            Action::Defend(card) => ActionDto::Defend {
+                card: CardDto::with_trump(card, trump),
             },
+            action => ActionDto::from(action),
+        }
+    }
+
+    pub fn card(&self) -> Option<CardDto> {
+        match self {
+            ActionDto::StopAttack | ActionDto::Take => None,
+            ActionDto::Attack { card } | ActionDto::Defend { card } => Some(*card),
+        }
+    }
+}
+
+impl Serialize for ActionDto {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Always write `card`, null for StopAttack/Take, as clients expect the key
+        #[derive(Serialize)]
+        struct Wire {
+            action_type: &'static str,
+            card: Option<CardDto>,
+        }
+        let action_type = match self {
+            ActionDto::StopAttack => "StopAttack",
+            ActionDto::Take => "Take",
+            ActionDto::Attack { .. } => "Attack",
+            ActionDto::Defend { .. } => "Defend",
+        };
+        Wire {
+            action_type,
+            card: self.card(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<Action> for ActionDto {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::StopAttack => ActionDto::StopAttack,
+            Action::Take => ActionDto::Take,
+            Action::Attack(card) => ActionDto::Attack { card: card.into() },
+            Action::Defend(card) => ActionDto::Defend { card: card.into() },
+        }
+    }
+}
+
+impl From<ActionDto> for Action {
+    fn from(action: ActionDto) -> Self {
+        match action {
+            ActionDto::StopAttack => Action::StopAttack,
+            ActionDto::Take => Action::Take,
+            ActionDto::Attack { card } => Action::Attack(card.into()),
+            ActionDto::Defend { card } => Action::Defend(card.into()),
         }
     }
 }
 
 impl From<ObservableGameState> for ObservableGameStateDto {
+    /// Every card is flagged as a trump or not for the game's trump suit.
     fn from(state: ObservableGameState) -> Self {
+        let trump = state.visible_card.suit;
+        let card = |card: Card| CardDto::with_trump(card, trump);
+        let cards = |cards: &[Card]| cards.iter().map(|c| card(*c)).collect();
+        let table = state
+            .table()
+            .into_iter()
+            .map(|(attack, defense)| TablePairDto {
+                attack: card(attack),
+                defense: defense.map(card),
+            })
+            .collect();
         ObservableGameStateDto {
             player: format!("{:?}", state.player),
             num_cards_in_deck: state.num_cards_in_deck,
-            attack_table: state
-                .attack_table
-                .iter()
-                .map(|c| CardDto::from(*c))
-                .collect(),
-            defense_table: state
-                .defense_table
-                .iter()
-                .map(|c| CardDto::from(*c))
-                .collect(),
-            hand: state.hand.0.iter().map(|c| CardDto::from(*c)).collect(),
-            visible_card: CardDto::from(state.visible_card),
+            attack_table: cards(&state.attack_table),
+            defense_table: cards(&state.defense_table),
+            hand: cards(&state.hand.0),
+            visible_card: card(state.visible_card),
             defender_has_taken: state.defender_has_taken,
             acting_player: format!("{:?}", state.acting_player),
             defender: format!("{:?}", state.defender),
             cards_in_opponent: state.cards_in_opponent,
+            bout: state.bout,
+            opponent_void_suits: state.opponent_void_suits,
+            opponent_hand: state.opponent_hand.map(|hand| cards(&hand.0)),
+            face_up_card: state.face_up_card.map(card),
+            table,
+            undefended_count: state.undefended_count,
+            deck_ranks: state.deck_ranks,
         }
     }
 }
 
+impl TryFrom<ObservableGameStateDto> for ObservableGameState {
+    type Error = String;
+
+    /// Rebuild the engine observation from a server response, rejecting unknown players,
+    /// impossible card ranks and a table with more defenses than attacks.
+    fn try_from(dto: ObservableGameStateDto) -> Result<Self, Self::Error> {
+        let cards = |cards: Vec<CardDto>| -> Result<Vec<Card>, String> {
+            cards
+                .into_iter()
+                .map(|card| match card.rank {
+                    6..=14 => Ok(Card::from(card)),
+                    rank => Err(format!("Invalid card rank {}", rank)),
+                })
+                .collect()
+        };
+        let attack_table = cards(dto.attack_table)?;
+        let defense_table = cards(dto.defense_table)?;
+        if defense_table.len() > attack_table.len() {
+            return Err(format!(
+                "{} defenses for {} attacks",
+                defense_table.len(),
+                attack_table.len()
+            ));
+        }
+        let undefended_count = (attack_table.len() - defense_table.len()) as u8;
+        if let Some(rank) = dto.deck_ranks.iter().find(|rank| !(6..=14).contains(*rank)) {
+            return Err(format!("Invalid deck rank {}", rank));
+        }
+        Ok(ObservableGameState {
+            player: dto.player.parse()?,
+            num_cards_in_deck: dto.num_cards_in_deck,
+            attack_table,
+            defense_table,
+            hand: Hand(cards(dto.hand)?),
+            visible_card: cards(vec![dto.visible_card])?[0],
+            defender_has_taken: dto.defender_has_taken,
+            acting_player: dto.acting_player.parse()?,
+            defender: dto.defender.parse()?,
+            cards_in_opponent: dto.cards_in_opponent,
+            bout: dto.bout,
+            opponent_void_suits: dto.opponent_void_suits,
+            opponent_hand: dto.opponent_hand.map(cards).transpose()?.map(Hand),
+            face_up_card: dto
+                .face_up_card
+                .map(|c| cards(vec![c]))
+                .transpose()?
+                .map(|c| c[0]),
+            hand_encoding: HandEncoding::default(),
+            undefended_count,
+            opponent_count: OpponentCount::default(),
+            deck_ranks: dto.deck_ranks,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TurnResponse {
+    pub acting_player: String,
+    pub role: String,
+    pub pending: String,
+    pub is_your_turn: bool,
+}
+
+#[derive(Deserialize)]
+pub struct TurnQuery {
+    pub player: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SeatQuery {
+    /// Identity of the caller, which decides whose hand they see.
+    pub player_id: Option<String>,
+    /// The seat asked for (`Player1` or `Player2`); defaults to the caller's own.
+    pub player: Option<String>,
+}
+
+pub fn parse_player(player: &str) -> Option<GamePlayer> {
+    player.parse().ok()
+}
+
+/// The caller's seat, see `GameSession::seat_of`. Asking for any other seat is refused with
+/// 403, since private information like the legal actions gives that seat's hand away.
+pub fn resolve_seat(session: &GameSession, query: &SeatQuery) -> Result<GamePlayer, StatusCode> {
+    let requested = match &query.player {
+        Some(player) => Some(parse_player(player).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    match session.seat_of(query.player_id.as_deref()) {
+        Some(seat) if requested.unwrap_or(seat) == seat => Ok(seat),
+        _ => Err(StatusCode::FORBIDDEN),
+    }
+}
+
 #[derive(Deserialize)]
 pub struct MakeMoveRequest {
-    pub action_type: String,
-    pub card: Option<CardDto>,
+    #[serde(flatten)]
+    pub action: ActionDto,
+    /// Identity of the caller; required once the acting seat has been joined.
+    pub player_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MakeMovesRequest {
+    /// The moves to make, in order; each must be for a seat the caller holds.
+    pub actions: Vec<ActionDto>,
+    /// Identity of the caller; required once either seat has been joined.
+    pub player_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct JoinGameRequest {
+    pub player_id: String,
+    /// The seat to take (`Player1` or `Player2`); defaults to the first free one.
+    pub player: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JoinGameResponse {
+    pub game_id: String,
+    pub player: String,
+}
+
+/// Build the state response for `player` from a single snapshot of the game.
+pub fn build_state_response(
+    game_id: String,
+    session: &GameSession,
+    player: GamePlayer,
+) -> GameStateResponse {
+    let snapshot = session.game.snapshot(player);
+    let trump = snapshot.state.visible_card.suit;
+    let action_history: Vec<ActionHistoryEntryDto> = session
+        .action_history
+        .iter()
+        .map(|entry| ActionHistoryEntryDto {
+            player: format!("{:?}", entry.player),
+            action: ActionDto::with_trump(entry.action, trump),
+            timestamp: entry.timestamp,
+        })
+        .collect();
+
+    GameStateResponse {
+        game_id,
+        game_state: ObservableGameStateDto::from(snapshot.state),
+        legal_actions: build_actions_response(session, player).legal_actions,
+        is_over: session.game.is_over(),
+        winner: session.game.get_winner().map(|p| format!("{:?}", p)),
+        action_history,
+        table_ranks: session.game.table_ranks(),
+        move_effect: None,
+        last_bout: None,
+    }
+}
+
+/// Describe the consequence of `action`, just taken by `player`, from the state of the
+/// game before and after the move.
+fn move_effect(
+    action: Action,
+    player: GamePlayer,
+    before: &GameState,
+    game: &Game,
+) -> &'static str {
+    if game.is_over() {
+        return match game.get_winner() {
+            Some(winner) if winner == player => "won",
+            Some(_) => "lost",
+            None => "draw",
+        };
+    }
+    let bout_resolved = game.game_state().bout > before.bout;
+    match action {
+        Action::Take => "took",
+        Action::StopAttack if bout_resolved && before.defender_has_taken => "took",
+        Action::StopAttack | Action::Defend(_) if bout_resolved => "bout_cleared",
+        Action::Defend(_) => "defended",
+        Action::Attack(_) | Action::StopAttack => "attacked",
+    }
+}
+
+/// The header a client sets to make retrying `create_game` safe.
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// The part of a deal code's config a caller may choose: the deck and the rules. Cheat
+/// mode and the observation settings stay at the server's defaults.
+fn dealt_rules(config: GameConfig) -> GameConfig {
+    let GameConfig {
+        lowest_rank,
+        ranks,
+        hand_size,
+        refill_order,
+        defense_order,
+        next_attacker,
+        max_attacks_per_bout,
+        no_trump_lead,
+        reveal_opponent: _,
+        observation: _,
+    } = config;
+    GameConfig {
+        lowest_rank,
+        ranks,
+        hand_size,
+        refill_order,
+        defense_order,
+        next_attacker,
+        max_attacks_per_bout,
+        no_trump_lead,
+        ..GameConfig::default()
+    }
 }
 
+/// Start a game against the AI. With `?player_id=...` the caller takes Player1 and the AI
+/// gets easier or harder depending on how they have been doing against it. A request with
+/// an `Idempotency-Key` header that already created a game gets that game back. With
+/// `?deal_code=...` the game is dealt as in the game the code came from, with its rules
+/// but without any cheat or observation settings it carries; an invalid code is a 400.
 pub async fn create_game(
     State(sessions): State<GameSessions>,
+    State(difficulty): State<Arc<DifficultyTuner>>,
+    State(clock): State<SharedClock>,
+    State(idempotency_keys): State<Arc<IdempotencyKeys>>,
+    State(deal_seeds): State<Arc<DealSeeds>>,
+    headers: HeaderMap,
+    Query(query): Query<CreateGameQuery>,
 ) -> Result<Json<CreateGameResponse>, StatusCode> {
-    let session = GameSession::new();
-    let game_id = session.id;
-
-    sessions
-        .write()
-        .await
-        .insert(game_id, Arc::new(tokio::sync::RwLock::new(session)));
+    let key = match headers.get(IDEMPOTENCY_KEY) {
+        Some(key) => Some(
+            key.to_str()
+                .map_err(|_| StatusCode::BAD_REQUEST)?
+                .to_string(),
+        ),
+        None => None,
+    };
+    let (config, seed) = match &query.deal_code {
+        Some(code) => parse_deal_code(code)
+            .map(|(config, seed)| (dealt_rules(config), Some(seed)))
+            .map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => (GameConfig::default(), None),
+    };
+    let now = clock.now();
+    let create = || {
+        // Only drawn for games actually created, so retries don't shift later deals
+        let seed = seed.unwrap_or_else(|| deal_seeds.next());
+        let mut session = GameSession::with_clock(clock.clone());
+        session.game = Game::with_config_seeded(config.clone(), seed)
+            .expect("deal codes only describe valid configs");
+        session.deal_code = Some(deal_code(&config, seed));
+        let game_id = session.id;
+        if let Some(player_id) = query.player_id.clone() {
+            session.ai = difficulty.select(&player_id);
+            session.assign_player(GamePlayer::Player1, player_id);
+        }
+        sessions.insert(game_id, Arc::new(tokio::sync::RwLock::new(session)));
+        game_id
+    };
+    let game_id = match key {
+        Some(key) => {
+            idempotency_keys.get_or_create(key, now, |id| sessions.contains_key(id), create)
+        }
+        None => create(),
+    };
+    // Only gone if it expired in the meantime
+    let session = session_by_id(&sessions, &game_id).ok_or(StatusCode::NOT_FOUND)?;
+    let session = session.read().await;
 
     Ok(Json(CreateGameResponse {
         game_id: game_id.to_string(),
+        ai: session.ai,
     }))
 }
 
+/// Take one request from the caller's bucket in `limiter`. Anonymous callers share one
+/// bucket per game.
+fn check_rate_limit(
+    limiter: &RateLimiter,
+    player_id: Option<&str>,
+    game_id: &str,
+) -> Result<(), ApiError> {
+    let client = match player_id {
+        Some(player_id) => format!("player:{}", player_id),
+        None => format!("game:{}", game_id),
+    };
+    if limiter.check(&client) {
+        return Ok(());
+    }
+    Err(ApiError {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        message: "Too many requests, slow down".to_string(),
+        retry_after: Some(limiter.retry_after(&client).as_secs_f64().ceil() as u64),
+        code: None,
+    })
+}
+
+/// The session of game `game_id`, cloned out of the map so no map entry is held while the
+/// caller waits on the session's lock.
+fn session_by_id(
+    sessions: &GameSessions,
+    game_id: &Uuid,
+) -> Option<Arc<tokio::sync::RwLock<GameSession>>> {
+    sessions.get(game_id).map(|session| session.clone())
+}
+
+/// The state as the caller's seat sees it, see `resolve_seat`.
 pub async fn get_game_state(
     State(sessions): State<GameSessions>,
     Path(game_id): Path<String>,
+    Query(query): Query<SeatQuery>,
 ) -> Result<Json<GameStateResponse>, StatusCode> {
     let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    let sessions_read = sessions.read().await;
-    let session = sessions_read.get(&uuid).ok_or(StatusCode::NOT_FOUND)?;
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND)?;
 
     let mut game = session.write().await;
+    let player = resolve_seat(&game, &query)?;
 
     // Make AI moves if it's Player2's turn - process_player_turns handles the loop
     game.make_ai_move_if_needed();
 
-    // Use GameLogic methods for consistency
-    let observable_state = game.game.game_state.observe(GamePlayer::Player1);
-    let legal_actions = game.game.get_actions();
-    let is_over = game.game.is_over();
-    let winner = game.game.get_winner().map(|p| format!("{:?}", p));
-    let action_history: Vec<ActionHistoryEntryDto> = game
-        .action_history
-        .iter()
-        .map(|entry| ActionHistoryEntryDto {
-            player: format!("{:?}", entry.player),
-            action: ActionDto::from(entry.action),
-            timestamp: entry.timestamp,
-        })
-        .collect();
-
-    Ok(Json(GameStateResponse {
-        game_id: game_id.clone(),
-        game_state: ObservableGameStateDto::from(observable_state),
-        legal_actions: legal_actions
-            .0
-            .iter()
-            .map(|a| ActionDto::from(*a))
-            .collect(),
-        is_over,
-        winner,
-        action_history,
-    }))
+    Ok(Json(build_state_response(game_id, &game, player)))
 }
 
 pub async fn make_move(
     State(sessions): State<GameSessions>,
+    State(limiter): State<Arc<RateLimiter>>,
+    State(difficulty): State<Arc<DifficultyTuner>>,
     Path(game_id): Path<String>,
-    Json(request): Json<MakeMoveRequest>,
-) -> Result<Json<GameStateResponse>, StatusCode> {
+    ApiJson(request): ApiJson<MakeMoveRequest>,
+) -> Result<Json<GameStateResponse>, ApiError> {
     let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    let sessions_read = sessions.read().await;
-    let session = sessions_read.get(&uuid).ok_or(StatusCode::NOT_FOUND)?;
+    check_rate_limit(&limiter, request.player_id.as_deref(), &game_id)?;
+
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND)?;
 
     let mut game = session.write().await;
 
-    // Convert request to Action
-    let action = match request.action_type.as_str() {
-        "StopAttack" => Action::StopAttack,
-        "Take" => Action::Take,
-        "Attack" => {
-            let card_dto = request.card.ok_or(StatusCode::BAD_REQUEST)?;
-            let card = Card {
-                suit: match card_dto.suit.as_str() {
-                    "Spades" => Suit::Spades,
-                    "Hearts" => Suit::Hearts,
-                    "Diamonds" => Suit::Diamonds,
-                    "Clubs" => Suit::Clubs,
-                    _ => return Err(StatusCode::BAD_REQUEST),
-                },
-                rank: card_dto.rank,
-            };
-            Action::Attack(card)
-        }
-        "Defend" => {
-            let card_dto = request.card.ok_or(StatusCode::BAD_REQUEST)?;
-            let card = Card {
-                suit: match card_dto.suit.as_str() {
-                    "Spades" => Suit::Spades,
-                    "Hearts" => Suit::Hearts,
-                    "Diamonds" => Suit::Diamonds,
-                    "Clubs" => Suit::Clubs,
-                    _ => return Err(StatusCode::BAD_REQUEST),
-                },
-                rank: card_dto.rank,
-            };
-            Action::Defend(card)
-        }
-        _ => return Err(StatusCode::BAD_REQUEST),
-    };
+    if !game.can_move(request.player_id.as_deref()) {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+    // The response is for the mover, who may have joined as either seat
+    let seat = game
+        .seat_of(request.player_id.as_deref())
+        .ok_or(StatusCode::FORBIDDEN)?;
+
+    let action = Action::from(request.action);
 
-    // Get the current acting player before the move
-    let acting_player = game.game.game_state.acting_player;
+    // Get the current acting player and state before the move
+    let acting_player = game.game.game_state().acting_player;
+    let before = game.game.game_state().clone();
 
     // Execute the action using GameLogic::step
-    game.game
-        .step(action)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    game.game.step(action).map_err(|e| ApiError {
+        status: StatusCode::BAD_REQUEST,
+        message: e.to_string(),
+        retry_after: None,
+        code: None,
+    })?;
+    let effect = move_effect(action, acting_player, &before, &game.game);
 
     // Record the player's action
     game.record_action(acting_player, action);
 
     // Make AI moves if it's now Player2's turn - process_player_turns handles the loop
     game.make_ai_move_if_needed();
+    if let Some((human, won)) = game.take_vs_ai_result() {
+        difficulty.record(&human, won);
+    }
 
-    // Get updated state using GameLogic methods
-    let observable_state = game.game.game_state.observe(GamePlayer::Player1);
-    let legal_actions = game.game.get_actions();
-    let is_over = game.game.is_over();
-    let winner = game.game.get_winner().map(|p| format!("{:?}", p));
-    let action_history: Vec<ActionHistoryEntryDto> = game
-        .action_history
-        .iter()
-        .map(|entry| ActionHistoryEntryDto {
-            player: format!("{:?}", entry.player),
-            action: ActionDto::from(entry.action),
-            timestamp: entry.timestamp,
-        })
-        .collect();
+    let mut response = build_state_response(game_id, &game, seat);
+    response.move_effect = Some(effect.to_string());
+    response.last_bout = game
+        .game
+        .last_bout
+        .as_ref()
+        .map(|bout| BoutSummaryDto::with_trump(bout, game.game.game_state().visible_card.suit));
+    Ok(Json(response))
+}
+
+/// Make a list of moves in one request, e.g. to replay a saved game on a fresh session.
+/// Either every move is made or, if one is illegal or for a seat the caller does not hold,
+/// none are and the response names the index of the first such move as `illegal_index`.
+pub async fn make_moves(
+    State(sessions): State<GameSessions>,
+    State(limiter): State<Arc<RateLimiter>>,
+    State(difficulty): State<Arc<DifficultyTuner>>,
+    Path(game_id): Path<String>,
+    ApiJson(request): ApiJson<MakeMovesRequest>,
+) -> Result<Json<GameStateResponse>, Response> {
+    let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST.into_response())?;
+
+    // A batch costs the same as a single move
+    check_rate_limit(&limiter, request.player_id.as_deref(), &game_id)
+        .map_err(IntoResponse::into_response)?;
+
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND.into_response())?;
+
+    let mut game = session.write().await;
+
+    let player_id = request.player_id.as_deref();
+    let seat = game
+        .seat_of(player_id)
+        .ok_or(StatusCode::FORBIDDEN.into_response())?;
+
+    let actions: Vec<Action> = request.actions.into_iter().map(Action::from).collect();
+    let played = game.game.actions.len();
+    // Played out on a copy, since who acts is only known once the moves before are made
+    let mut trial = game.game.clone();
+    trial.apply_actions(&actions).map_err(|(index, e)| {
+        let body = serde_json::json!({ "error": e.to_string(), "illegal_index": index });
+        (StatusCode::BAD_REQUEST, Json(body)).into_response()
+    })?;
+    let foreign = (0..actions.len())
+        .find(|index| !game.holds_seat(trial.history[played + index].acting_player, player_id));
+    if let Some(index) = foreign {
+        let body = serde_json::json!({
+            "error": "move is for a seat that is not the caller's",
+            "illegal_index": index,
+        });
+        return Err((StatusCode::FORBIDDEN, Json(body)).into_response());
+    }
+    game.game = trial;
+
+    for (index, action) in actions.into_iter().enumerate() {
+        let acting_player = game.game.history[played + index].acting_player;
+        game.record_action(acting_player, action);
+    }
+
+    game.make_ai_move_if_needed();
+    if let Some((human, won)) = game.take_vs_ai_result() {
+        difficulty.record(&human, won);
+    }
+
+    let mut response = build_state_response(game_id, &game, seat);
+    response.last_bout = game
+        .game
+        .last_bout
+        .as_ref()
+        .map(|bout| BoutSummaryDto::with_trump(bout, game.game.game_state().visible_card.suit));
+    Ok(Json(response))
+}
+
+/// Claim a seat in the game. Joining as Player2 turns off the AI opponent.
+pub async fn join_game(
+    State(sessions): State<GameSessions>,
+    Path(game_id): Path<String>,
+    ApiJson(request): ApiJson<JoinGameRequest>,
+) -> Result<Json<JoinGameResponse>, StatusCode> {
+    let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let seat = match request.player {
+        Some(player) => Some(parse_player(&player).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut game = session.write().await;
+
+    let seat = match seat {
+        Some(seat) => seat,
+        None => [GamePlayer::Player1, GamePlayer::Player2]
+            .into_iter()
+            .find(|p| game.get_player_id(*p).is_none())
+            .ok_or(StatusCode::CONFLICT)?,
+    };
+    if !game.assign_player(seat, request.player_id) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    Ok(Json(JoinGameResponse {
+        game_id,
+        player: format!("{:?}", seat),
+    }))
+}
 
-    Ok(Json(GameStateResponse {
-        game_id: game_id.clone(),
-        game_state: ObservableGameStateDto::from(observable_state),
-        legal_actions: legal_actions
+/// Report whose turn it is and what kind of action is expected, from the point of
+/// view of the requesting seat (`?player=Player1|Player2`, defaults to Player1).
+pub async fn get_turn(
+    State(sessions): State<GameSessions>,
+    Path(game_id): Path<String>,
+    Query(query): Query<TurnQuery>,
+) -> Result<Json<TurnResponse>, StatusCode> {
+    let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let requester = match query.player {
+        Some(player) => parse_player(&player).ok_or(StatusCode::BAD_REQUEST)?,
+        None => GamePlayer::Player1,
+    };
+
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut game = session.write().await;
+
+    // Make AI moves if it's Player2's turn so the answer matches get_game_state
+    game.make_ai_move_if_needed();
+
+    let state = game.game.game_state();
+    let role = match state.defending_player == requester {
+        true => "defender",
+        false => "attacker",
+    };
+    let pending = match state.acting_player == state.defending_player {
+        true => "defense",
+        false => "attack",
+    };
+
+    Ok(Json(TurnResponse {
+        acting_player: format!("{:?}", state.acting_player),
+        role: role.to_string(),
+        pending: pending.to_string(),
+        is_your_turn: state.acting_player == requester && !game.game.is_over(),
+    }))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerGameSummary {
+    pub game_id: String,
+    /// The seat the player holds.
+    pub player: String,
+    pub acting_player: String,
+    pub is_over: bool,
+    /// The winning seat once the game is over; null while it runs or after a draw.
+    pub winner: Option<String>,
+}
+
+/// The games in which `player_id` holds a seat, for a "my games" view. Scans every
+/// session, so the order follows the session map.
+pub async fn get_player_games(
+    State(sessions): State<GameSessions>,
+    Path(player_id): Path<String>,
+) -> Json<Vec<PlayerGameSummary>> {
+    // Map entries must not be held across the awaits below
+    let all: Vec<_> = sessions.iter().map(|entry| entry.value().clone()).collect();
+    let mut games = Vec::new();
+    for session in all {
+        let session = session.read().await;
+        let seat = [GamePlayer::Player1, GamePlayer::Player2]
+            .into_iter()
+            .find(|seat| session.get_player_id(*seat).as_deref() == Some(player_id.as_str()));
+        if let Some(seat) = seat {
+            games.push(PlayerGameSummary {
+                game_id: session.id.to_string(),
+                player: format!("{:?}", seat),
+                acting_player: format!("{:?}", session.game.game_state().acting_player),
+                is_over: session.game.is_over(),
+                winner: session.game.get_winner().map(|p| format!("{:?}", p)),
+            });
+        }
+    }
+    Json(games)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ActionsResponse {
+    /// The legal actions for the requested seat; empty when it is not their turn.
+    pub legal_actions: Vec<ActionDto>,
+}
+
+/// The actions open to `player` in the session's current state. Read-only: unlike
+/// `get_turn`, the AI is not given its move first.
+pub fn build_actions_response(session: &GameSession, player: GamePlayer) -> ActionsResponse {
+    let trump = session.game.game_state().visible_card.suit;
+    let legal_actions = match session.game.game_state().acting_player == player {
+        true => session
+            .game
+            .legal_actions()
             .0
             .iter()
-            .map(|a| ActionDto::from(*a))
+            .map(|a| ActionDto::with_trump(*a, trump))
             .collect(),
-        is_over,
-        winner,
-        action_history,
+        false => Vec::new(),
+    };
+    ActionsResponse { legal_actions }
+}
+
+/// The caller's legal actions, for the seat `resolve_seat` gives them.
+pub async fn get_actions(
+    State(sessions): State<GameSessions>,
+    Path(game_id): Path<String>,
+    Query(query): Query<SeatQuery>,
+) -> Result<Json<ActionsResponse>, StatusCode> {
+    let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND)?;
+    let game = session.read().await;
+    let player = resolve_seat(&game, &query)?;
+    Ok(Json(build_actions_response(&game, player)))
+}
+
+/// How many playouts `get_win_prob` runs unless asked otherwise.
+const DEFAULT_WIN_PROB_ROLLOUTS: usize = 200;
+/// The most playouts one `get_win_prob` request may ask for.
+const MAX_WIN_PROB_ROLLOUTS: usize = 2000;
+
+#[derive(Deserialize)]
+pub struct WinProbQuery {
+    /// Identity of the caller, see `SeatQuery`.
+    pub player_id: Option<String>,
+    pub player: Option<String>,
+    /// The number of playouts, capped at `MAX_WIN_PROB_ROLLOUTS`.
+    pub rollouts: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WinProbResponse {
+    /// The seat whose view the estimate is based on.
+    pub player: String,
+    /// The fraction of playouts won by each seat; the rest were drawn.
+    pub player1: f32,
+    pub player2: f32,
+    pub rollouts: usize,
+}
+
+/// Estimate each seat's chance of winning with random playouts, see
+/// `Game::estimate_win_prob`. Only what the caller's seat can see is used, see
+/// `resolve_seat`; the hidden cards are guessed afresh for every playout. Each request
+/// counts against the move rate limit, and the playouts run off the async executor.
+pub async fn get_win_prob(
+    State(sessions): State<GameSessions>,
+    State(limiter): State<Arc<RateLimiter>>,
+    Path(game_id): Path<String>,
+    Query(query): Query<WinProbQuery>,
+) -> Result<Json<WinProbResponse>, ApiError> {
+    let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    check_rate_limit(&limiter, query.player_id.as_deref(), &game_id)?;
+    let rollouts = query
+        .rollouts
+        .unwrap_or(DEFAULT_WIN_PROB_ROLLOUTS)
+        .min(MAX_WIN_PROB_ROLLOUTS);
+
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND)?;
+    // Play out a copy so the session is not locked while the playouts run
+    let (player, game) = {
+        let session = session.read().await;
+        let seat = SeatQuery {
+            player_id: query.player_id,
+            player: query.player,
+        };
+        (resolve_seat(&session, &seat)?, session.game.clone())
+    };
+
+    let (player1, player2) = tokio::task::spawn_blocking(move || {
+        let policy = || Box::new(RandomPlayer::new(None)) as Box<dyn Player>;
+        game.estimate_win_prob(player, rollouts, policy, &mut StdRng::from_entropy())
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(WinProbResponse {
+        player: format!("{:?}", player),
+        player1,
+        player2,
+        rollouts,
+    }))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HandSizesResponse {
+    pub player1: Vec<usize>,
+    pub player2: Vec<usize>,
+}
+
+/// Both players' hand sizes after every move of a finished game, for analytics. Returns
+/// 409 while the game is still running so it cannot leak how play is going.
+pub async fn get_hand_sizes(
+    State(sessions): State<GameSessions>,
+    Path(game_id): Path<String>,
+) -> Result<Json<HandSizesResponse>, StatusCode> {
+    let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND)?;
+    let game = session.read().await;
+
+    if !game.game.is_over() {
+        return Err(StatusCode::CONFLICT);
+    }
+    let (player1, player2) = game.game.hand_size_series();
+    Ok(Json(HandSizesResponse { player1, player2 }))
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// `text` (the default) for a readable transcript, `json` for the replayable `GameLog`,
+    /// `deal_code` for the code that deals the game again.
+    pub format: Option<String>,
+}
+
+/// Download a finished game to share: `?format=text` for a transcript, `?format=json` for
+/// the `GameLog`, `?format=deal_code` for its deal code (404 if the deal is not known).
+/// Returns 409 while the game is still running, since all of them give away every hand.
+pub async fn export_game(
+    State(sessions): State<GameSessions>,
+    Path(game_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, StatusCode> {
+    let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let session = session_by_id(&sessions, &uuid).ok_or(StatusCode::NOT_FOUND)?;
+    let game = session.read().await;
+
+    if !game.game.is_over() {
+        return Err(StatusCode::CONFLICT);
+    }
+    let (content_type, extension, body) = match query.format.as_deref().unwrap_or("text") {
+        "text" => ("text/plain; charset=utf-8", "txt", game.game.transcript()),
+        "json" => (
+            "application/json",
+            "json",
+            serde_json::to_string_pretty(&game.game.log())
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        ),
+        "deal_code" => (
+            "text/plain; charset=utf-8",
+            "txt",
+            game.deal_code.clone().ok_or(StatusCode::NOT_FOUND)?,
+        ),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let disposition = format!("attachment; filename=\"durak-{}.{}\"", game_id, extension);
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<usize>,
+}
+
+/// Liveness: the server is up and answering requests.
+pub async fn health() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+        sessions: None,
+    })
+}
+
+/// Readiness: the server can serve games, with the number it is running.
+pub async fn ready(
+    State(sessions): State<GameSessions>,
+) -> Result<Json<HealthResponse>, StatusCode> {
+    Ok(Json(HealthResponse {
+        status: "ready".to_string(),
+        sessions: Some(sessions.len()),
     }))
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionResponse {
+    pub version: String,
+    /// The observation layout version, see `ENCODING_VERSION`.
+    pub encoding_version: u32,
+}
+
+pub async fn version() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        encoding_version: ENCODING_VERSION,
+    })
+}
+
+/// Where the server binary mounts the API unless told otherwise.
+pub const DEFAULT_API_PREFIX: &str = "/api";
+
+/// Mount `api_router` under `prefix`, e.g. `/api` or `/durak/v1` behind a reverse proxy.
+/// Leading and trailing slashes are optional; an empty prefix serves the API at the root.
+pub fn nest_api(prefix: &str, api_router: Router) -> Router {
+    match prefix.trim_matches('/') {
+        "" => Router::new().merge(api_router),
+        path => Router::new().nest(&format!("/{}", path), api_router),
+    }
+}
+
+/// The API router with the default move rate limit.
 pub fn create_api_router(sessions: GameSessions) -> Router {
+    create_api_router_with_state(AppState::new(sessions, RateLimitConfig::default()))
+}
+
+pub fn create_api_router_with_state(state: AppState) -> Router {
     Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .route("/version", get(version))
         .route("/games", post(create_game))
         .route("/games/:game_id", get(get_game_state))
+        .route("/games/:game_id/join", post(join_game))
+        .route("/games/:game_id/turn", get(get_turn))
+        .route("/games/:game_id/actions", get(get_actions))
+        .route("/games/:game_id/hand_sizes", get(get_hand_sizes))
+        .route("/games/:game_id/win_prob", get(get_win_prob))
+        .route("/games/:game_id/export", get(export_game))
+        .route("/games/:game_id/ws", get(websocket::game_ws))
         .route("/games/:game_id/move", post(make_move))
-        .with_state(sessions)
+        .route("/games/:game_id/moves", post(make_moves))
+        .route("/players/:player_id/games", get(get_player_games))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::game::config::ObservationConfig;
+    use crate::game::game::GameLog;
+
+    async fn two_player_session() -> (GameSessions, String, ActionDto) {
+        let mut session = GameSession::new();
+        let game_id = session.id;
+        session.assign_player(GamePlayer::Player1, "alice".to_string());
+        session.assign_player(GamePlayer::Player2, "bob".to_string());
+        session.game.game_state_mut().acting_player = GamePlayer::Player1;
+        session.game.game_state_mut().defending_player = GamePlayer::Player2;
+        let action = ActionDto::from(session.game.legal_actions().0[0]);
+
+        let sessions = GameSessions::default();
+        sessions.insert(game_id, Arc::new(RwLock::new(session)));
+        (sessions, game_id.to_string(), action)
+    }
+
+    fn default_limiter() -> State<Arc<RateLimiter>> {
+        State(Arc::new(RateLimiter::new(RateLimitConfig::default())))
+    }
+
+    fn default_tuner() -> State<Arc<DifficultyTuner>> {
+        State(Arc::new(DifficultyTuner::default()))
+    }
+
+    fn move_request(action: &ActionDto, player_id: &str) -> MakeMoveRequest {
+        MakeMoveRequest {
+            action: *action,
+            player_id: Some(player_id.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_action_dto_wire_format() {
+        let attack = ActionDto::from(Action::Attack(Card {
+            suit: Suit::Hearts,
+            rank: 10,
+        }));
+        let json = serde_json::json!({
+            "action_type": "Attack",
+            "card": {
+                "suit": "Hearts",
+                "rank": 10,
+                "color": "Red",
+                "rank_name": "10",
+                "is_trump": false,
+            },
+        });
+        assert_eq!(serde_json::to_value(attack).unwrap(), json);
+        assert_eq!(serde_json::from_value::<ActionDto>(json).unwrap(), attack);
+        // Requests may leave out the color, which follows from the suit anyway
+        let request = serde_json::json!({
+            "action_type": "Attack",
+            "card": {"suit": "Hearts", "rank": 10},
+        });
+        assert_eq!(
+            serde_json::from_value::<ActionDto>(request).unwrap(),
+            attack
+        );
+
+        let take = serde_json::json!({"action_type": "Take", "card": null});
+        assert_eq!(serde_json::to_value(ActionDto::Take).unwrap(), take);
+        assert_eq!(
+            serde_json::from_value::<ActionDto>(take).unwrap(),
+            ActionDto::Take
+        );
+
+        let request: MakeMoveRequest = serde_json::from_value(serde_json::json!({
+            "action_type": "StopAttack",
+            "card": null,
+            "player_id": "alice",
+        }))
+        .unwrap();
+        assert_eq!(request.action, ActionDto::StopAttack);
+        assert_eq!(request.player_id.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn test_invalid_action_payloads_are_rejected() {
+        let invalid = [
+            serde_json::json!({"action_type": "Pass", "card": null}),
+            serde_json::json!({"action_type": "Attack", "card": null}),
+            serde_json::json!({"action_type": "Defend"}),
+            serde_json::json!({"action_type": "Attack", "card": {"suit": "Stars", "rank": 6}}),
+            serde_json::json!({"card": {"suit": "Hearts", "rank": 6}}),
+        ];
+        for payload in invalid {
+            assert!(
+                serde_json::from_value::<ActionDto>(payload.clone()).is_err(),
+                "{}",
+                payload
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_ready_and_version() {
+        use axum::{body::Body, http::Request};
+        use tower::ServiceExt;
+
+        let sessions = GameSessions::default();
+        let router = create_api_router(sessions);
+        for uri in ["/health", "/ready", "/version"] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_mounts_under_any_prefix() {
+        use axum::{body::Body, http::Request};
+        use tower::ServiceExt;
+
+        let sessions = GameSessions::default();
+        let router = nest_api("durak/v1/", create_api_router(sessions.clone()));
+        for (uri, status) in [
+            ("/durak/v1/health", StatusCode::OK),
+            ("/api/health", StatusCode::NOT_FOUND),
+        ] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status, "{}", uri);
+        }
+
+        let router = nest_api("/", create_api_router(sessions));
+        let request = Request::get("/health").body(Body::empty()).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_move_body_gets_structured_error() {
+        use axum::{body::Body, http::Request};
+        use tower::ServiceExt;
+
+        let (sessions, game_id, _) = two_player_session().await;
+        let router = create_api_router(sessions);
+        let request = Request::post(format!("/games/{}/move", game_id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"action": {"action_type": "Take""#))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], BAD_REQUEST_BODY);
+        assert!(body["error"].as_str().unwrap().contains("EOF"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_master_seed_makes_deals_reproducible() {
+        use axum::{body::Body, http::Request};
+        use tower::ServiceExt;
+
+        let first_deal = |master: u64| async move {
+            let state = AppState {
+                deal_seeds: Arc::new(DealSeeds::from_master(master)),
+                ..AppState::new(GameSessions::default(), RateLimitConfig::default())
+            };
+            let sessions = state.sessions.clone();
+            let router = create_api_router_with_state(state);
+            let request = Request::post("/games").body(Body::empty()).unwrap();
+            let response = router.oneshot(request).await.unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let created: CreateGameResponse = serde_json::from_slice(&body).unwrap();
+            let game_id = Uuid::parse_str(&created.game_id).unwrap();
+            let session = session_by_id(&sessions, &game_id).unwrap();
+            let session = session.read().await;
+            (session.deal_code.clone(), session.game.game_state().clone())
+        };
+        let (code, state) = first_deal(7).await;
+        assert_eq!(first_deal(7).await, (code.clone(), state));
+        assert_ne!(first_deal(8).await.0, code);
+    }
+
+    #[test]
+    fn test_observation_round_trips_through_dto() {
+        let mut game = Game::new_seeded(5);
+        for _ in 0..3 {
+            let action = game.legal_actions().0[0];
+            game.step(action).unwrap();
+        }
+        let mut observation = game.game_state().observe(GamePlayer::Player2);
+        observation.opponent_void_suits = vec![Suit::Clubs];
+
+        let dto = ObservableGameStateDto::from(observation.clone());
+        assert_eq!(ObservableGameState::try_from(dto), Ok(observation.clone()));
+
+        let mut dto = ObservableGameStateDto::from(observation);
+        dto.acting_player = "Player3".to_string();
+        assert!(ObservableGameState::try_from(dto).is_err());
+    }
+
+    #[test]
+    fn test_state_dto_flags_trumps() {
+        let mut observation = Game::new_seeded(5)
+            .game_state()
+            .observe(GamePlayer::Player1);
+        let trump = observation.visible_card.suit;
+        let off_suit = [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs]
+            .into_iter()
+            .find(|suit| *suit != trump)
+            .unwrap();
+        observation.hand = Hand(vec![
+            Card::new(trump, 6),
+            Card::new(off_suit, 14),
+            Card::new(trump, 12),
+        ]);
+        let dto = ObservableGameStateDto::from(observation);
+        let flags: Vec<bool> = dto.hand.iter().map(|card| card.is_trump).collect();
+        assert_eq!(flags, vec![true, false, true]);
+        assert!(dto.visible_card.is_trump);
+
+        let json = serde_json::to_value(&dto).unwrap();
+        assert_eq!(json["hand"][1]["is_trump"], false);
+        assert_eq!(json["visible_card"]["is_trump"], true);
+    }
+
+    #[tokio::test]
+    async fn test_hand_sizes_only_for_finished_games() {
+        let (sessions, game_id, _) = two_player_session().await;
+        let request = || get_hand_sizes(State(sessions.clone()), Path(game_id.clone()));
+        assert_eq!(request().await.err(), Some(StatusCode::CONFLICT));
+
+        let uuid = Uuid::parse_str(&game_id).unwrap();
+        let session = sessions.get(&uuid).unwrap().clone();
+        let num_actions = {
+            let game = &mut session.write().await.game;
+            while !game.is_over() {
+                let action = game.legal_actions().0[0];
+                game.step(action).unwrap();
+            }
+            game.actions.len()
+        };
+
+        let Json(response) = request().await.unwrap();
+        assert_eq!(response.player1.len(), num_actions + 1);
+        assert_eq!(response.player2.len(), num_actions + 1);
+    }
+
+    #[tokio::test]
+    async fn test_export_finished_game_as_text_and_json() {
+        let session = GameSession::new();
+        let game_id = session.id.to_string();
+        let sessions = GameSessions::default();
+        sessions.insert(session.id, Arc::new(RwLock::new(session)));
+        let request =
+            |format: &str| get_game_export(sessions.clone(), game_id.clone(), format.to_string());
+        assert_eq!(request("text").await.err(), Some(StatusCode::CONFLICT));
+
+        let uuid = Uuid::parse_str(&game_id).unwrap();
+        let session = sessions.get(&uuid).unwrap().clone();
+        let actions = {
+            let game = &mut session.write().await.game;
+            while !game.is_over() {
+                let action = game.legal_actions().0[0];
+                game.step(action).unwrap();
+            }
+            game.actions.clone()
+        };
+
+        let (headers, text) = request("text").await.unwrap();
+        assert_eq!(headers[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+        let filename = format!("attachment; filename=\"durak-{}.txt\"", game_id);
+        assert_eq!(headers[header::CONTENT_DISPOSITION], filename.as_str());
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("Trump: "));
+        assert!(text.contains(&format!("\n{}. ", actions.len())));
+        assert!(text
+            .trim_end()
+            .lines()
+            .last()
+            .unwrap()
+            .starts_with("Result: "));
+
+        let (headers, json) = request("json").await.unwrap();
+        assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+        let log: GameLog = serde_json::from_slice(&json).unwrap();
+        assert_eq!(log.actions, actions);
+        assert!(Game::validate_log(&log).unwrap().is_over());
+
+        assert_eq!(request("pgn").await.err(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    /// Call `export_game` and split a successful response into its headers and body.
+    async fn get_game_export(
+        sessions: GameSessions,
+        game_id: String,
+        format: String,
+    ) -> Result<(HeaderMap, Vec<u8>), StatusCode> {
+        let query = ExportQuery {
+            format: Some(format),
+        };
+        let response = export_game(State(sessions), Path(game_id), Query(query)).await?;
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        Ok((headers, body.to_vec()))
+    }
+
+    #[tokio::test]
+    async fn test_create_game_tunes_ai_to_player() {
+        let sessions = GameSessions::default();
+        let tuner = Arc::new(DifficultyTuner::default());
+        for _ in 0..3 {
+            tuner.record("alice", false);
+        }
+        let create = |player_id: Option<&str>| {
+            create_game(
+                State(sessions.clone()),
+                State(tuner.clone()),
+                State(Arc::new(SystemClock) as SharedClock),
+                State(Arc::new(IdempotencyKeys::default())),
+                State(Arc::new(DealSeeds::default())),
+                HeaderMap::new(),
+                Query(CreateGameQuery {
+                    player_id: player_id.map(str::to_string),
+                    deal_code: None,
+                }),
+            )
+        };
+
+        let Json(response) = create(Some("alice")).await.unwrap();
+        assert_eq!(response.ai, AiKind::Random);
+        let uuid = Uuid::parse_str(&response.game_id).unwrap();
+        let session = sessions.get(&uuid).unwrap().clone();
+        assert_eq!(session.read().await.player1_id.as_deref(), Some("alice"));
+
+        let Json(response) = create(Some("carol")).await.unwrap();
+        assert_eq!(response.ai, AiKind::Greedy);
+        let Json(response) = create(None).await.unwrap();
+        assert_eq!(response.ai, AiKind::Random);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_creates_get_distinct_games() {
+        let sessions = GameSessions::default();
+        let creates: Vec<_> = (0..200)
+            .map(|_| {
+                tokio::spawn(create_game(
+                    State(sessions.clone()),
+                    default_tuner(),
+                    State(Arc::new(SystemClock) as SharedClock),
+                    State(Arc::new(IdempotencyKeys::default())),
+                    State(Arc::new(DealSeeds::default())),
+                    HeaderMap::new(),
+                    Query(CreateGameQuery {
+                        player_id: None,
+                        deal_code: None,
+                    }),
+                ))
+            })
+            .collect();
+        let mut ids = std::collections::HashSet::new();
+        for create in creates {
+            let Json(response) = create.await.unwrap().unwrap();
+            assert!(ids.insert(response.game_id));
+        }
+        assert_eq!(sessions.len(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_win_prob_only_for_own_seat_and_rate_limited() {
+        let (sessions, game_id, _) = two_player_session().await;
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            rate_per_sec: 0.001,
+            burst: 1,
+        }));
+        let estimate = |player_id: &str, player: &str, rollouts| {
+            get_win_prob(
+                State(sessions.clone()),
+                State(limiter.clone()),
+                Path(game_id.clone()),
+                Query(WinProbQuery {
+                    player_id: Some(player_id.to_string()),
+                    player: Some(player.to_string()),
+                    rollouts: Some(rollouts),
+                }),
+            )
+        };
+
+        let Json(estimate_for_bob) = estimate("bob", "Player2", 1_000_000).await.unwrap();
+        assert_eq!(estimate_for_bob.player, "Player2");
+        assert_eq!(estimate_for_bob.rollouts, MAX_WIN_PROB_ROLLOUTS);
+        assert!(estimate_for_bob.player1 + estimate_for_bob.player2 <= 1.0 + 1e-6);
+
+        // Alice may not see the game from Bob's side
+        let forbidden = estimate("alice", "Player2", 10).await.err().unwrap();
+        assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+        let limited = estimate("bob", "Player2", 10).await.err().unwrap();
+        assert_eq!(limited.status, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_player_games_lists_joined_games() {
+        let sessions = GameSessions::default();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let session = GameSession::new();
+            ids.push(session.id.to_string());
+            sessions.insert(session.id, Arc::new(RwLock::new(session)));
+        }
+        for (game_id, player) in [(&ids[0], "Player1"), (&ids[1], "Player2")] {
+            let Json(joined) = join_game(
+                State(sessions.clone()),
+                Path(game_id.clone()),
+                ApiJson(JoinGameRequest {
+                    player_id: "carol".to_string(),
+                    player: Some(player.to_string()),
+                }),
+            )
+            .await
+            .unwrap();
+            assert_eq!(joined.player, player);
+        }
+
+        let Json(games) =
+            get_player_games(State(sessions.clone()), Path("carol".to_string())).await;
+        let mut seats: Vec<_> = games
+            .iter()
+            .map(|g| (&g.game_id, g.player.as_str()))
+            .collect();
+        seats.sort();
+        let mut expected = vec![(&ids[0], "Player1"), (&ids[1], "Player2")];
+        expected.sort();
+        assert_eq!(seats, expected);
+
+        let Json(games) = get_player_games(State(sessions), Path("dave".to_string())).await;
+        assert!(games.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_game_from_deal_code_deals_same_game() {
+        let sessions = GameSessions::default();
+        let create = |deal_code: Option<String>| {
+            create_game(
+                State(sessions.clone()),
+                default_tuner(),
+                State(Arc::new(SystemClock) as SharedClock),
+                State(Arc::new(IdempotencyKeys::default())),
+                State(Arc::new(DealSeeds::default())),
+                HeaderMap::new(),
+                Query(CreateGameQuery {
+                    player_id: None,
+                    deal_code,
+                }),
+            )
+        };
+        let initial_state = |game_id: &str| {
+            let session = sessions
+                .get(&Uuid::parse_str(game_id).unwrap())
+                .unwrap()
+                .clone();
+            async move { session.read().await.game.history[0].clone() }
+        };
+        let code_of = |game_id: &str| {
+            let session = sessions
+                .get(&Uuid::parse_str(game_id).unwrap())
+                .unwrap()
+                .clone();
+            async move { session.read().await.deal_code.clone() }
+        };
+
+        let Json(first) = create(None).await.unwrap();
+        let code = code_of(&first.game_id).await.unwrap();
+        let Json(again) = create(Some(code.clone())).await.unwrap();
+        assert_ne!(again.game_id, first.game_id);
+        assert_eq!(code_of(&again.game_id).await, Some(code));
+        assert_eq!(
+            initial_state(&again.game_id).await,
+            initial_state(&first.game_id).await
+        );
+
+        let bad = create(Some("not-a-code".to_string())).await;
+        assert_eq!(bad.err(), Some(StatusCode::BAD_REQUEST));
+
+        // A code can carry cheat mode and observation settings, but only its deal and rules
+        // are honoured
+        let config = GameConfig {
+            no_trump_lead: true,
+            reveal_opponent: true,
+            observation: ObservationConfig {
+                hand: HandEncoding::Aggregate,
+                ..ObservationConfig::default()
+            },
+            ..GameConfig::default()
+        };
+        let Json(cheat) = create(Some(deal_code(&config, 7))).await.unwrap();
+        let rules = GameConfig {
+            no_trump_lead: true,
+            ..GameConfig::default()
+        };
+        assert_eq!(code_of(&cheat.game_id).await, Some(deal_code(&rules, 7)));
+        let session = sessions
+            .get(&Uuid::parse_str(&cheat.game_id).unwrap())
+            .unwrap()
+            .clone();
+        assert_eq!(session.read().await.game.config(), &rules);
+        assert_eq!(
+            initial_state(&cheat.game_id).await,
+            Game::with_config_seeded(config, 7).unwrap().history[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deal_code_is_only_given_out_once_the_game_is_over() {
+        let sessions = GameSessions::default();
+        let Json(created) = create_game(
+            State(sessions.clone()),
+            default_tuner(),
+            State(Arc::new(SystemClock) as SharedClock),
+            State(Arc::new(IdempotencyKeys::default())),
+            State(Arc::new(DealSeeds::default())),
+            HeaderMap::new(),
+            Query(CreateGameQuery {
+                player_id: Some("alice".to_string()),
+                deal_code: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let game_id = created.game_id.clone();
+        let created = serde_json::to_value(&created).unwrap();
+        assert!(created.get("deal_code").is_none(), "{}", created);
+
+        let Json(state) = get_game_state(
+            State(sessions.clone()),
+            Path(game_id.clone()),
+            Query(SeatQuery {
+                player_id: Some("alice".to_string()),
+                player: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert!(!state.is_over);
+        let state = serde_json::to_string(&state).unwrap();
+        assert!(!state.contains("deal_code"), "{}", state);
+        let export = get_game_export(sessions.clone(), game_id.clone(), "deal_code".into());
+        assert_eq!(export.await.err(), Some(StatusCode::CONFLICT));
+
+        let session = session_by_id(&sessions, &Uuid::parse_str(&game_id).unwrap()).unwrap();
+        let code = {
+            let mut session = session.write().await;
+            while !session.game.is_over() {
+                let action = session.game.legal_actions().0[0];
+                session.game.step(action).unwrap();
+            }
+            session.deal_code.clone().unwrap()
+        };
+        let (headers, body) =
+            get_game_export(sessions.clone(), game_id.clone(), "deal_code".into())
+                .await
+                .unwrap();
+        assert_eq!(headers[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+        assert_eq!(String::from_utf8(body).unwrap(), code);
+
+        session.write().await.deal_code = None;
+        let export = get_game_export(sessions, game_id, "deal_code".into());
+        assert_eq!(export.await.err(), Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_create_game_with_same_key_returns_same_game() {
+        let sessions = GameSessions::default();
+        let keys = Arc::new(IdempotencyKeys::default());
+        let create = |key: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(IDEMPOTENCY_KEY, key.parse().unwrap());
+            create_game(
+                State(sessions.clone()),
+                default_tuner(),
+                State(Arc::new(SystemClock) as SharedClock),
+                State(keys.clone()),
+                State(Arc::new(DealSeeds::default())),
+                headers,
+                Query(CreateGameQuery {
+                    player_id: None,
+                    deal_code: None,
+                }),
+            )
+        };
+
+        let Json(first) = create("retry-1").await.unwrap();
+        let Json(retry) = create("retry-1").await.unwrap();
+        assert_eq!(retry.game_id, first.game_id);
+        assert_eq!(sessions.len(), 1);
+
+        let Json(other) = create("retry-2").await.unwrap();
+        assert_ne!(other.game_id, first.game_id);
+        assert_eq!(sessions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_move_from_wrong_player_is_rejected() {
+        let (sessions, game_id, action) = two_player_session().await;
+
+        let result = make_move(
+            State(sessions),
+            default_limiter(),
+            default_tuner(),
+            Path(game_id),
+            ApiJson(move_request(&action, "bob")),
+        )
+        .await;
+
+        assert_eq!(result.err().map(|e| e.status), Some(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn test_moves_past_burst_are_rate_limited() {
+        let (sessions, game_id, action) = two_player_session().await;
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            rate_per_sec: 0.5,
+            burst: 1,
+        }));
+
+        let first = make_move(
+            State(sessions.clone()),
+            State(limiter.clone()),
+            default_tuner(),
+            Path(game_id.clone()),
+            ApiJson(move_request(&action, "alice")),
+        )
+        .await;
+        assert!(first.is_ok());
+
+        let second = make_move(
+            State(sessions),
+            State(limiter),
+            default_tuner(),
+            Path(game_id),
+            ApiJson(move_request(&action, "alice")),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(second.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(second.retry_after, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_move_from_seat_owner_is_accepted() {
+        let (sessions, game_id, action) = two_player_session().await;
+
+        let Json(response) = make_move(
+            State(sessions),
+            default_limiter(),
+            default_tuner(),
+            Path(game_id),
+            ApiJson(move_request(&action, "alice")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.action_history.len(), 1);
+        assert_eq!(response.action_history[0].player, "Player1");
+    }
+
+    #[tokio::test]
+    async fn test_state_is_for_the_callers_seat() {
+        let (sessions, game_id, _) = two_player_session().await;
+        let state = |player_id: Option<&str>| {
+            get_game_state(
+                State(sessions.clone()),
+                Path(game_id.clone()),
+                Query(SeatQuery {
+                    player_id: player_id.map(str::to_string),
+                    player: None,
+                }),
+            )
+        };
+        let hand2 = {
+            let session = session_by_id(&sessions, &Uuid::parse_str(&game_id).unwrap()).unwrap();
+            let session = session.read().await;
+            session.game.game_state().hand2.clone()
+        };
+
+        // Bob joined as Player2 and sees his own hand, without Alice's legal actions
+        let Json(for_bob) = state(Some("bob")).await.unwrap();
+        assert_eq!(for_bob.game_state.player, "Player2");
+        let cards = for_bob.game_state.hand.iter().map(|c| Card::from(*c));
+        assert_eq!(Hand(cards.collect()), hand2);
+        assert!(for_bob.legal_actions.is_empty());
+
+        let Json(for_alice) = state(Some("alice")).await.unwrap();
+        assert_eq!(for_alice.game_state.player, "Player1");
+        assert!(!for_alice.legal_actions.is_empty());
+        assert_eq!(state(None).await.err(), Some(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn test_moves_are_replayed_or_rejected_together() {
+        let mut source = Game::new_seeded(5);
+        for _ in 0..5 {
+            let action = source.legal_actions().0[0];
+            source.step(action).unwrap();
+        }
+        let replay_session = || async {
+            let mut session = GameSession::new();
+            let game_id = session.id;
+            session.game = Game::new_seeded(5);
+            session.assign_player(GamePlayer::Player1, "alice".to_string());
+            session.assign_player(GamePlayer::Player2, "alice".to_string());
+            let sessions = GameSessions::default();
+            sessions.insert(game_id, Arc::new(RwLock::new(session)));
+            (sessions, game_id.to_string())
+        };
+        let request = |actions: &[Action]| MakeMovesRequest {
+            actions: actions.iter().copied().map(ActionDto::from).collect(),
+            player_id: Some("alice".to_string()),
+        };
+
+        let (sessions, game_id) = replay_session().await;
+        let Json(response) = make_moves(
+            State(sessions),
+            default_limiter(),
+            default_tuner(),
+            Path(game_id),
+            ApiJson(request(&source.actions)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.action_history.len(), 5);
+        let replayed: Vec<Action> = response
+            .action_history
+            .iter()
+            .map(|entry| Action::from(entry.action))
+            .collect();
+        assert_eq!(replayed, source.actions);
+
+        // The face-up trump is still in the deck, so it cannot be played
+        let mut actions = source.actions.clone();
+        actions.insert(2, Action::Attack(source.game_state().visible_card));
+        let (sessions, game_id) = replay_session().await;
+        let response = make_moves(
+            State(sessions.clone()),
+            default_limiter(),
+            default_tuner(),
+            Path(game_id.clone()),
+            ApiJson(request(&actions)),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["illegal_index"], 2);
+        let uuid = Uuid::parse_str(&game_id).unwrap();
+        let session = sessions.get(&uuid).unwrap().clone();
+        assert!(session.read().await.game.actions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_moves_for_the_ai_seat_are_rejected() {
+        let mut session = GameSession::new();
+        let game_id = session.id;
+        session.game = Game::new_seeded(5);
+        session.assign_player(GamePlayer::Player1, "alice".to_string());
+        let mut source = session.game.clone();
+        let sessions = GameSessions::default();
+        sessions.insert(game_id, Arc::new(RwLock::new(session)));
+        let game_id = game_id.to_string();
+
+        // Alice's opening turn, followed by the AI's answer
+        while source.game_state().acting_player == GamePlayer::Player1 {
+            let action = source.legal_actions().0[0];
+            source.step(action).unwrap();
+        }
+        let action = source.legal_actions().0[0];
+        source.step(action).unwrap();
+        let response = make_moves(
+            State(sessions.clone()),
+            default_limiter(),
+            default_tuner(),
+            Path(game_id.clone()),
+            ApiJson(MakeMovesRequest {
+                actions: source
+                    .actions
+                    .iter()
+                    .copied()
+                    .map(ActionDto::from)
+                    .collect(),
+                player_id: Some("alice".to_string()),
+            }),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["illegal_index"], source.actions.len() - 1);
+        let session = session_by_id(&sessions, &Uuid::parse_str(&game_id).unwrap()).unwrap();
+        assert!(session.read().await.game.actions.is_empty());
+    }
+
+    /// A two-player game where Player2 must answer 6♠ holding 7♠ and 8♥, clubs are trump.
+    async fn defense_session() -> (GameSessions, String) {
+        let mut session = GameSession::new();
+        let game_id = session.id;
+        session.assign_player(GamePlayer::Player1, "alice".to_string());
+        session.assign_player(GamePlayer::Player2, "bob".to_string());
+        {
+            let attack = Card {
+                suit: Suit::Spades,
+                rank: 6,
+            };
+            let hand2 = vec![
+                Card {
+                    suit: Suit::Spades,
+                    rank: 7,
+                },
+                Card {
+                    suit: Suit::Hearts,
+                    rank: 8,
+                },
+            ];
+            let state = session.game.game_state_mut();
+            state
+                .deck
+                .cards
+                .retain(|c| *c != attack && !hand2.contains(c));
+            state.hand2.0 = hand2;
+            state.attack_table = vec![attack];
+            state.defense_table.clear();
+            state.visible_card = Card {
+                suit: Suit::Clubs,
+                rank: 9,
+            };
+            state.defending_player = GamePlayer::Player2;
+            state.acting_player = GamePlayer::Player2;
+        }
+
+        let sessions = GameSessions::default();
+        sessions.insert(game_id, Arc::new(RwLock::new(session)));
+        (sessions, game_id.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_move_effect_for_defense() {
+        let (sessions, game_id) = defense_session().await;
+        let defense = ActionDto::from(Action::Defend(Card {
+            suit: Suit::Spades,
+            rank: 7,
+        }));
+
+        let Json(response) = make_move(
+            State(sessions),
+            default_limiter(),
+            default_tuner(),
+            Path(game_id),
+            ApiJson(move_request(&defense, "bob")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.move_effect.as_deref(), Some("defended"));
+    }
+
+    #[tokio::test]
+    async fn test_move_effect_for_take() {
+        let (sessions, game_id) = defense_session().await;
+        let take = ActionDto::from(Action::Take);
+
+        let Json(response) = make_move(
+            State(sessions),
+            default_limiter(),
+            default_tuner(),
+            Path(game_id),
+            ApiJson(move_request(&take, "bob")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.move_effect.as_deref(), Some("took"));
+    }
+
+    #[tokio::test]
+    async fn test_turn_pending_defense_for_defender() {
+        let mut session = GameSession::new();
+        let game_id = session.id;
+        {
+            let state = session.game.game_state_mut();
+            state.defending_player = GamePlayer::Player1;
+            state.acting_player = GamePlayer::Player1;
+            let card = state.hand2.0.remove(0);
+            state.attack_table.push(card);
+        }
+        let sessions = GameSessions::default();
+        sessions.insert(game_id, Arc::new(RwLock::new(session)));
+
+        let Json(turn) = get_turn(
+            State(sessions),
+            Path(game_id.to_string()),
+            Query(TurnQuery {
+                player: Some("Player1".to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(turn.pending, "defense");
+        assert_eq!(turn.role, "defender");
+        assert_eq!(turn.acting_player, "Player1");
+        assert!(turn.is_your_turn);
+    }
 }
@@ -0,0 +1,128 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::game::config::GameConfig;
+use crate::game::mcts::{MctsConfig, MctsPlayer};
+use crate::game::player::{GreedyPlayer, Player, RandomPlayer};
+
+/// The server AI's strength, weakest first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiKind {
+    #[default]
+    Random,
+    Greedy,
+    /// Monte Carlo tree search over determinizations of the hidden cards.
+    Search,
+}
+
+impl AiKind {
+    /// A fresh player of this kind for games under `game_config`.
+    pub fn player(&self, game_config: &GameConfig) -> Box<dyn Player> {
+        match self {
+            AiKind::Random => Box::new(RandomPlayer::new(None)),
+            AiKind::Greedy => Box::new(GreedyPlayer),
+            AiKind::Search => Box::new(MctsPlayer::new(
+                game_config.clone(),
+                MctsConfig {
+                    seed: thread_rng().next_u64(),
+                    ..MctsConfig::default()
+                },
+            )),
+        }
+    }
+}
+
+/// When the AI gets easier or harder, based on the human's recent win rate.
+#[derive(Clone, Copy, Debug)]
+pub struct DifficultyConfig {
+    /// How many of the latest games the win rate is taken over.
+    pub window: usize,
+    /// Below this win rate the human gets the random AI.
+    pub easy_below: f64,
+    /// Above this win rate the human gets the search AI.
+    pub hard_above: f64,
+}
+
+impl Default for DifficultyConfig {
+    fn default() -> Self {
+        Self {
+            window: 10,
+            easy_below: 0.3,
+            hard_above: 0.6,
+        }
+    }
+}
+
+/// Tracks each human's results against the AI and picks the AI for their next game.
+/// Players without any finished game get the greedy AI.
+#[derive(Default)]
+pub struct DifficultyTuner {
+    config: DifficultyConfig,
+    results: Mutex<HashMap<String, VecDeque<bool>>>,
+}
+
+impl DifficultyTuner {
+    pub fn new(config: DifficultyConfig) -> Self {
+        Self {
+            config,
+            results: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a finished game against the AI; draws count as not winning.
+    pub fn record(&self, player_id: &str, won: bool) {
+        let mut results = self.results.lock().unwrap();
+        let games = results.entry(player_id.to_string()).or_default();
+        games.push_back(won);
+        while games.len() > self.config.window {
+            games.pop_front();
+        }
+    }
+
+    /// The human's win rate over the window, if they have finished a game.
+    pub fn win_rate(&self, player_id: &str) -> Option<f64> {
+        let results = self.results.lock().unwrap();
+        let games = results.get(player_id).filter(|games| !games.is_empty())?;
+        let wins = games.iter().filter(|won| **won).count();
+        Some(wins as f64 / games.len() as f64)
+    }
+
+    /// The AI to put up against `player_id` next.
+    pub fn select(&self, player_id: &str) -> AiKind {
+        match self.win_rate(player_id) {
+            None => AiKind::Greedy,
+            Some(rate) if rate < self.config.easy_below => AiKind::Random,
+            Some(rate) if rate > self.config.hard_above => AiKind::Search,
+            Some(_) => AiKind::Greedy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_losing_streak_downgrades_and_winning_upgrades() {
+        let tuner = DifficultyTuner::default();
+        assert_eq!(tuner.select("alice"), AiKind::Greedy);
+
+        for _ in 0..3 {
+            tuner.record("alice", false);
+        }
+        assert_eq!(tuner.select("alice"), AiKind::Random);
+        // Other players are tracked separately
+        assert_eq!(tuner.select("bob"), AiKind::Greedy);
+
+        // Only the latest games count, so a winning streak wipes out the losses
+        for _ in 0..10 {
+            tuner.record("alice", true);
+        }
+        assert_eq!(tuner.win_rate("alice"), Some(1.0));
+        assert_eq!(tuner.select("alice"), AiKind::Search);
+    }
+}
@@ -1,2 +1,110 @@
+use std::time::Duration;
+
+use crate::game::game::GameLogic;
+
 // Re-export for convenience
 pub use super::{GameSession, GameSessions};
+
+/// How long sessions live without activity.
+#[derive(Clone, Copy, Debug)]
+pub struct SessionTtl {
+    /// A seat the game waits on for this long forfeits.
+    pub idle: Duration,
+    /// Finished games stay around this long so both players can see the result.
+    pub linger: Duration,
+}
+
+impl Default for SessionTtl {
+    fn default() -> Self {
+        Self {
+            idle: Duration::from_secs(10 * 60),
+            linger: Duration::from_secs(2 * 60),
+        }
+    }
+}
+
+/// Forfeit idle seats of running games and drop games that finished more than
+/// `ttl.linger` ago, going by each session's clock. Returns the number of sessions removed.
+pub async fn expire_sessions(sessions: &GameSessions, ttl: SessionTtl) -> usize {
+    // Map entries must not be held across the awaits below
+    let running: Vec<_> = sessions
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().clone()))
+        .collect();
+    let mut expired = Vec::new();
+    for (id, session) in running {
+        let mut session = session.write().await;
+        if !session.game.is_over() {
+            session.forfeit_if_idle(ttl.idle);
+        }
+        if session.game.is_over() {
+            let now = session.now();
+            let finished_at = *session.finished_at.get_or_insert(now);
+            if now.saturating_sub(finished_at) >= ttl.linger.as_secs() {
+                expired.push(id);
+            }
+        }
+    }
+    for id in expired.iter() {
+        sessions.remove(id);
+    }
+    expired.len()
+}
+
+/// Run `expire_sessions` every `period`, forever.
+pub async fn expire_sessions_every(sessions: GameSessions, ttl: SessionTtl, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        expire_sessions(&sessions, ttl).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::game::gamestate::GamePlayer;
+    use crate::server::clock::{Clock, MockClock};
+
+    #[tokio::test]
+    async fn test_idle_player1_forfeits_then_expires() {
+        let clock = Arc::new(MockClock::new(1_000));
+        let mut session = GameSession::with_clock(clock.clone());
+        let game_id = session.id;
+        session.assign_player(GamePlayer::Player1, "alice".to_string());
+        session.assign_player(GamePlayer::Player2, "bob".to_string());
+        session.game.game_state_mut().acting_player = GamePlayer::Player1;
+        session.game.game_state_mut().defending_player = GamePlayer::Player2;
+        let sessions = GameSessions::default();
+        sessions.insert(game_id, Arc::new(RwLock::new(session)));
+
+        let ttl = SessionTtl::default();
+        assert_eq!(expire_sessions(&sessions, ttl).await, 0);
+
+        clock.advance(ttl.idle - Duration::from_secs(1));
+        assert_eq!(expire_sessions(&sessions, ttl).await, 0);
+        let session = sessions.get(&game_id).unwrap().clone();
+        assert!(!session.read().await.game.is_over());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(expire_sessions(&sessions, ttl).await, 0);
+        {
+            let session = session.read().await;
+            assert!(session.game.is_over());
+            assert_eq!(session.game.get_winner(), Some(GamePlayer::Player2));
+            assert!(session.game.legal_actions().0.is_empty());
+            assert_eq!(session.finished_at, Some(clock.now()));
+        }
+
+        // The result stays visible for a while before the session goes away
+        clock.advance(ttl.linger - Duration::from_secs(1));
+        assert_eq!(expire_sessions(&sessions, ttl).await, 0);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(expire_sessions(&sessions, ttl).await, 1);
+        assert!(sessions.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+/// Remembers which game each `Idempotency-Key` created, so a retried `create_game` gets
+/// the same game back instead of an orphan. Keys are forgotten after `ttl`.
+pub struct IdempotencyKeys {
+    ttl: Duration,
+    /// The game created for each key and when, in seconds since the Unix epoch.
+    games: Mutex<HashMap<String, (Uuid, u64)>>,
+}
+
+impl Default for IdempotencyKeys {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60 * 60))
+    }
+}
+
+impl IdempotencyKeys {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            games: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The game created for `key`, unless it was created `ttl` or longer before `now`.
+    pub fn get(&self, key: &str, now: u64) -> Option<Uuid> {
+        let games = self.games.lock().unwrap();
+        games
+            .get(key)
+            .filter(|(_, created)| now.saturating_sub(*created) < self.ttl.as_secs())
+            .map(|(game_id, _)| *game_id)
+    }
+
+    /// The game for `key`: the one it created, if it has not expired and `exists` says the
+    /// game is still around, or else a new one from `create`, remembered for the key. The
+    /// keys stay locked throughout, so concurrent retries with one key get the same game.
+    pub fn get_or_create(
+        &self,
+        key: String,
+        now: u64,
+        exists: impl Fn(&Uuid) -> bool,
+        create: impl FnOnce() -> Uuid,
+    ) -> Uuid {
+        let mut games = self.games.lock().unwrap();
+        games.retain(|_, (_, created)| now.saturating_sub(*created) < self.ttl.as_secs());
+        if let Some((game_id, _)) = games.get(&key).filter(|(game_id, _)| exists(game_id)) {
+            return *game_id;
+        }
+        let game_id = create();
+        games.insert(key, (game_id, now));
+        game_id
+    }
+
+    /// Remember that `key` created `game_id` at `now`, dropping keys that have expired.
+    pub fn insert(&self, key: String, game_id: Uuid, now: u64) {
+        let mut games = self.games.lock().unwrap();
+        games.retain(|_, (_, created)| now.saturating_sub(*created) < self.ttl.as_secs());
+        games.insert(key, (game_id, now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_expire_after_ttl() {
+        let keys = IdempotencyKeys::new(Duration::from_secs(60));
+        let game_id = Uuid::new_v4();
+        keys.insert("retry-1".to_string(), game_id, 1_000);
+        assert_eq!(keys.get("retry-1", 1_059), Some(game_id));
+        assert_eq!(keys.get("retry-2", 1_059), None);
+        assert_eq!(keys.get("retry-1", 1_060), None);
+    }
+
+    #[test]
+    fn test_get_or_create_replaces_a_missing_game() {
+        let keys = IdempotencyKeys::default();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(
+            keys.get_or_create("k".to_string(), 0, |_| true, || first),
+            first
+        );
+        assert_eq!(
+            keys.get_or_create("k".to_string(), 1, |_| true, || second),
+            first
+        );
+        // The first game is gone, so the key gets a new one
+        assert_eq!(
+            keys.get_or_create("k".to_string(), 2, |_| false, || second),
+            second
+        );
+        assert_eq!(keys.get("k", 3), Some(second));
+    }
+}
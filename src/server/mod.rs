@@ -1,19 +1,32 @@
 pub mod api;
+pub mod clock;
+pub mod difficulty;
 pub mod game_session;
+pub mod idempotency;
+pub mod rate_limit;
+pub mod seeds;
 pub mod websocket;
 
-use std::collections::HashMap;
+use dashmap::DashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
 
 use crate::game::actions::Action;
 use crate::game::game::{Game, GameLogic};
-use crate::game::gamestate::{GamePlayer, ObservableGameHistory};
+use crate::game::gamestate::GamePlayer;
 use crate::game::player::Player;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::server::clock::{SharedClock, SystemClock};
+use crate::server::difficulty::AiKind;
+use std::time::Duration;
 
-pub type GameSessions = Arc<RwLock<HashMap<Uuid, Arc<RwLock<GameSession>>>>>;
+/// The running games. The map is sharded, so creating a game only locks the shard it goes
+/// into. Clone a session out of the map rather than holding an entry across an `.await`.
+pub type GameSessions = Arc<DashMap<Uuid, Arc<RwLock<GameSession>>>>;
+
+/// How many update notifications a slow websocket client may fall behind by. Each update
+/// is followed by a full state, so a client that lags further just skips to the latest.
+const UPDATE_BUFFER: usize = 64;
 
 #[derive(Clone)]
 pub struct ActionHistoryEntry {
@@ -28,80 +41,163 @@ pub struct GameSession {
     pub player1_id: Option<String>,
     pub player2_id: Option<String>,
     pub action_history: Vec<ActionHistoryEntry>,
+    /// Notified with the number of actions played whenever the game changes.
+    pub updates: broadcast::Sender<usize>,
+    /// The AI that plays Player2 until a human joins.
+    pub ai: AiKind,
+    /// Whether the human's result against the AI has been handed out, see
+    /// `take_vs_ai_result`.
+    result_taken: bool,
+    /// Timestamps action history entries and times out idle seats.
+    clock: SharedClock,
+    /// When each seat last joined or moved, indexed by `GamePlayer as usize`.
+    last_activity: [u64; 2],
+    /// When the game was first seen to be over, to keep it around briefly afterwards.
+    pub finished_at: Option<u64>,
+    /// The code that deals this game again, see `deal_code`, if the deal is known.
+    pub deal_code: Option<String>,
 }
 
 impl GameSession {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// A new session whose timestamps and idle times come from `clock`.
+    pub fn with_clock(clock: SharedClock) -> Self {
+        let now = clock.now();
         Self {
             id: Uuid::new_v4(),
             game: Game::new(),
             player1_id: None,
             player2_id: None,
             action_history: Vec::new(),
+            updates: broadcast::channel(UPDATE_BUFFER).0,
+            ai: AiKind::default(),
+            result_taken: false,
+            clock,
+            last_activity: [now; 2],
+            finished_at: None,
+            deal_code: None,
         }
     }
 
-    fn get_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+    /// The session clock's current time, in seconds since the Unix epoch.
+    pub fn now(&self) -> u64 {
+        self.clock.now()
     }
 
     pub fn record_action(&mut self, player: GamePlayer, action: Action) {
+        let timestamp = self.now();
+        self.last_activity[player as usize] = timestamp;
         self.action_history.push(ActionHistoryEntry {
             player,
             action,
-            timestamp: Self::get_timestamp(),
+            timestamp,
         });
         // Keep only last 100 actions
         if self.action_history.len() > 100 {
             self.action_history.remove(0);
         }
+        // Nobody listening is fine
+        let _ = self.updates.send(self.game.actions.len());
     }
 
     /// Process turns for a specific player until it's the other player's turn or the game is over.
-    /// Uses GameLogic trait methods to handle game state.
-    pub fn process_player_turns<F>(&mut self, player: GamePlayer, mut get_player: F) -> bool
+    /// Returns whether any move was made.
+    pub fn process_player_turns<F>(&mut self, player: GamePlayer, get_player: F) -> bool
     where
-        F: FnMut() -> Box<dyn Player>,
+        F: FnOnce() -> Box<dyn Player>,
     {
-        let mut made_move = false;
-        while self.game.game_state.acting_player == player && !self.game.is_over() {
-            // Use GameLogic methods instead of direct access
-            let actions = self.game.get_actions();
-            let current_player = self.game.game_state.acting_player;
-            let history: Vec<_> = self
-                .game
-                .history
-                .iter()
-                .map(|state| state.observe(current_player))
-                .collect();
-
-            let mut player_instance = get_player();
-            let action = player_instance.choose_action(
-                self.game.game_state.observe(current_player),
-                actions,
-                ObservableGameHistory(history),
-            );
-
-            // Use GameLogic::step instead of direct step call
-            if self.game.step(action).is_ok() {
-                // Record the action in history
-                self.record_action(current_player, action);
-                made_move = true;
-            } else {
-                // If step fails, break to avoid infinite loop
-                break;
-            }
+        let before = self.game.actions.len();
+        // An illegal choice just ends the turns early; the seat is asked again next time
+        let _ = self
+            .game
+            .play_until_turn(player.other(), get_player().as_mut());
+        let played = self.game.actions[before..].to_vec();
+        for action in played.iter() {
+            self.record_action(player, *action);
         }
-        made_move
+        !played.is_empty()
     }
 
-    /// Make AI moves if it's Player2's turn, reusing GameLogic functionality
+    /// Make AI moves if it's Player2's turn, reusing GameLogic functionality.
+    /// Does nothing once a human has joined as Player2.
     pub fn make_ai_move_if_needed(&mut self) -> bool {
-        use crate::game::player::RandomPlayer;
-        self.process_player_turns(GamePlayer::Player2, || Box::new(RandomPlayer::new(None)))
+        if !self.is_vs_ai() {
+            return false;
+        }
+        let (ai, config) = (self.ai, self.game.config().clone());
+        self.process_player_turns(GamePlayer::Player2, || ai.player(&config))
+    }
+
+    /// Once a game against the AI is over, the human's id and whether they won. Returns
+    /// `Some` only the first time, so each game is counted once.
+    pub fn take_vs_ai_result(&mut self) -> Option<(String, bool)> {
+        if self.result_taken || !self.is_vs_ai() || !self.game.is_over() {
+            return None;
+        }
+        let human = self.player1_id.clone()?;
+        self.result_taken = true;
+        Some((human, self.game.get_winner() == Some(GamePlayer::Player1)))
+    }
+
+    /// The seat the game is waiting on: the acting seat, or the human Player1 in a game
+    /// against the AI, whose moves are only made when the human asks for the state.
+    pub fn waiting_on(&self) -> GamePlayer {
+        match self.is_vs_ai() {
+            true => GamePlayer::Player1,
+            false => self.game.game_state().acting_player,
+        }
+    }
+
+    /// How long `player` has been idle.
+    pub fn idle_for(&self, player: GamePlayer) -> Duration {
+        Duration::from_secs(
+            self.now()
+                .saturating_sub(self.last_activity[player as usize]),
+        )
+    }
+
+    /// If the seat the game is waiting on has been idle for `timeout`, it forfeits and the
+    /// other player wins. Returns whether it forfeited.
+    pub fn forfeit_if_idle(&mut self, timeout: Duration) -> bool {
+        let seat = self.waiting_on();
+        if self.game.is_over() || self.idle_for(seat) < timeout {
+            return false;
+        }
+        self.game.concede(seat);
+        // Nobody listening is fine
+        let _ = self.updates.send(self.game.actions.len());
+        true
+    }
+
+    /// A game is played against the AI until someone joins as Player2.
+    pub fn is_vs_ai(&self) -> bool {
+        self.player2_id.is_none()
+    }
+
+    /// Check whether the caller identified by `player_id` may move for the acting seat.
+    pub fn can_move(&self, player_id: Option<&str>) -> bool {
+        self.holds_seat(self.game.game_state().acting_player, player_id)
+    }
+
+    /// Check whether `seat` is the caller's. A seat that has been joined is only its
+    /// owner's. In a game against the AI, an unclaimed Player1 seat is implicitly the
+    /// caller's; the AI's seat never is.
+    pub fn holds_seat(&self, seat: GamePlayer, player_id: Option<&str>) -> bool {
+        match self.get_player_id(seat) {
+            Some(owner) => player_id == Some(owner.as_str()),
+            None => seat == GamePlayer::Player1 && self.is_vs_ai(),
+        }
+    }
+
+    /// The seat whose hand the caller identified by `player_id` may see: the first seat
+    /// they hold, see `holds_seat`.
+    pub fn seat_of(&self, player_id: Option<&str>) -> Option<GamePlayer> {
+        [GamePlayer::Player1, GamePlayer::Player2]
+            .into_iter()
+            .find(|seat| self.holds_seat(*seat, player_id))
     }
 
     pub fn get_player_id(&self, player: GamePlayer) -> Option<String> {
@@ -112,23 +208,15 @@ impl GameSession {
     }
 
     pub fn assign_player(&mut self, player: GamePlayer, player_id: String) -> bool {
-        match player {
-            GamePlayer::Player1 => {
-                if self.player1_id.is_none() {
-                    self.player1_id = Some(player_id);
-                    true
-                } else {
-                    false
-                }
-            }
-            GamePlayer::Player2 => {
-                if self.player2_id.is_none() {
-                    self.player2_id = Some(player_id);
-                    true
-                } else {
-                    false
-                }
-            }
+        let seat = match player {
+            GamePlayer::Player1 => &mut self.player1_id,
+            GamePlayer::Player2 => &mut self.player2_id,
+        };
+        if seat.is_some() {
+            return false;
         }
+        *seat = Some(player_id);
+        self.last_activity[player as usize] = self.now();
+        true
     }
 }
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token bucket parameters: each client may make `burst` requests at once and then
+/// `rate_per_sec` requests per second on average.
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimitConfig {
+    pub rate_per_sec: f64,
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            rate_per_sec: 5.0,
+            burst: 10,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// In-memory token buckets keyed by client.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `key`, returning false if its bucket is empty.
+    pub fn check(&self, key: &str) -> bool {
+        self.check_at(key, Instant::now())
+    }
+
+    /// Like `check`, at a given time. Times earlier than a bucket's last update do not
+    /// refill it.
+    pub fn check_at(&self, key: &str, now: Instant) -> bool {
+        let burst = self.config.burst as f64;
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.config.rate_per_sec).min(burst);
+        bucket.updated = bucket.updated.max(now);
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// How long until `key` has a token again, for the `Retry-After` hint.
+    pub fn retry_after(&self, key: &str) -> Duration {
+        let buckets = self.buckets.lock().unwrap();
+        match buckets.get(key) {
+            Some(bucket) if bucket.tokens < 1.0 && self.config.rate_per_sec > 0.0 => {
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.config.rate_per_sec)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_rejects_past_burst_and_refills() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            rate_per_sec: 2.0,
+            burst: 3,
+        });
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at("alice", start));
+        }
+        assert!(!limiter.check_at("alice", start));
+        // Other clients have their own bucket
+        assert!(limiter.check_at("bob", start));
+
+        // Half a second at 2 tokens per second buys one more move
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at("alice", later));
+        assert!(!limiter.check_at("alice", later));
+        // Refills stop at the burst size
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.check_at("alice", much_later));
+        }
+        assert!(!limiter.check_at("alice", much_later));
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Where `create_game` gets the seed of each new deal: random by default, or derived from
+/// a master seed so that a whole run of the server, such as a test suite, deals the same
+/// games in the same order.
+#[derive(Default)]
+pub struct DealSeeds {
+    master: Option<u64>,
+    /// How many seeds have been derived from `master`.
+    dealt: AtomicU64,
+}
+
+impl DealSeeds {
+    /// Seeds derived from `master`: the n-th game created gets the n-th seed.
+    pub fn from_master(master: u64) -> Self {
+        Self {
+            master: Some(master),
+            dealt: AtomicU64::new(0),
+        }
+    }
+
+    /// The seed for the next game.
+    pub fn next(&self) -> u64 {
+        match self.master {
+            Some(master) => split(master, self.dealt.fetch_add(1, Ordering::Relaxed)),
+            None => rand::random(),
+        }
+    }
+}
+
+/// The `n`-th output of a SplitMix64 generator started at `master`, so consecutive games
+/// get unrelated seeds.
+fn split(master: u64, n: u64) -> u64 {
+    let mut z = master.wrapping_add((n + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -1,2 +1,296 @@
-// WebSocket support can be added here later if needed
-// For now, we'll use REST API with polling
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
+
+use crate::game::gamestate::GamePlayer;
+use crate::server::api::{
+    build_actions_response, build_state_response, resolve_seat, ActionsResponse, GameStateResponse,
+    SeatQuery,
+};
+use crate::server::{GameSession, GameSessions};
+
+/// Messages pushed from the server to a websocket client.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// The full current state, sent on connection and after every change.
+    StateUpdate(Box<GameStateResponse>),
+    /// The client's current legal actions, in reply to `GetActions`.
+    Actions(ActionsResponse),
+    /// A request the client may no longer make, e.g. `GetActions` after someone else has
+    /// claimed the seat.
+    Error { message: String },
+}
+
+/// Messages a websocket client can send. Moves still go through `POST /move`.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Ask for the legal actions again, e.g. after a reconnect. Changes nothing.
+    GetActions,
+}
+
+/// The reply to a text message from the client connected for `player` as `player_id`, if
+/// it is one we understand. The seat is checked again, since it may have been claimed by
+/// someone else since the client connected.
+pub async fn reply(
+    session: &RwLock<GameSession>,
+    player: GamePlayer,
+    player_id: Option<&str>,
+    text: &str,
+) -> Option<ServerMessage> {
+    match serde_json::from_str(text).ok()? {
+        ClientMessage::GetActions => {
+            let session = session.read().await;
+            Some(match session.seat_of(player_id) == Some(player) {
+                true => ServerMessage::Actions(build_actions_response(&session, player)),
+                false => ServerMessage::Error {
+                    message: "This seat is not yours".to_string(),
+                },
+            })
+        }
+    }
+}
+
+/// The state update to push to the client connected for `player` as `player_id`, or
+/// `None` if the seat is no longer theirs, e.g. because someone has since joined the
+/// unclaimed seat an anonymous client was watching.
+pub async fn state_update(
+    session: &RwLock<GameSession>,
+    game_id: String,
+    player: GamePlayer,
+    player_id: Option<&str>,
+) -> Option<ServerMessage> {
+    let session = session.read().await;
+    (session.seat_of(player_id) == Some(player)).then(|| {
+        ServerMessage::StateUpdate(Box::new(build_state_response(game_id, &session, player)))
+    })
+}
+
+/// Subscribe to a session's updates and take a snapshot of its current state. Both happen
+/// under the same lock, so no update can slip in between the snapshot and the subscription.
+pub async fn subscribe(
+    session: &RwLock<GameSession>,
+    game_id: String,
+    player: GamePlayer,
+) -> (broadcast::Receiver<usize>, ServerMessage) {
+    let session = session.read().await;
+    let updates = session.updates.subscribe();
+    let snapshot =
+        ServerMessage::StateUpdate(Box::new(build_state_response(game_id, &session, player)));
+    (updates, snapshot)
+}
+
+/// Stream state updates for a game over a websocket, from the point of view of the
+/// caller's seat (`?player_id=...`), see `resolve_seat`. Asking for another seat with
+/// `?player=Player1|Player2` is refused with 403.
+pub async fn game_ws(
+    ws: WebSocketUpgrade,
+    State(sessions): State<GameSessions>,
+    Path(game_id): Path<String>,
+    Query(query): Query<SeatQuery>,
+) -> Result<Response, StatusCode> {
+    let uuid = Uuid::parse_str(&game_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let session = sessions
+        .get(&uuid)
+        .map(|session| session.clone())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let player = resolve_seat(&*session.read().await, &query)?;
+
+    Ok(ws
+        .on_upgrade(move |socket| handle_socket(socket, session, game_id, player, query.player_id)))
+}
+
+async fn handle_socket(
+    mut socket: WebSocket,
+    session: Arc<RwLock<GameSession>>,
+    game_id: String,
+    player: GamePlayer,
+    player_id: Option<String>,
+) {
+    // A (re)connecting client gets the full state straight away
+    let (mut updates, snapshot) = subscribe(&session, game_id.clone(), player).await;
+    if send(&mut socket, &snapshot).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                // Every message carries the full state, so a lagged client only needs the latest one
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let update = state_update(&session, game_id.clone(), player, player_id.as_deref()).await;
+                    let Some(message) = update else {
+                        // The seat was claimed by someone else; tell the client and hang up
+                        let message = ServerMessage::Error {
+                            message: "This seat is not yours".to_string(),
+                        };
+                        let _ = send(&mut socket, &message).await;
+                        let _ = socket.send(Message::Close(None)).await;
+                        return;
+                    };
+                    if send(&mut socket, &message).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(Message::Text(text))) => {
+                    if let Some(message) = reply(&session, player, player_id.as_deref(), &text).await {
+                        if send(&mut socket, &message).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &ServerMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).unwrap();
+    socket.send(Message::Text(text)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Json;
+
+    use crate::game::game::GameLogic;
+    use crate::server::api::get_actions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_late_subscriber_receives_current_state() {
+        let mut session = GameSession::new();
+        let game_id = session.id.to_string();
+        for _ in 0..3 {
+            let player = session.game.game_state().acting_player;
+            let action = session.game.legal_actions().0[0];
+            session.game.step(action).unwrap();
+            session.record_action(player, action);
+        }
+        let session = RwLock::new(session);
+
+        let (mut updates, snapshot) = subscribe(&session, game_id, GamePlayer::Player1).await;
+        let ServerMessage::StateUpdate(state) = snapshot else {
+            panic!("expected a state update");
+        };
+        assert_eq!(state.action_history.len(), 3);
+        assert!(updates.try_recv().is_err());
+
+        // Later changes are still delivered after the snapshot
+        let mut session = session.write().await;
+        let player = session.game.game_state().acting_player;
+        let action = session.game.legal_actions().0[0];
+        session.game.step(action).unwrap();
+        session.record_action(player, action);
+        assert_eq!(updates.try_recv().unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_updates_stop_once_the_seat_is_claimed() {
+        let session = GameSession::new();
+        let game_id = session.id.to_string();
+        let session = RwLock::new(session);
+
+        // An anonymous client watches the unclaimed Player1 seat against the AI
+        let update = state_update(&session, game_id.clone(), GamePlayer::Player1, None).await;
+        assert!(matches!(update, Some(ServerMessage::StateUpdate(_))));
+
+        session
+            .write()
+            .await
+            .assign_player(GamePlayer::Player1, "alice".to_string());
+        let update = state_update(&session, game_id.clone(), GamePlayer::Player1, None).await;
+        assert!(update.is_none());
+        let update = state_update(&session, game_id, GamePlayer::Player1, Some("alice")).await;
+        assert!(matches!(update, Some(ServerMessage::StateUpdate(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_actions_matches_rest_endpoint() {
+        let mut session = GameSession::new();
+        let game_id = session.id;
+        session.assign_player(GamePlayer::Player1, "alice".to_string());
+        session.assign_player(GamePlayer::Player2, "bob".to_string());
+        let acting = session.game.game_state().acting_player;
+        let sessions = GameSessions::default();
+        sessions.insert(game_id, Arc::new(RwLock::new(session)));
+        let session = sessions.get(&game_id).unwrap().clone();
+        let rest = |player_id: &str, player: Option<GamePlayer>| {
+            get_actions(
+                State(sessions.clone()),
+                Path(game_id.to_string()),
+                Query(SeatQuery {
+                    player_id: Some(player_id.to_string()),
+                    player: player.map(|p| format!("{:?}", p)),
+                }),
+            )
+        };
+
+        for (player_id, player) in [("alice", GamePlayer::Player1), ("bob", GamePlayer::Player2)] {
+            let Some(ServerMessage::Actions(over_ws)) = reply(
+                &session,
+                player,
+                Some(player_id),
+                r#"{"type": "get_actions"}"#,
+            )
+            .await
+            else {
+                panic!("expected an actions reply");
+            };
+            let Json(over_rest) = rest(player_id, None).await.unwrap();
+            assert_eq!(over_ws.legal_actions, over_rest.legal_actions);
+            assert_eq!(over_ws.legal_actions.is_empty(), player != acting);
+        }
+        assert!(reply(&session, acting, Some("alice"), "hello")
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_actions_only_for_own_seat() {
+        let mut session = GameSession::new();
+        let game_id = session.id;
+        session.assign_player(GamePlayer::Player1, "alice".to_string());
+        session.assign_player(GamePlayer::Player2, "bob".to_string());
+        let sessions = GameSessions::default();
+        sessions.insert(game_id, Arc::new(RwLock::new(session)));
+        let session = sessions.get(&game_id).unwrap().clone();
+
+        for (player_id, player) in [(Some("alice"), "Player2"), (None, "Player1")] {
+            let over_rest = get_actions(
+                State(sessions.clone()),
+                Path(game_id.to_string()),
+                Query(SeatQuery {
+                    player_id: player_id.map(str::to_string),
+                    player: Some(player.to_string()),
+                }),
+            )
+            .await;
+            assert_eq!(over_rest.err(), Some(StatusCode::FORBIDDEN));
+        }
+        let over_ws = reply(
+            &session,
+            GamePlayer::Player2,
+            Some("alice"),
+            r#"{"type": "get_actions"}"#,
+        )
+        .await;
+        assert!(matches!(over_ws, Some(ServerMessage::Error { .. })));
+    }
+}
@@ -0,0 +1,50 @@
+//! Replays every game log in `tests/fixtures` through `Game::validate_log`.
+//!
+//! Each fixture is a serialized `GameLog` that once triggered a bug (a panic or an
+//! illegal state). Drop new logs into the directory to grow the regression corpus.
+
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use durak_rt::game::actions::Action;
+use durak_rt::game::game::{Game, GameLog, GameLogic};
+
+fn fixture_paths() -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_replay_regression_fixtures() {
+    let paths = fixture_paths();
+    assert!(!paths.is_empty(), "no fixture logs found");
+    for path in paths {
+        let reader = BufReader::new(File::open(&path).unwrap());
+        let log: GameLog = serde_json::from_reader(reader).unwrap();
+        let game = Game::validate_log(&log)
+            .unwrap_or_else(|e| panic!("{} failed to replay: {}", path.display(), e));
+        assert_eq!(game.actions.len(), log.actions.len());
+        assert!(game.game_state().validate().is_ok());
+    }
+}
+
+#[test]
+fn test_underflow_fixture_takes_over_defended_cards() {
+    // The seed fixture must keep reaching a take with beaten attacks on the table, where
+    // the undefended count once underflowed
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/undefended_count_underflow.json");
+    let log: GameLog = serde_json::from_reader(BufReader::new(File::open(path).unwrap())).unwrap();
+    let game = Game::validate_log(&log).unwrap();
+    assert!(game.is_over());
+    assert!(game
+        .history
+        .iter()
+        .zip(game.actions.iter())
+        .any(|(state, action)| *action == Action::Take && !state.defense_table.is_empty()));
+}
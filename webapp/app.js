@@ -56,6 +56,7 @@ function renderGameState(state) {
   // Update opponent info
   document.getElementById('opponentCards').textContent = state.game_state.cards_in_opponent;
   document.getElementById('deckSize').textContent = state.game_state.num_cards_in_deck;
+  document.getElementById('boutNumber').textContent = state.game_state.bout + 1;
 
   // Update trump card
   const trumpCardEl = document.getElementById('trumpCard');
@@ -122,7 +123,7 @@ function renderGameState(state) {
     cardEl.className = `card ${card.suit.toLowerCase()}`;
 
     // Add visual indicator for trump suit cards
-    if (card.suit === trumpSuit) {
+    if (card.is_trump) {
       cardEl.classList.add('trump-card');
     }
 
@@ -195,14 +196,8 @@ function renderCard(card) {
     'Clubs': '♣'
   };
 
-  const rankDisplay = card.rank === 11 ? 'J' :
-    card.rank === 12 ? 'Q' :
-      card.rank === 13 ? 'K' :
-        card.rank === 14 ? 'A' :
-          card.rank.toString();
-
   return `
-        <div class="card-rank">${rankDisplay}</div>
+        <div class="card-rank">${card.rank_name}</div>
         <div class="card-suit">${suitSymbols[card.suit]}</div>
     `;
 }